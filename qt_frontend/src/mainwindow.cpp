@@ -1,5 +1,6 @@
 #include "mainwindow.h"
 #include "plotwidget.h"
+#include "fruit_classifier.h"
 #include <QVBoxLayout>
 #include <QHBoxLayout>
 #include <QPushButton>
@@ -17,32 +18,18 @@
 #include <QFrame>
 #include <QFileDialog>
 
-#ifdef _WIN32
-    #define RUST_IMPORT __declspec(dllimport)
-#else
-    #define RUST_IMPORT
-#endif
-
-extern "C" {
-    RUST_IMPORT bool train_network(
-        const char* dataset_path,
-        double** accuracies,
-        double** losses,
-        double* final_accuracy,
-        size_t* length,
-        size_t epochs
-    );
-    RUST_IMPORT char* predict(double weight, double size, double width, double height);
-    RUST_IMPORT void free_array(double* ptr);
-    RUST_IMPORT void free_string(char* ptr);
+static QString lastRustError()
+{
+    const char *message = fc_last_error_message();
+    return message ? QString::fromUtf8(message) : QString("Unknown error");
 }
 
 MainWindow::MainWindow(QWidget *parent) : QMainWindow(parent),
-    currentEpoch(0), totalEpochs(5000), finalAccuracy(0)
+    trainingJob(nullptr), model(nullptr), currentEpoch(0), totalEpochs(5000), finalAccuracy(0)
 {
     setupUI();
     trainingTimer = new QTimer(this);
-    connect(trainingTimer, &QTimer::timeout, this, &MainWindow::updateTrainingProgress);
+    connect(trainingTimer, &QTimer::timeout, this, &MainWindow::pollTraining);
 }
 
 void MainWindow::setupUI()
@@ -111,7 +98,23 @@ void MainWindow::setupUI()
                               "}"
                               "QPushButton:hover { background-color: #45a049; }"
                               "QPushButton:disabled { background-color: #cccccc; }");
-    trainingLayout->addWidget(trainButton);
+    stopButton = new QPushButton("Stop Training");
+    stopButton->setEnabled(false);
+    stopButton->setStyleSheet("QPushButton {"
+                              "background-color: #f44336;"
+                              "border: none;"
+                              "color: white;"
+                              "padding: 10px;"
+                              "font-weight: bold;"
+                              "border-radius: 5px;"
+                              "}"
+                              "QPushButton:hover { background-color: #da190b; }"
+                              "QPushButton:disabled { background-color: #cccccc; }");
+
+    QHBoxLayout *trainButtonLayout = new QHBoxLayout();
+    trainButtonLayout->addWidget(trainButton);
+    trainButtonLayout->addWidget(stopButton);
+    trainingLayout->addLayout(trainButtonLayout);
 
     progressBar = new QProgressBar();
     progressBar->setRange(0, totalEpochs);
@@ -205,6 +208,7 @@ void MainWindow::setupUI()
 
     connect(selectDatasetButton, &QPushButton::clicked, this, &MainWindow::selectDataset);
     connect(trainButton, &QPushButton::clicked, this, &MainWindow::startTraining);
+    connect(stopButton, &QPushButton::clicked, this, &MainWindow::stopTraining);
     connect(predictButton, &QPushButton::clicked, this, &MainWindow::predictFruit);
 }
 
@@ -240,69 +244,75 @@ void MainWindow::startTraining()
         return;
     }
 
-    // Call Rust training function
-    double* rustAccuracies = nullptr;
-    double* rustLosses = nullptr;
-    double rustFinalAccuracy = 0.0;
-    size_t dataLength = 0;
-    
-    bool success = train_network(
-        datasetPath.toUtf8().constData(),
-        &rustAccuracies,
-        &rustLosses,
-        &rustFinalAccuracy,
-        &dataLength,
-        static_cast<size_t>(totalEpochs)
-    );
-
-    if (!success) {
-        QMessageBox::critical(this, "Error", "Training failed");
+    // Training runs on a Rust-owned thread; progress is polled by the timer
+    trainingJob = fc_train_start(datasetPath.toUtf8().constData(), static_cast<size_t>(totalEpochs));
+    if (!trainingJob) {
+        QMessageBox::critical(this, "Error", "Training failed:\n" + lastRustError());
         trainButton->setEnabled(true);
         return;
     }
 
-    // Copy data from Rust
-    accuracies.resize(dataLength);
-    losses.resize(dataLength);
-    for (size_t i = 0; i < dataLength; ++i) {
-        accuracies[i] = rustAccuracies[i];
-        losses[i] = rustLosses[i];
-    }
-
-    finalAccuracy = rustFinalAccuracy;
-    
-    // Free Rust-allocated memory
-    free_array(rustAccuracies);
-    free_array(rustLosses);
+    stopButton->setEnabled(true);
+    trainingTimer->start(50);
+}
 
-    // Start timer for animation
-    trainingTimer->start(10); // Faster update for smoother animation
+void MainWindow::stopTraining()
+{
+    if (trainingJob) {
+        fc_train_cancel(trainingJob);
+    }
+    stopButton->setEnabled(false);
 }
 
-void MainWindow::updateTrainingProgress()
+void MainWindow::pollTraining()
 {
-    if (currentEpoch >= accuracies.size()) {
-        trainingTimer->stop();
-        trainButton->setEnabled(true);
-        accuracyLabel->setText(QString("Final Accuracy: %1%").arg(finalAccuracy * 100, 0, 'f', 2));
+    FcTrainProgress progress;
+    FcTrainStatus status = fc_train_poll(trainingJob, &progress);
+
+    if (status != FC_TRAIN_STATUS_ERROR && static_cast<int>(progress.epoch) > currentEpoch) {
+        currentEpoch = static_cast<int>(progress.epoch);
+
+        // Several epochs may finish between two polls, so plot the full history
+        FcF64Array epochAccuracies = {nullptr, 0};
+        FcF64Array epochLosses = {nullptr, 0};
+        if (fc_train_history(trainingJob, &epochAccuracies, &epochLosses)) {
+            accuracies = QVector<double>(epochAccuracies.ptr, epochAccuracies.ptr + epochAccuracies.len);
+            losses = QVector<double>(epochLosses.ptr, epochLosses.ptr + epochLosses.len);
+            fc_f64_array_free(&epochAccuracies);
+            fc_f64_array_free(&epochLosses);
+        }
+
+        progressBar->setValue(currentEpoch);
+        epochLabel->setText(QString("Epoch: %1/%2").arg(currentEpoch).arg(totalEpochs));
+        accuracyLabel->setText(QString("Current Accuracy: %1% (validation %2%)")
+                               .arg(progress.train_accuracy * 100, 0, 'f', 2)
+                               .arg(progress.val_accuracy * 100, 0, 'f', 2));
+
+        accuracyPlot->setData(accuracies);
+        lossPlot->setData(losses);
+    }
+
+    if (status == FC_TRAIN_STATUS_RUNNING) {
         return;
     }
 
-    progressBar->setValue(currentEpoch + 1);
-    epochLabel->setText(QString("Epoch: %1/%2").arg(currentEpoch + 1).arg(totalEpochs));
-    
-    // Update accuracy label
-    double currentAccuracy = accuracies[currentEpoch];
-    accuracyLabel->setText(QString("Current Accuracy: %1%").arg(currentAccuracy * 100, 0, 'f', 2));
-    
-    // Update charts with data up to current epoch
-    QVector<double> partialAccuracies(accuracies.begin(), accuracies.begin() + currentEpoch + 1);
-    QVector<double> partialLosses(losses.begin(), losses.begin() + currentEpoch + 1);
-    
-    accuracyPlot->setData(partialAccuracies);
-    lossPlot->setData(partialLosses);
-    
-    currentEpoch++;
+    trainingTimer->stop();
+    stopButton->setEnabled(false);
+    trainButton->setEnabled(true);
+
+    FcModel *trained = fc_train_join(trainingJob);
+    trainingJob = nullptr;
+    if (!trained) {
+        QMessageBox::critical(this, "Error", "Training failed:\n" + lastRustError());
+        return;
+    }
+
+    if (model) {
+        fc_model_free(model);
+    }
+    model = trained;
+    finalAccuracy = fc_model_final_accuracy(model);
+    accuracyLabel->setText(QString("Final Accuracy: %1%").arg(finalAccuracy * 100, 0, 'f', 2));
 }
 
 void MainWindow::predictFruit()
@@ -336,7 +346,14 @@ void MainWindow::predictFruit()
         return;
     }
 
-    char* result = predict(weight, size, width, height);
+    // Use the trained network when available, the rule-based fallback otherwise
+    char* result = model ? fc_model_predict(model, weight, size, width, height)
+                         : predict(weight, size, width, height);
+    if (!result) {
+        predictionLabel->setText("Prediction failed: " + lastRustError());
+        predictionLabel->setStyleSheet("color: #d32f2f;");
+        return;
+    }
     QString prediction = QString::fromUtf8(result);
     free_string(result);
     
@@ -353,5 +370,12 @@ void MainWindow::predictFruit()
 
 MainWindow::~MainWindow()
 {
+    if (trainingJob) {
+        fc_train_cancel(trainingJob);
+        fc_model_free(fc_train_join(trainingJob));
+    }
+    if (model) {
+        fc_model_free(model);
+    }
     delete trainingTimer;
 }
\ No newline at end of file
@@ -12,6 +12,8 @@ class QVBoxLayout;
 class QHBoxLayout;
 class PlotWidget;
 class QProgressBar;
+struct FcTrainJob;
+struct FcModel;
 
 class MainWindow : public QMainWindow
 {
@@ -24,7 +26,8 @@ public:
 private slots:
     void selectDataset();
     void startTraining();
-    void updateTrainingProgress();
+    void stopTraining();
+    void pollTraining();
     void predictFruit();
 
 private:
@@ -40,6 +43,7 @@ private:
     QLabel *epochLabel;
     QLabel *datasetLabel;
     QPushButton *trainButton;
+    QPushButton *stopButton;
     QPushButton *predictButton;
     QPushButton *selectDatasetButton;
     QProgressBar *progressBar;
@@ -47,6 +51,8 @@ private:
     QLineEdit *epochInput;
     
     QTimer *trainingTimer;
+    FcTrainJob *trainingJob;
+    FcModel *model;
     int currentEpoch;
     int totalEpochs;
     QVector<double> accuracies;
@@ -0,0 +1,101 @@
+// lib.rs
+//! Inference for the fruit classifier without `std` or an allocator: the
+//! scaler, both dense layers, ReLU and softmax on fixed-size arrays, so a
+//! trained network fits in a `static` on a microcontroller. Training and the
+//! batch (ndarray) forward pass stay in the `fruit_classifier` crate, which
+//! converts its models with `TrainingResult::to_core`.
+#![no_std]
+
+/// Weight, size, width and height, in that order.
+pub const FEATURES: usize = 4;
+
+/// Standardization with the training mean and standard deviation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Scaler {
+    pub mean: [f64; FEATURES],
+    pub std: [f64; FEATURES],
+}
+
+impl Scaler {
+    pub fn transform(&self, input: &[f64; FEATURES]) -> [f64; FEATURES] {
+        let mut normalized = [0.0; FEATURES];
+        for (((x, value), mean), std) in normalized.iter_mut().zip(input).zip(&self.mean).zip(&self.std) {
+            *x = (value - mean) / std;
+        }
+        normalized
+    }
+}
+
+/// A dense layer computing `x.dot(weights) + bias` with `I` inputs and `O`
+/// outputs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Dense<const I: usize, const O: usize> {
+    pub weights: [[f64; O]; I],
+    pub bias: [f64; O],
+}
+
+impl<const I: usize, const O: usize> Dense<I, O> {
+    pub fn forward(&self, x: &[f64; I]) -> [f64; O] {
+        let mut output = self.bias;
+        for (value, row) in x.iter().zip(&self.weights) {
+            for (out, weight) in output.iter_mut().zip(row) {
+                *out += value * weight;
+            }
+        }
+        output
+    }
+}
+
+pub fn relu(x: &mut [f64]) {
+    for value in x.iter_mut() {
+        if *value <= 0.0 {
+            *value = 0.0;
+        }
+    }
+}
+
+/// Softmax in place, shifted by the maximum for numerical stability.
+pub fn softmax(x: &mut [f64]) {
+    let max = x.iter().fold(f64::NEG_INFINITY, |max, &v| max.max(v));
+    let mut sum = 0.0;
+    for value in x.iter_mut() {
+        *value = libm::exp(*value - max);
+        sum += *value;
+    }
+    for value in x.iter_mut() {
+        *value /= sum;
+    }
+}
+
+/// A trained network with `H` hidden units and `C` classes, batch
+/// normalization already folded into `hidden`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Network<const H: usize, const C: usize> {
+    pub scaler: Scaler,
+    pub hidden: Dense<FEATURES, H>,
+    pub output: Dense<H, C>,
+}
+
+impl<const H: usize, const C: usize> Network<H, C> {
+    /// Class probabilities for raw measurements.
+    pub fn forward(&self, input: &[f64; FEATURES]) -> [f64; C] {
+        let mut hidden = self.hidden.forward(&self.scaler.transform(input));
+        relu(&mut hidden);
+        let mut output = self.output.forward(&hidden);
+        softmax(&mut output);
+        output
+    }
+
+    /// The most likely class and its probability. Callers decide below which
+    /// probability to treat the fruit as unknown.
+    pub fn classify(&self, input: &[f64; FEATURES]) -> (usize, f64) {
+        let probabilities = self.forward(input);
+        let mut best = 0;
+        for (class, &p) in probabilities.iter().enumerate().skip(1) {
+            if p > probabilities[best] {
+                best = class;
+            }
+        }
+        (best, probabilities[best])
+    }
+}
@@ -0,0 +1,43 @@
+// network.rs
+//
+// The no_std kernels on a hand-sized network held in a `static`, as firmware
+// would store it.
+
+use fruit_core::{softmax, Dense, Network, Scaler};
+
+static NETWORK: Network<2, 2> = Network {
+    scaler: Scaler {
+        mean: [100.0, 5.0, 5.0, 5.0],
+        std: [50.0, 1.0, 1.0, 1.0],
+    },
+    hidden: Dense {
+        weights: [[1.0, -1.0], [0.0, 0.0], [0.0, 0.0], [0.0, 0.0]],
+        bias: [0.0, 0.0],
+    },
+    output: Dense {
+        weights: [[2.0, 0.0], [0.0, 2.0]],
+        bias: [0.0, 0.5],
+    },
+};
+
+#[test]
+fn forward_scales_applies_relu_and_softmax() {
+    // Normalized weight 2: hidden (2, 0) after the ReLU, logits (4, 0.5)
+    let expected = 1.0 / (1.0 + (-3.5f64).exp());
+    let probabilities = NETWORK.forward(&[200.0, 7.0, 1.0, 5.0]);
+    assert!((probabilities[0] - expected).abs() < 1e-15);
+    assert!((probabilities[0] + probabilities[1] - 1.0).abs() < 1e-15);
+    assert_eq!(NETWORK.classify(&[200.0, 7.0, 1.0, 5.0]).0, 0);
+
+    // Normalized weight -1: hidden (0, 1), logits (0, 2.5)
+    let (class, probability) = NETWORK.classify(&[50.0, 0.0, 0.0, 0.0]);
+    assert_eq!(class, 1);
+    assert!((probability - 1.0 / (1.0 + (-2.5f64).exp())).abs() < 1e-15);
+}
+
+#[test]
+fn softmax_is_stable_for_large_logits() {
+    let mut logits = [1000.0, 1000.0, -1000.0];
+    softmax(&mut logits);
+    assert_eq!(logits, [0.5, 0.5, 0.0]);
+}
@@ -0,0 +1,347 @@
+/* Fruit classifier C API. Generated by cbindgen from rust_backend/src, do not edit. */
+
+#ifndef FRUIT_CLASSIFIER_H
+#define FRUIT_CLASSIFIER_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+#define KNearestNeighbors_DEFAULT_K 5
+
+// Added to every variance, relative to the largest feature variance,
+// so constant features don't divide by zero
+#define GaussianNaiveBayes_VAR_SMOOTHING 1e-9
+
+// Largest `schedule.epochs` a config may ask for.
+#define MAX_EPOCHS 1000000
+
+// Steps of the integrated-gradients path integral.
+#define INTEGRATED_GRADIENTS_STEPS 50
+
+// Predictions below this softmax probability are reported as "unknown".
+#define UNKNOWN_CONFIDENCE_THRESHOLD 0.5
+
+// Version of the JSON layout written by `TrainingResult::save`.
+#define MODEL_FORMAT_VERSION 1
+
+// Category of the most recent failure, see `fc_last_error_code`.
+typedef enum FcErrorCode {
+  FC_ERROR_CODE_NONE = 0,
+  FC_ERROR_CODE_NULL_POINTER = 1,
+  FC_ERROR_CODE_INVALID_UTF8 = 2,
+  FC_ERROR_CODE_DATASET = 3,
+  FC_ERROR_CODE_TRAINING = 4,
+  FC_ERROR_CODE_PANIC = 5,
+  FC_ERROR_CODE_IO = 6,
+  FC_ERROR_CODE_CONFIG = 7,
+  FC_ERROR_CODE_INVALID_ARGUMENT = 8,
+} FcErrorCode;
+
+// State of a background training job, returned by `fc_train_poll`.
+typedef enum FcTrainStatus {
+  FC_TRAIN_STATUS_RUNNING = 0,
+  // The thread is done; `fc_train_join` returns the model or the error
+  FC_TRAIN_STATUS_FINISHED = 1,
+  // The poll itself failed, see `fc_last_error_code`
+  FC_TRAIN_STATUS_ERROR = 2,
+} FcTrainStatus;
+
+// Attribution method for `fc_model_explain`.
+typedef enum FcExplainMethod {
+  FC_EXPLAIN_METHOD_GRADIENT_TIMES_INPUT = 0,
+  FC_EXPLAIN_METHOD_INTEGRATED_GRADIENTS = 1,
+  FC_EXPLAIN_METHOD_KERNEL_SHAP = 2,
+} FcExplainMethod;
+
+// Floating-point type a model computes in, see `fc_model_precision` and
+// `fc_model_convert`.
+typedef enum FcPrecision {
+  FC_PRECISION_F64 = 0,
+  FC_PRECISION_F32 = 1,
+} FcPrecision;
+
+// Opaque handle to a trained model returned by `fc_train_join` or
+// `fc_model_load`.
+typedef struct FcModel FcModel;
+
+// Opaque handle to a training run started with `fc_train_start`.
+typedef struct FcTrainJob FcTrainJob;
+
+// Array of doubles allocated by the library. Release it with
+// `fc_f64_array_free`, which needs both the pointer and the length.
+typedef struct FcF64Array {
+  double *ptr;
+  size_t len;
+} FcF64Array;
+
+// Per-epoch metrics filled in by `train_network`. Release it with
+// `free_training_result`.
+typedef struct TrainingResult {
+  struct FcF64Array accuracies;
+  struct FcF64Array losses;
+  double final_accuracy;
+} TrainingResult;
+
+// Called after every epoch with the 1-based epoch number, the training loss,
+// the training accuracy and the validation accuracy. Return `false` to stop
+// training early; the metrics gathered so far are still returned.
+typedef bool (*EpochCallback)(void *user_data,
+                              size_t epoch,
+                              double loss,
+                              double train_accuracy,
+                              double val_accuracy);
+
+// Snapshot of a background training job, filled in by `fc_train_poll`.
+typedef struct FcTrainProgress {
+  size_t epoch;
+  size_t total_epochs;
+  double loss;
+  double train_accuracy;
+  double val_accuracy;
+  bool finished;
+} FcTrainProgress;
+
+// A prediction with the contribution of each measurement, filled in by
+// `fc_model_explain`. Release it with `fc_explanation_free`.
+typedef struct FcExplanation {
+  // Predicted class, or "unknown" when the model is unsure
+  char *label;
+  double confidence;
+  // Probability of the predicted class at the baseline input
+  double base_value;
+  // Contributions of weight, size, width and height
+  double attributions[4];
+} FcExplanation;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Code of the most recent failed call on this thread, or `None` if no call
+// has failed yet.
+enum FcErrorCode fc_last_error_code(void);
+
+// Human-readable description of the most recent failed call on this thread,
+// or null if no call has failed yet. The string is owned by the library and
+// stays valid until the next failing call on the same thread.
+const char *fc_last_error_message(void);
+
+// Trains a network on the CSV at `dataset_path` and stores the per-epoch
+// accuracy and loss arrays in `result`, which must be released with
+// `free_training_result`. `epochs` must be between 1 and `MAX_EPOCHS`.
+// Returns `false` on failure, see `fc_last_error_message`.
+//
+// # Safety
+//
+// `dataset_path` must be null or a valid NUL-terminated string and `result`
+// must be null or valid for writes.
+bool train_network(const char *dataset_path, size_t epochs, struct TrainingResult *result);
+
+// Same as `train_network`, but invokes `callback` with `user_data` after
+// every epoch so the caller can display live progress or stop early.
+//
+// # Safety
+//
+// Same requirements as `train_network`. `callback` is called on the calling
+// thread and must be safe to invoke with `user_data`.
+bool train_network_with_callback(const char *dataset_path,
+                                 size_t epochs,
+                                 EpochCallback callback,
+                                 void *user_data,
+                                 struct TrainingResult *result);
+
+// Starts training on a Rust-owned thread and returns immediately. The job
+// must eventually be released with `fc_train_join`. Returns null on failure,
+// e.g. for `epochs` outside 1 to `MAX_EPOCHS`, see `fc_last_error_message`;
+// dataset errors are reported by the join.
+//
+// # Safety
+//
+// `dataset_path` must be null or a valid NUL-terminated string.
+struct FcTrainJob *fc_train_start(const char *dataset_path, size_t epochs);
+
+// Like `fc_train_start`, but takes every setting from a TOML or JSON
+// training config. Invalid configs are rejected here, before a thread is
+// started.
+//
+// # Safety
+//
+// `config_path` must be null or a valid NUL-terminated string.
+struct FcTrainJob *fc_train_start_with_config(const char *config_path);
+
+// Copies the latest progress of `job` into `progress` and reports whether
+// the training thread is still running. Safe to call from any thread.
+// Returns `FcTrainStatus::Error` without touching `progress` if either
+// pointer is null.
+//
+// # Safety
+//
+// `job` must be null or a live handle from `fc_train_start` and `progress`
+// must be null or valid for writes.
+enum FcTrainStatus fc_train_poll(const struct FcTrainJob *job, struct FcTrainProgress *progress);
+
+// Copies the per-epoch training accuracy and loss of every epoch `job` has
+// finished so far into `accuracies` and `losses`, which must be released
+// with `fc_f64_array_free`. Their previous contents are overwritten, not
+// freed. Safe to call from any thread. Returns `false` on failure, see
+// `fc_last_error_message`.
+//
+// # Safety
+//
+// `job` must be null or a live handle from `fc_train_start`, and
+// `accuracies` and `losses` must be null or valid for writes.
+bool fc_train_history(const struct FcTrainJob *job,
+                      struct FcF64Array *accuracies,
+                      struct FcF64Array *losses);
+
+// Asks the training thread to stop after the current epoch. The job still
+// has to be joined. Safe to call from any thread.
+//
+// # Safety
+//
+// `job` must be null or a live handle from `fc_train_start`.
+void fc_train_cancel(const struct FcTrainJob *job);
+
+// Waits for the training thread, releases `job` and returns the trained
+// model, or null if training failed (see `fc_last_error_message`). A
+// cancelled job returns the model as trained so far. The model must be
+// released with `fc_model_free`.
+//
+// # Safety
+//
+// `job` must be null or a handle from `fc_train_start` that has not been
+// joined yet, and no other thread may use it during or after this call.
+struct FcModel *fc_train_join(struct FcTrainJob *job);
+
+// Validation accuracy of `model` at the end of training, or NaN if `model`
+// is null.
+//
+// # Safety
+//
+// `model` must be null or a live model handle.
+double fc_model_final_accuracy(const struct FcModel *model);
+
+// Classifies one fruit with a trained model. The returned string must be
+// released with `free_string`. Returns null on failure.
+//
+// # Safety
+//
+// `model` must be null or a live model handle.
+char *fc_model_predict(const struct FcModel *model,
+                       double weight,
+                       double size,
+                       double width,
+                       double height);
+
+// `fc_model_predict` for callers that keep measurements as floats. Works
+// with models of either precision.
+//
+// # Safety
+//
+// `model` must be null or a live model handle.
+char *fc_model_predict_f32(const struct FcModel *model,
+                           float weight,
+                           float size,
+                           float width,
+                           float height);
+
+// One of the `FcPrecision` values, or -1 if `model` is null. Models have
+// the precision of the config they were trained with.
+//
+// # Safety
+//
+// `model` must be null or a live model handle.
+int32_t fc_model_precision(const struct FcModel *model);
+
+// Copies `model` into a new handle computing in `precision`, one of the
+// `FcPrecision` values. Converting to `f32` rounds every parameter. The
+// copy must be released with `fc_model_free`; returns null on failure.
+//
+// # Safety
+//
+// `model` must be null or a live model handle.
+struct FcModel *fc_model_convert(const struct FcModel *model, uint32_t precision);
+
+// Classifies one fruit like `fc_model_predict` and explains the prediction
+// with `method`, one of the `FcExplainMethod` values. Returns `false` on
+// failure.
+//
+// # Safety
+//
+// `model` must be null or a live model handle and `explanation` must be
+// null or valid for writes.
+bool fc_model_explain(const struct FcModel *model,
+                      double weight,
+                      double size,
+                      double width,
+                      double height,
+                      uint32_t method,
+                      struct FcExplanation *explanation);
+
+// Saves `model` together with the config it was trained with as JSON.
+// Returns `false` on failure.
+//
+// # Safety
+//
+// `model` must be null or a live model handle and `path` must be null or a
+// valid NUL-terminated string.
+bool fc_model_save(const struct FcModel *model, const char *path);
+
+// Loads a model written by `fc_model_save`. Returns null on failure. The
+// model must be released with `fc_model_free`.
+//
+// # Safety
+//
+// `path` must be null or a valid NUL-terminated string.
+struct FcModel *fc_model_load(const char *path);
+
+// # Safety
+//
+// `model` must be null or a model handle that has not been freed yet.
+void fc_model_free(struct FcModel *model);
+
+// Rule-based fallback classifier for when no trained model is available.
+// The returned string must be released with `free_string`.
+char *predict(double weight, double size, double width, double height);
+
+// Releases an array returned by the library and resets it to empty, so
+// freeing the same array twice is harmless.
+//
+// # Safety
+//
+// `array` must be null, or point to an `FcF64Array` that is empty or whose
+// `ptr` and `len` are unchanged from when the library returned it.
+void fc_f64_array_free(struct FcF64Array *array);
+
+// Releases both arrays of a `TrainingResult` filled in by `train_network`.
+//
+// # Safety
+//
+// Same requirements as `fc_f64_array_free` for both arrays of `result`.
+void free_training_result(struct TrainingResult *result);
+
+// Releases the label of an explanation filled in by `fc_model_explain` and
+// resets it to null, so freeing twice is harmless.
+//
+// # Safety
+//
+// `explanation` must be null, or point to an `FcExplanation` whose `label`
+// is null or unchanged from when the library returned it.
+void fc_explanation_free(struct FcExplanation *explanation);
+
+// Releases a label returned by the library.
+//
+// # Safety
+//
+// `ptr` must be null or a string returned by `predict`, `fc_model_predict`
+// or `fc_model_predict_f32` that has not been freed yet.
+void free_string(char *ptr);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* FRUIT_CLASSIFIER_H */
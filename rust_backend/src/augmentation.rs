@@ -0,0 +1,82 @@
+// augmentation.rs
+use crate::config::{AugmentationConfig, Noise};
+use crate::utils::Normalizer;
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Beta, Distribution, StandardNormal};
+
+/// Perturbs normalized training features the way noisy instruments would.
+/// Features are mapped back to raw units for noise and rounding, then
+/// normalized again; mixup blends samples and their labels.
+#[derive(Debug, Clone)]
+pub struct Augmenter {
+    config: AugmentationConfig,
+    normalizer: Normalizer,
+    rng: StdRng,
+}
+
+impl Augmenter {
+    pub fn new(config: &AugmentationConfig, normalizer: &Normalizer, seed: u64) -> Self {
+        Augmenter {
+            config: config.clone(),
+            normalizer: normalizer.clone(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// A perturbed copy of normalized `features` and one-hot `labels`;
+    /// every call draws new perturbations.
+    pub fn augment(&mut self, features: &Array2<f64>, labels: &Array2<f64>) -> (Array2<f64>, Array2<f64>) {
+        let (mean, std) = (&self.normalizer.mean, &self.normalizer.std);
+        let mut raw = features * std + mean;
+
+        for mut row in raw.rows_mut() {
+            for (j, value) in row.iter_mut().enumerate() {
+                match self.config.noise {
+                    Noise::None => {}
+                    Noise::Gaussian { std } => {
+                        let e: f64 = self.rng.sample(StandardNormal);
+                        *value += e * std[j];
+                    }
+                    Noise::Multiplicative { relative } => {
+                        let e: f64 = self.rng.sample(StandardNormal);
+                        *value *= 1.0 + e * relative;
+                    }
+                }
+                if self.config.rounding > 0.0 && self.rng.gen_bool(self.config.rounding) {
+                    let unit = self.config.rounding_units[j];
+                    *value = (*value / unit).round() * unit;
+                }
+            }
+        }
+
+        let mut augmented = (raw - mean) / std;
+        let mut labels = labels.clone();
+        if self.config.mixup_alpha > 0.0 {
+            self.mixup(&mut augmented, &mut labels);
+        }
+        (augmented, labels)
+    }
+
+    // Blends each sample with a random partner using a Beta-distributed
+    // weight, keeping the larger share on the original sample
+    fn mixup(&mut self, features: &mut Array2<f64>, labels: &mut Array2<f64>) {
+        let beta = Beta::new(self.config.mixup_alpha, self.config.mixup_alpha).expect("alpha is positive");
+        let mut partners: Vec<usize> = (0..features.nrows()).collect();
+        partners.shuffle(&mut self.rng);
+        let (original_features, original_labels) = (features.clone(), labels.clone());
+
+        for (i, &j) in partners.iter().enumerate() {
+            let lambda: f64 = beta.sample(&mut self.rng);
+            let lambda = lambda.max(1.0 - lambda);
+            let mut row = features.row_mut(i);
+            row *= lambda;
+            row.scaled_add(1.0 - lambda, &original_features.row(j));
+            let mut row = labels.row_mut(i);
+            row *= lambda;
+            row.scaled_add(1.0 - lambda, &original_labels.row(j));
+        }
+    }
+}
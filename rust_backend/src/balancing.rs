@@ -0,0 +1,92 @@
+// balancing.rs
+use crate::classifier::argmax_rows;
+use crate::config::Balancing;
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Resamples normalized features and one-hot labels as `method` describes.
+/// The rows come back shuffled, since training walks the batches in order.
+pub fn rebalance<R: Rng>(features: &Array2<f64>, labels: &Array2<f64>, method: Balancing, rng: &mut R) -> (Array2<f64>, Array2<f64>) {
+    if method == Balancing::None {
+        return (features.clone(), labels.clone());
+    }
+
+    let classes = argmax_rows(labels);
+    let mut by_class = vec![Vec::new(); labels.ncols()];
+    for (row, &class) in classes.iter().enumerate() {
+        by_class[class].push(row);
+    }
+    by_class.retain(|rows| !rows.is_empty());
+    let largest = by_class.iter().map(Vec::len).max().unwrap_or(0);
+    let smallest = by_class.iter().map(Vec::len).min().unwrap_or(0);
+
+    let mut new_features: Vec<Array1<f64>> = Vec::new();
+    let mut new_labels: Vec<ArrayView1<f64>> = Vec::new();
+    for rows in &by_class {
+        let label = labels.row(rows[0]);
+        let kept: Vec<usize> = match method {
+            Balancing::Undersample => rows.choose_multiple(rng, smallest).copied().collect(),
+            _ => rows.clone(),
+        };
+        for &row in &kept {
+            new_features.push(features.row(row).to_owned());
+            new_labels.push(label);
+        }
+
+        let missing = match method {
+            Balancing::Undersample | Balancing::None => 0,
+            Balancing::Oversample | Balancing::Smote { .. } => largest - rows.len(),
+        };
+        match method {
+            Balancing::Smote { k_neighbors } if rows.len() > 1 => {
+                let neighbours: Vec<Vec<usize>> = rows
+                    .iter()
+                    .map(|&row| nearest_neighbours(features, rows, row, k_neighbors))
+                    .collect();
+                for _ in 0..missing {
+                    let i = rng.gen_range(0..rows.len());
+                    let &other = neighbours[i].choose(rng).expect("classes with two samples have a neighbour");
+                    let (a, b) = (features.row(rows[i]), features.row(other));
+                    let t: f64 = rng.gen_range(0.0..1.0);
+                    new_features.push(&a + &((&b - &a) * t));
+                    new_labels.push(label);
+                }
+            }
+            // Duplicates, also for SMOTE on a class with a single sample
+            _ => {
+                for _ in 0..missing {
+                    let &row = rows.choose(rng).expect("classes are not empty");
+                    new_features.push(features.row(row).to_owned());
+                    new_labels.push(label);
+                }
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..new_features.len()).collect();
+    order.shuffle(rng);
+    let features = Array2::from_shape_fn((order.len(), features.ncols()), |(i, j)| new_features[order[i]][j]);
+    let labels = Array2::from_shape_fn((order.len(), labels.ncols()), |(i, j)| new_labels[order[i]][j]);
+    (features, labels)
+}
+
+/// The `k` rows of `rows` closest to `row`, excluding itself.
+fn nearest_neighbours(features: &Array2<f64>, rows: &[usize], row: usize, k: usize) -> Vec<usize> {
+    let sample = features.row(row);
+    let mut distances: Vec<(f64, usize)> = rows
+        .iter()
+        .filter(|&&other| other != row)
+        .map(|&other| {
+            let d = features.row(other).iter().zip(&sample).map(|(a, b)| (a - b).powi(2)).sum::<f64>();
+            (d, other)
+        })
+        .collect();
+    distances.sort_by(|a, b| a.0.total_cmp(&b.0));
+    distances.into_iter().take(k).map(|(_, other)| other).collect()
+}
+
+/// Number of samples of each class in one-hot `labels`.
+pub fn class_counts(labels: &Array2<f64>) -> Vec<usize> {
+    labels.sum_axis(Axis(0)).iter().map(|&c| c.round() as usize).collect()
+}
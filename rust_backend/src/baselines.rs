@@ -0,0 +1,191 @@
+// baselines.rs
+use crate::classifier::{argmax_rows, Classifier};
+use ndarray::{Array1, Array2, Axis};
+use serde::{Deserialize, Serialize};
+
+/// Classifies by majority vote of the `k` closest training samples.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KNearestNeighbors {
+    k: usize,
+    features: Array2<f64>,
+    classes: Vec<usize>,
+    n_classes: usize,
+}
+
+impl KNearestNeighbors {
+    pub const DEFAULT_K: usize = 5;
+
+    /// Stores the normalized training set; `labels` are one-hot rows.
+    pub fn fit(features: &Array2<f64>, labels: &Array2<f64>, k: usize) -> Self {
+        KNearestNeighbors {
+            k: k.clamp(1, features.nrows().max(1)),
+            features: features.clone(),
+            classes: argmax_rows(labels),
+            n_classes: labels.ncols(),
+        }
+    }
+}
+
+impl Classifier for KNearestNeighbors {
+    fn name(&self) -> &str {
+        "knn"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        let mut probabilities = Array2::zeros((features.nrows(), self.n_classes));
+        let mut distances: Vec<(f64, usize)> = Vec::with_capacity(self.features.nrows());
+
+        for (query, mut out) in features.axis_iter(Axis(0)).zip(probabilities.axis_iter_mut(Axis(0))) {
+            distances.clear();
+            distances.extend(self.features.axis_iter(Axis(0)).zip(&self.classes).map(|(row, &class)| {
+                let d: f64 = row.iter().zip(query.iter()).map(|(a, b)| (a - b).powi(2)).sum();
+                (d, class)
+            }));
+            if distances.is_empty() {
+                continue;
+            }
+
+            let k = self.k.min(distances.len());
+            distances.select_nth_unstable_by(k - 1, |a, b| a.0.total_cmp(&b.0));
+            for &(_, class) in &distances[..k] {
+                out[class] += 1.0 / k as f64;
+            }
+        }
+        probabilities
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LogisticRegressionParams {
+    pub learning_rate: f64,
+    pub epochs: usize,
+    pub l2: f64,
+}
+
+impl Default for LogisticRegressionParams {
+    fn default() -> Self {
+        LogisticRegressionParams {
+            learning_rate: 0.5,
+            epochs: 500,
+            l2: 0.001,
+        }
+    }
+}
+
+/// Multinomial (softmax) logistic regression trained with full-batch
+/// gradient descent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogisticRegression {
+    weights: Array2<f64>,
+    bias: Array1<f64>,
+}
+
+impl LogisticRegression {
+    pub fn fit(features: &Array2<f64>, labels: &Array2<f64>, params: &LogisticRegressionParams) -> Self {
+        let mut model = LogisticRegression {
+            weights: Array2::zeros((features.ncols(), labels.ncols())),
+            bias: Array1::zeros(labels.ncols()),
+        };
+        let n = features.nrows().max(1) as f64;
+
+        for _ in 0..params.epochs {
+            let error = model.predict_proba(features) - labels;
+            let grad_weights = features.t().dot(&error) / n + &model.weights * params.l2;
+            let grad_bias = error.sum_axis(Axis(0)) / n;
+            model.weights -= &(grad_weights * params.learning_rate);
+            model.bias -= &(grad_bias * params.learning_rate);
+        }
+        model
+    }
+}
+
+impl Classifier for LogisticRegression {
+    fn name(&self) -> &str {
+        "logistic_regression"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        softmax(features.dot(&self.weights) + &self.bias)
+    }
+}
+
+/// Treats every feature as an independent normal distribution per class.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GaussianNaiveBayes {
+    /// Per class (rows) and feature (columns)
+    means: Array2<f64>,
+    variances: Array2<f64>,
+    log_priors: Array1<f64>,
+}
+
+impl GaussianNaiveBayes {
+    /// Added to every variance, relative to the largest feature variance,
+    /// so constant features don't divide by zero
+    pub const VAR_SMOOTHING: f64 = 1e-9;
+
+    pub fn fit(features: &Array2<f64>, labels: &Array2<f64>) -> Self {
+        let (n_classes, n_features) = (labels.ncols(), features.ncols());
+        let classes = argmax_rows(labels);
+
+        let max_variance = features.var_axis(Axis(0), 0.0).fold(0.0f64, |a, &b| a.max(b));
+        let epsilon = Self::VAR_SMOOTHING * max_variance.max(1.0);
+
+        let mut means = Array2::zeros((n_classes, n_features));
+        let mut variances = Array2::from_elem((n_classes, n_features), epsilon);
+        let mut log_priors = Array1::from_elem(n_classes, f64::NEG_INFINITY);
+
+        for class in 0..n_classes {
+            let rows: Vec<usize> = (0..classes.len()).filter(|&i| classes[i] == class).collect();
+            if rows.is_empty() {
+                continue;
+            }
+            let members = features.select(Axis(0), &rows);
+            means.row_mut(class).assign(&members.mean_axis(Axis(0)).expect("class has members"));
+            variances.row_mut(class).assign(&(members.var_axis(Axis(0), 0.0) + epsilon));
+            log_priors[class] = (rows.len() as f64 / classes.len() as f64).ln();
+        }
+
+        GaussianNaiveBayes {
+            means,
+            variances,
+            log_priors,
+        }
+    }
+}
+
+impl Classifier for GaussianNaiveBayes {
+    fn name(&self) -> &str {
+        "naive_bayes"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        let n_classes = self.log_priors.len();
+        let mut log_likelihood = Array2::zeros((features.nrows(), n_classes));
+
+        for (sample, mut out) in features.axis_iter(Axis(0)).zip(log_likelihood.axis_iter_mut(Axis(0))) {
+            for class in 0..n_classes {
+                let mean = self.means.row(class);
+                let variance = self.variances.row(class);
+                let log_density: f64 = sample
+                    .iter()
+                    .zip(mean.iter().zip(variance.iter()))
+                    .map(|(x, (m, v))| -0.5 * ((2.0 * std::f64::consts::PI * v).ln() + (x - m).powi(2) / v))
+                    .sum();
+                out[class] = self.log_priors[class] + log_density;
+            }
+        }
+        softmax(log_likelihood)
+    }
+}
+
+/// Row-wise softmax that tolerates `-inf` entries, e.g. classes that never
+/// occurred in training.
+pub(crate) fn softmax(mut scores: Array2<f64>) -> Array2<f64> {
+    for mut row in scores.axis_iter_mut(Axis(0)) {
+        let max = row.fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+        row.mapv_inplace(|v| (v - max).exp());
+        let sum = row.sum();
+        row.mapv_inplace(|v| v / sum);
+    }
+    scores
+}
@@ -0,0 +1,137 @@
+// boosting.rs
+use crate::baselines::softmax;
+use crate::classifier::{argmax_rows, Classifier, TrainedModel};
+use crate::config::{BoostingConfig, TrainingConfig};
+use crate::data::FruitSample;
+use crate::training::{self, TrainingError};
+use crate::tree::{normalized_importances, RegressionTree, TreeParams};
+use ndarray::{Array1, Array2, Axis};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// Multiclass gradient-boosted trees on the softmax cross-entropy: every
+/// round fits one regression tree per class to the gradient of the loss.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GradientBoosting {
+    /// Log class priors the additive scores start from
+    initial_scores: Array1<f64>,
+    learning_rate: f64,
+    /// One tree per class for every round
+    rounds: Vec<Vec<RegressionTree>>,
+}
+
+impl GradientBoosting {
+    /// Fits on normalized features and one-hot labels. `rng` only drives
+    /// row subsampling.
+    pub fn fit<R: Rng>(features: &Array2<f64>, labels: &Array2<f64>, config: &BoostingConfig, rng: &mut R) -> Self {
+        let (n, n_classes) = (features.nrows(), labels.ncols());
+        let classes = argmax_rows(labels);
+        let params = TreeParams {
+            max_depth: config.max_depth,
+            min_samples_leaf: config.min_samples_leaf,
+            ..TreeParams::default()
+        };
+
+        let mut counts = vec![0usize; n_classes];
+        for &c in &classes {
+            counts[c] += 1;
+        }
+        // Classes absent from training start very unlikely rather than at -inf
+        let initial_scores: Array1<f64> = counts
+            .iter()
+            .map(|&c| (c.max(1) as f64 / n.max(1) as f64).ln())
+            .collect();
+
+        let mut model = GradientBoosting {
+            initial_scores,
+            learning_rate: config.learning_rate,
+            rounds: Vec::with_capacity(config.n_rounds),
+        };
+        let mut scores = Array2::from_shape_fn((n, n_classes), |(_, k)| model.initial_scores[k]);
+        let sample_size = ((n as f64 * config.subsample).round() as usize).clamp(1, n.max(1));
+        // Friedman's multiclass scaling of the Newton step
+        let scale = (n_classes as f64 - 1.0) / n_classes as f64;
+
+        for _ in 0..config.n_rounds {
+            let probabilities = softmax(scores.clone());
+            let rows: Vec<usize> = if sample_size < n {
+                rand::seq::index::sample(rng, n, sample_size).into_vec()
+            } else {
+                (0..n).collect()
+            };
+
+            let trees: Vec<RegressionTree> = (0..n_classes)
+                .map(|k| {
+                    let gradients: Vec<f64> = (0..n).map(|i| labels[[i, k]] - probabilities[[i, k]]).collect();
+                    let hessians: Vec<f64> = (0..n)
+                        .map(|i| probabilities[[i, k]] * (1.0 - probabilities[[i, k]]))
+                        .collect();
+                    RegressionTree::fit(features, &rows, &gradients, &hessians, &params)
+                })
+                .collect();
+
+            for (k, tree) in trees.iter().enumerate() {
+                let update = tree.predict(features) * (scale * config.learning_rate);
+                let mut column = scores.column_mut(k);
+                column += &update;
+            }
+            model.rounds.push(trees);
+        }
+        model
+    }
+
+    pub fn n_rounds(&self) -> usize {
+        self.rounds.len()
+    }
+
+    /// Squared-error decrease per feature over every tree, scaled to sum
+    /// to 1.
+    pub fn feature_importances(&self) -> Vec<f64> {
+        let mut total = vec![0.0; self.rounds.first().and_then(|r| r.first()).map_or(0, |t| t.error_decrease().len())];
+        for tree in self.rounds.iter().flatten() {
+            for (t, v) in total.iter_mut().zip(tree.error_decrease()) {
+                *t += v;
+            }
+        }
+        normalized_importances(&total)
+    }
+
+    /// Additive class scores before the softmax.
+    fn scores(&self, features: &Array2<f64>) -> Array2<f64> {
+        let n_classes = self.initial_scores.len();
+        let scale = (n_classes as f64 - 1.0) / n_classes as f64 * self.learning_rate;
+        let mut scores = Array2::from_shape_fn((features.nrows(), n_classes), |(_, k)| self.initial_scores[k]);
+        for trees in &self.rounds {
+            for (k, tree) in trees.iter().enumerate() {
+                let mut column = scores.index_axis_mut(Axis(1), k);
+                column += &(tree.predict(features) * scale);
+            }
+        }
+        scores
+    }
+}
+
+impl Classifier for GradientBoosting {
+    fn name(&self) -> &str {
+        "gradient_boosting"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        softmax(self.scores(features))
+    }
+}
+
+/// Trains gradient-boosted trees on the dataset named in `config.data`.
+pub fn run_boosting_training(config: &TrainingConfig) -> Result<TrainedModel<GradientBoosting>, TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    train_boosting(&samples, config)
+}
+
+/// Runs `config.boosting.n_rounds` rounds on the training split of
+/// `samples`; the validation split only scores the finished model.
+pub fn train_boosting(samples: &[FruitSample], config: &TrainingConfig) -> Result<TrainedModel<GradientBoosting>, TrainingError> {
+    TrainedModel::fit(samples, config, |data, rng| {
+        GradientBoosting::fit(&data.train_features, &data.train_labels, &config.boosting, rng)
+    })
+}
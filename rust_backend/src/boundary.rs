@@ -0,0 +1,277 @@
+// boundary.rs
+use crate::classifier::Classifier;
+use crate::config::{BoundaryConfig, HeldFeatures, TrainingConfig};
+use crate::data::FruitSample;
+use crate::training::{self, PreparedSplit, TrainingError, TrainingResult, UNKNOWN_CONFIDENCE_THRESHOLD};
+use crate::utils::{Normalizer, FEATURE_NAMES};
+use ndarray::{Array2, Axis};
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::path::Path;
+
+/// One axis of a `DecisionGrid`: a feature column and the raw range it spans.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GridAxis {
+    pub feature: usize,
+    pub low: f64,
+    pub high: f64,
+}
+
+impl GridAxis {
+    // Centres of `resolution` equal cells
+    fn centres(&self, resolution: usize) -> Vec<f64> {
+        let step = (self.high - self.low) / resolution as f64;
+        (0..resolution).map(|i| self.low + (i as f64 + 0.5) * step).collect()
+    }
+}
+
+/// Predictions of a model over a grid spanning two features, with the other
+/// two held fixed. Coordinates are raw measurements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecisionGrid {
+    /// Column of the feature on the horizontal axis
+    pub x_feature: usize,
+    /// Column of the feature on the vertical axis
+    pub y_feature: usize,
+    /// Cell centres along each axis
+    pub xs: Vec<f64>,
+    pub ys: Vec<f64>,
+    /// Most likely class of each cell, row by row with `x` varying fastest
+    pub classes: Vec<usize>,
+    /// Probability of that class
+    pub confidences: Vec<f64>,
+}
+
+impl DecisionGrid {
+    /// Evaluates `model` at `resolution` x `resolution` cell centres
+    /// covering both axes; every other feature takes its value from `held`.
+    pub fn new<C: Classifier + ?Sized>(
+        model: &C,
+        normalizer: &Normalizer,
+        x: GridAxis,
+        y: GridAxis,
+        held: [f64; 4],
+        resolution: usize,
+    ) -> Self {
+        let (xs, ys) = (x.centres(resolution), y.centres(resolution));
+        let mut features = Array2::from_shape_fn((resolution * resolution, held.len()), |(_, j)| held[j]);
+        for (cell, mut row) in features.axis_iter_mut(Axis(0)).enumerate() {
+            row[x.feature] = xs[cell % resolution];
+            row[y.feature] = ys[cell / resolution];
+        }
+        normalizer.normalize(&mut features);
+
+        let probabilities = model.predict_proba(&features);
+        let (classes, confidences) = probabilities
+            .axis_iter(Axis(0))
+            .map(|row| {
+                row.iter()
+                    .copied()
+                    .enumerate()
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                    .unwrap_or((0, 0.0))
+            })
+            .unzip();
+
+        DecisionGrid {
+            x_feature: x.feature,
+            y_feature: y.feature,
+            xs,
+            ys,
+            classes,
+            confidences,
+        }
+    }
+}
+
+/// Raw values for the features that are not plotted.
+pub fn held_values(held: HeldFeatures, train: &[FruitSample]) -> [f64; 4] {
+    match held {
+        HeldFeatures::Mean => {
+            let n = train.len().max(1) as f64;
+            let mut mean = [0.0; 4];
+            for sample in train {
+                for (m, value) in mean.iter_mut().zip([sample.weight, sample.size, sample.width, sample.height]) {
+                    *m += value / n;
+                }
+            }
+            mean
+        }
+        HeldFeatures::Sample { measurements } => measurements,
+    }
+}
+
+/// Trains a network on the dataset named in `config.data` and returns it
+/// together with its training split, whose points the plot overlays.
+pub fn run_boundary(config: &TrainingConfig) -> Result<(TrainingResult, Vec<FruitSample>), TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    network_boundary(&samples, config)
+}
+
+/// Like `run_boundary`, on samples that are already loaded.
+pub fn network_boundary(
+    samples: &[FruitSample],
+    config: &TrainingConfig,
+) -> Result<(TrainingResult, Vec<FruitSample>), TrainingError> {
+    config.validate()?;
+    let mut rng = training::seeded_rng(config.seed);
+    let (train, val) = training::split_samples(samples, config, &mut rng);
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    let result = training::train_prepared(data, config, &mut rng, |_| true)?;
+    Ok((result, train))
+}
+
+/// Draws the decision regions of `result` over the two features named in
+/// `settings`, with the training points on top. Cells whose top probability
+/// is below `UNKNOWN_CONFIDENCE_THRESHOLD`, which `predict` reports as
+/// "unknown", are grey.
+pub fn plot_decision_boundary(
+    result: &TrainingResult,
+    train: &[FruitSample],
+    settings: &BoundaryConfig,
+) -> Result<(), Box<dyn Error>> {
+    let feature = |name: &str| {
+        FEATURE_NAMES
+            .iter()
+            .position(|f| *f == name)
+            .ok_or_else(|| format!("Unknown feature '{}'", name))
+    };
+    let (x_feature, y_feature) = (feature(&settings.x)?, feature(&settings.y)?);
+    let points: Vec<([f64; 4], usize)> = train
+        .iter()
+        .map(|s| {
+            let class = result.class_names.iter().position(|c| *c == s.label).unwrap_or(usize::MAX);
+            ([s.weight, s.size, s.width, s.height], class)
+        })
+        .collect();
+    let axis = |feature: usize| {
+        let (low, high) = points.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), (m, _)| {
+            (low.min(m[feature]), high.max(m[feature]))
+        });
+        let margin = (high - low).max(1e-6) * 0.05;
+        GridAxis {
+            feature,
+            // Measurements are positive
+            low: (low - margin).max(0.0),
+            high: high + margin,
+        }
+    };
+
+    let held = held_values(settings.held, train);
+    let grid = DecisionGrid::new(
+        &result.model,
+        &result.normalizer,
+        axis(x_feature),
+        axis(y_feature),
+        held,
+        settings.resolution,
+    );
+    let marker = matches!(settings.held, HeldFeatures::Sample { .. }).then_some(held);
+    let chart = BoundaryChart {
+        grid: &grid,
+        class_names: &result.class_names,
+        points: &points,
+        marker,
+    };
+
+    let path = Path::new(&settings.output);
+    if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("svg")) {
+        chart.draw(SVGBackend::new(path, (1000, 800)).into_drawing_area())
+    } else {
+        chart.draw(BitMapBackend::new(path, (1000, 800)).into_drawing_area())
+    }
+}
+
+// Cells the network is unsure about
+const UNSURE: RGBColor = RGBColor(200, 200, 200);
+
+struct BoundaryChart<'a> {
+    grid: &'a DecisionGrid,
+    class_names: &'a [String],
+    points: &'a [([f64; 4], usize)],
+    /// Held sample, marked with a cross
+    marker: Option<[f64; 4]>,
+}
+
+impl BoundaryChart<'_> {
+    fn draw<DB: DrawingBackend>(&self, root: DrawingArea<DB, Shift>) -> Result<(), Box<dyn Error>>
+    where
+        DB::ErrorType: 'static,
+    {
+        let grid = self.grid;
+        let (xs, ys) = (&grid.xs, &grid.ys);
+        let (dx, dy) = (cell_size(xs), cell_size(ys));
+        let x_range = xs[0] - dx / 2.0..xs[xs.len() - 1] + dx / 2.0;
+        let y_range = ys[0] - dy / 2.0..ys[ys.len() - 1] + dy / 2.0;
+        let axis_label = |j: usize| format!("{} ({})", FEATURE_NAMES[j], if j == 0 { "g" } else { "cm" });
+
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(
+                format!("Decision regions: {} vs {}", FEATURE_NAMES[grid.x_feature], FEATURE_NAMES[grid.y_feature]),
+                ("sans-serif", 30),
+            )
+            .margin(10)
+            .x_label_area_size(50)
+            .y_label_area_size(60)
+            .build_cartesian_2d(x_range, y_range)?;
+        chart
+            .configure_mesh()
+            .disable_mesh()
+            .x_desc(axis_label(grid.x_feature))
+            .y_desc(axis_label(grid.y_feature))
+            .draw()?;
+
+        let cells = |unsure: bool| {
+            grid.classes
+                .iter()
+                .zip(&grid.confidences)
+                .enumerate()
+                .filter(move |(_, (_, confidence))| (**confidence < UNKNOWN_CONFIDENCE_THRESHOLD) == unsure)
+                .map(move |(cell, (&class, _))| {
+                    let (x, y) = (xs[cell % xs.len()], ys[cell / xs.len()]);
+                    let color = if unsure { UNSURE.to_rgba() } else { Palette99::pick(class).mix(0.3) };
+                    Rectangle::new([(x - dx / 2.0, y - dy / 2.0), (x + dx / 2.0, y + dy / 2.0)], color.filled())
+                })
+        };
+        chart.draw_series(cells(false))?;
+        chart
+            .draw_series(cells(true))?
+            .label(format!("below {:.0}% confidence", UNKNOWN_CONFIDENCE_THRESHOLD * 100.0))
+            .legend(|(x, y)| Rectangle::new([(x + 4, y - 5), (x + 16, y + 5)], UNSURE.filled()));
+
+        for (class, name) in self.class_names.iter().enumerate() {
+            let color = Palette99::pick(class);
+            chart
+                .draw_series(
+                    self.points
+                        .iter()
+                        .filter(|(_, c)| *c == class)
+                        .map(|(m, _)| Circle::new((m[grid.x_feature], m[grid.y_feature]), 3, color.filled())),
+                )?
+                .label(name.as_str())
+                .legend(move |(x, y)| Circle::new((x + 10, y), 4, color.filled()));
+        }
+        if let Some(m) = self.marker {
+            chart
+                .draw_series(std::iter::once(Cross::new((m[grid.x_feature], m[grid.y_feature]), 8, BLACK.stroke_width(3))))?
+                .label("held sample")
+                .legend(|(x, y)| Cross::new((x + 10, y), 5, BLACK.stroke_width(2)));
+        }
+
+        chart
+            .configure_series_labels()
+            .position(SeriesLabelPosition::UpperLeft)
+            .background_style(WHITE.mix(0.8))
+            .border_style(BLACK)
+            .draw()?;
+        root.present()?;
+        Ok(())
+    }
+}
+
+fn cell_size(centres: &[f64]) -> f64 {
+    if centres.len() > 1 { centres[1] - centres[0] } else { 1.0 }
+}
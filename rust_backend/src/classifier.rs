@@ -0,0 +1,177 @@
+// classifier.rs
+use crate::config::TrainingConfig;
+use crate::data::FruitSample;
+use crate::model::NeuralNet;
+use crate::precision::Real;
+use crate::training::{self, PreparedSplit, TrainingError, UNKNOWN_CONFIDENCE_THRESHOLD};
+use crate::utils::{measurement_row, Normalizer};
+use ndarray::{Array2, Axis};
+use rand::rngs::StdRng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+/// Inference interface shared by the neural network and the baseline
+/// models. Features are normalized rows of weight, size, width and height;
+/// class indices refer to the `class_names` of the split the model was
+/// fitted on.
+pub trait Classifier {
+    /// Short name used in reports
+    fn name(&self) -> &str;
+
+    /// Class probabilities, one row per sample
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64>;
+
+    /// Most likely class of each sample
+    fn predict(&self, features: &Array2<f64>) -> Vec<usize> {
+        argmax_rows(&self.predict_proba(features))
+    }
+}
+
+impl<F: Real> Classifier for NeuralNet<F> {
+    fn name(&self) -> &str {
+        "neural_net"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        let (_, _, output) = self.forward(&features.mapv(F::cast_from));
+        output.mapv(F::into_f64)
+    }
+}
+
+/// Column of the largest value in each row, e.g. the class of a one-hot
+/// label or the prediction from a row of probabilities.
+pub fn argmax_rows<F: Real>(values: &Array2<F>) -> Vec<usize> {
+    values
+        .axis_iter(Axis(0))
+        .map(|row| {
+            row.iter()
+                .enumerate()
+                .max_by(|(_, a), (_, b)| a.into_f64().total_cmp(&b.into_f64()))
+                .map_or(0, |(i, _)| i)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Metrics {
+    pub accuracy: f64,
+    /// Mean negative log-probability of the true class
+    pub log_loss: f64,
+    /// Unweighted mean of the per-class F1 scores
+    pub macro_f1: f64,
+}
+
+impl Metrics {
+    /// Scores `model` on normalized features and one-hot labels.
+    pub fn evaluate<C: Classifier + ?Sized>(model: &C, features: &Array2<f64>, labels: &Array2<f64>) -> Self {
+        Self::from_probabilities(&model.predict_proba(features), labels)
+    }
+
+    pub fn from_probabilities(probabilities: &Array2<f64>, labels: &Array2<f64>) -> Self {
+        let n_classes = labels.ncols();
+        let truth = argmax_rows(labels);
+        let predicted = argmax_rows(probabilities);
+        let n = truth.len().max(1) as f64;
+
+        let correct = truth.iter().zip(&predicted).filter(|(t, p)| t == p).count();
+        let log_loss = truth
+            .iter()
+            .enumerate()
+            .map(|(i, &t)| probabilities[[i, t]].max(1e-15).recip().ln())
+            .sum::<f64>()
+            / n;
+
+        let mut true_positives = vec![0usize; n_classes];
+        let mut predicted_counts = vec![0usize; n_classes];
+        let mut actual_counts = vec![0usize; n_classes];
+        for (&t, &p) in truth.iter().zip(&predicted) {
+            actual_counts[t] += 1;
+            predicted_counts[p] += 1;
+            if t == p {
+                true_positives[t] += 1;
+            }
+        }
+
+        // Classes that never occur and are never predicted don't count
+        let f1_scores: Vec<f64> = (0..n_classes)
+            .filter(|&c| actual_counts[c] + predicted_counts[c] > 0)
+            .map(|c| 2.0 * true_positives[c] as f64 / (actual_counts[c] + predicted_counts[c]) as f64)
+            .collect();
+        let macro_f1 = if f1_scores.is_empty() {
+            0.0
+        } else {
+            f1_scores.iter().sum::<f64>() / f1_scores.len() as f64
+        };
+
+        Metrics {
+            accuracy: correct as f64 / n,
+            log_loss,
+            macro_f1,
+        }
+    }
+}
+
+/// A classifier other than the neural network together with everything
+/// needed to use it on raw measurements, saved and loaded as one JSON file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainedModel<M> {
+    pub config: TrainingConfig,
+    pub model: M,
+    pub normalizer: Normalizer,
+    pub class_names: Vec<String>,
+    /// Validation accuracy
+    pub final_accuracy: f64,
+}
+
+impl<M: Classifier> TrainedModel<M> {
+    /// Fits `model` on the split described by `config` and records its
+    /// validation accuracy.
+    pub(crate) fn fit(
+        samples: &[FruitSample],
+        config: &TrainingConfig,
+        fit: impl FnOnce(&PreparedSplit, &mut StdRng) -> M,
+    ) -> Result<Self, TrainingError> {
+        config.validate()?;
+        let mut rng = training::seeded_rng(config.seed);
+        let (train, val) = training::split_samples(samples, config, &mut rng);
+        let data = PreparedSplit::new(&train, &val, config.scaler)?;
+
+        let model = fit(&data, &mut rng);
+        let final_accuracy = Metrics::evaluate(&model, &data.val_features, &data.val_labels).accuracy;
+        Ok(TrainedModel {
+            config: config.clone(),
+            model,
+            normalizer: data.normalizer,
+            class_names: data.class_names,
+            final_accuracy,
+        })
+    }
+
+    /// Classifies raw (unnormalized) measurements, reporting "unknown" below
+    /// the usual confidence threshold.
+    pub fn predict(&self, weight: f64, size: f64, width: f64, height: f64) -> (&str, f64) {
+        let mut input = measurement_row(weight, size, width, height);
+        self.normalizer.normalize(&mut input);
+
+        let probabilities = self.model.predict_proba(&input);
+        let class = argmax_rows(&probabilities)[0];
+        let confidence = probabilities[[0, class]];
+        if confidence < UNKNOWN_CONFIDENCE_THRESHOLD || class >= self.class_names.len() {
+            ("unknown", confidence)
+        } else {
+            (&self.class_names[class], confidence)
+        }
+    }
+}
+
+impl<M: Serialize + DeserializeOwned> TrainedModel<M> {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        training::save_json(self, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        training::load_json(path)
+    }
+}
@@ -0,0 +1,304 @@
+// codegen.rs
+use crate::model::DenseLayer;
+use crate::training::{TrainingResult, UNKNOWN_CONFIDENCE_THRESHOLD};
+use ndarray::{Array1, Array2};
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+/// Writes `result` as dependency-free C to `path` and the matching header
+/// next to it (same name, `.h` extension). See `c_source`.
+pub fn write_c(result: &TrainingResult, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let path = path.as_ref();
+    let header = path.with_extension("h");
+    let header_name = header.file_name().and_then(|n| n.to_str()).ok_or("Invalid C source path")?;
+    fs::write(&header, c_header(result, header_name))?;
+    fs::write(path, c_source(result, header_name)?)?;
+    Ok(())
+}
+
+/// Writes `result` as a `no_std` Rust module to `path`. See `rust_source`.
+pub fn write_rust(result: &TrainingResult, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    fs::write(path, rust_source(result)?)?;
+    Ok(())
+}
+
+/// Declarations for `c_source`: `FRUIT_FEATURES`, `FRUIT_CLASSES`,
+/// `FRUIT_UNKNOWN_THRESHOLD`, `FRUIT_CLASS_LABELS`, `fruit_probabilities` and
+/// `fruit_classify`.
+pub fn c_header(result: &TrainingResult, header_name: &str) -> String {
+    let guard: String = header_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect();
+    format!(
+        "/* Generated by fruit_classifier {version}; do not edit. */
+#ifndef {guard}
+#define {guard}
+
+#define FRUIT_FEATURES 4
+#define FRUIT_CLASSES {classes}
+/* Below this probability fruit_classify() returns \"unknown\" */
+#define FRUIT_UNKNOWN_THRESHOLD {threshold}f
+
+extern const char *const FRUIT_CLASS_LABELS[FRUIT_CLASSES];
+
+/* Class probabilities for raw measurements, in FRUIT_CLASS_LABELS order */
+void fruit_probabilities(float weight, float size, float width, float height,
+                         float probabilities[FRUIT_CLASSES]);
+
+/* The most likely class label, or \"unknown\" below FRUIT_UNKNOWN_THRESHOLD */
+const char *fruit_classify(float weight, float size, float width, float height);
+
+#endif /* {guard} */
+",
+        version = env!("CARGO_PKG_VERSION"),
+        classes = result.class_names.len(),
+        threshold = float(UNKNOWN_CONFIDENCE_THRESHOLD),
+    )
+}
+
+/// C11 source computing the same probabilities as `NeuralNet::forward` on
+/// normalized input, in float32 and without libm: batch normalization is
+/// folded into the hidden layer and softmax uses its own `exp`. Includes the
+/// header named `header_name`.
+pub fn c_source(result: &TrainingResult, header_name: &str) -> Result<String, Box<dyn Error>> {
+    let (hidden, output) = inference_layers(result)?;
+    let mut source = format!(
+        "/* Generated by fruit_classifier {}; do not edit. */\n#include \"{}\"\n\n#include <stdint.h>\n\n#define FRUIT_HIDDEN {}\n\n",
+        env!("CARGO_PKG_VERSION"),
+        header_name,
+        hidden.bias.len()
+    );
+    writeln!(source, "static const float MEAN[FRUIT_FEATURES] = {};", c_vector(&result.normalizer.mean))?;
+    writeln!(source, "static const float STD[FRUIT_FEATURES] = {};", c_vector(&result.normalizer.std))?;
+    writeln!(source, "static const float WEIGHTS1[FRUIT_FEATURES][FRUIT_HIDDEN] = {};", c_matrix(&hidden.weights))?;
+    writeln!(source, "static const float BIAS1[FRUIT_HIDDEN] = {};", c_vector(&hidden.bias))?;
+    writeln!(source, "static const float WEIGHTS2[FRUIT_HIDDEN][FRUIT_CLASSES] = {};", c_matrix(&output.weights))?;
+    writeln!(source, "static const float BIAS2[FRUIT_CLASSES] = {};\n", c_vector(&output.bias))?;
+    let labels: Vec<String> = result.class_names.iter().map(|name| c_string(name)).collect();
+    writeln!(source, "const char *const FRUIT_CLASS_LABELS[FRUIT_CLASSES] = {{{}}};\n", labels.join(", "))?;
+
+    source.push_str(
+        "/* exp(x) for x <= 0 as 2^k * e^r with |r| <= ln(2) / 2, where the Taylor
+   polynomial of degree 6 is accurate to float32 precision */
+static float fruit_exp(float x)
+{
+    union {
+        float value;
+        uint32_t bits;
+    } scale;
+    int k;
+    float r;
+
+    if (x < -87.0f)
+        return 0.0f;
+    k = (int)(x * 1.44269504f - 0.5f);
+    r = x - (float)k * 0.693147181f;
+    scale.bits = (uint32_t)(k + 127) << 23;
+    return (1.0f + r * (1.0f + r * (0.5f + r * (1.0f / 6.0f + r * (1.0f / 24.0f + r * (1.0f / 120.0f + r / 720.0f))))))
+           * scale.value;
+}
+
+void fruit_probabilities(float weight, float size, float width, float height,
+                         float probabilities[FRUIT_CLASSES])
+{
+    const float input[FRUIT_FEATURES] = {weight, size, width, height};
+    float normalized[FRUIT_FEATURES];
+    float hidden[FRUIT_HIDDEN];
+    float max, sum = 0.0f;
+    int i, j;
+
+    for (i = 0; i < FRUIT_FEATURES; i++)
+        normalized[i] = (input[i] - MEAN[i]) / STD[i];
+    for (j = 0; j < FRUIT_HIDDEN; j++) {
+        float value = BIAS1[j];
+        for (i = 0; i < FRUIT_FEATURES; i++)
+            value += normalized[i] * WEIGHTS1[i][j];
+        hidden[j] = value > 0.0f ? value : 0.0f;
+    }
+    for (j = 0; j < FRUIT_CLASSES; j++) {
+        float value = BIAS2[j];
+        for (i = 0; i < FRUIT_HIDDEN; i++)
+            value += hidden[i] * WEIGHTS2[i][j];
+        probabilities[j] = value;
+    }
+
+    max = probabilities[0];
+    for (j = 1; j < FRUIT_CLASSES; j++)
+        if (probabilities[j] > max)
+            max = probabilities[j];
+    for (j = 0; j < FRUIT_CLASSES; j++) {
+        probabilities[j] = fruit_exp(probabilities[j] - max);
+        sum += probabilities[j];
+    }
+    for (j = 0; j < FRUIT_CLASSES; j++)
+        probabilities[j] /= sum;
+}
+
+const char *fruit_classify(float weight, float size, float width, float height)
+{
+    float probabilities[FRUIT_CLASSES];
+    int best = 0, j;
+
+    fruit_probabilities(weight, size, width, height, probabilities);
+    for (j = 1; j < FRUIT_CLASSES; j++)
+        if (probabilities[j] > probabilities[best])
+            best = j;
+    return probabilities[best] < FRUIT_UNKNOWN_THRESHOLD ? \"unknown\" : FRUIT_CLASS_LABELS[best];
+}
+",
+    );
+    Ok(source)
+}
+
+/// A Rust module using only `core`, for inclusion in `no_std` firmware. It
+/// exposes `FEATURES`, `CLASSES`, `UNKNOWN_THRESHOLD`, `CLASS_LABELS`,
+/// `probabilities` and `classify`, computed in float32 like `c_source`.
+pub fn rust_source(result: &TrainingResult) -> Result<String, Box<dyn Error>> {
+    let (hidden, output) = inference_layers(result)?;
+    let mut source = format!(
+        "//! Generated by fruit_classifier {}; do not edit.
+//!
+//! Uses only `core`, so it can be a module of a `no_std` crate.
+
+pub const FEATURES: usize = 4;
+pub const CLASSES: usize = {};
+const HIDDEN: usize = {};
+/// Below this probability `classify` returns \"unknown\"
+pub const UNKNOWN_THRESHOLD: f32 = {};
+
+",
+        env!("CARGO_PKG_VERSION"),
+        result.class_names.len(),
+        hidden.bias.len(),
+        float(UNKNOWN_CONFIDENCE_THRESHOLD)
+    );
+    let labels: Vec<String> = result.class_names.iter().map(|name| format!("{:?}", name)).collect();
+    writeln!(source, "pub static CLASS_LABELS: [&str; CLASSES] = [{}];\n", labels.join(", "))?;
+    writeln!(source, "static MEAN: [f32; FEATURES] = {};", rust_vector(&result.normalizer.mean))?;
+    writeln!(source, "static STD: [f32; FEATURES] = {};", rust_vector(&result.normalizer.std))?;
+    writeln!(source, "static WEIGHTS1: [[f32; HIDDEN]; FEATURES] = {};", rust_matrix(&hidden.weights))?;
+    writeln!(source, "static BIAS1: [f32; HIDDEN] = {};", rust_vector(&hidden.bias))?;
+    writeln!(source, "static WEIGHTS2: [[f32; CLASSES]; HIDDEN] = {};", rust_matrix(&output.weights))?;
+    writeln!(source, "static BIAS2: [f32; CLASSES] = {};\n", rust_vector(&output.bias))?;
+
+    source.push_str(
+        "// exp(x) for x <= 0 as 2^k * e^r with |r| <= ln(2) / 2, where the Taylor
+// polynomial of degree 6 is accurate to float32 precision
+fn exp(x: f32) -> f32 {
+    if x < -87.0 {
+        return 0.0;
+    }
+    let k = (x * core::f32::consts::LOG2_E - 0.5) as i32;
+    let r = x - k as f32 * core::f32::consts::LN_2;
+    let p = 1.0 + r * (1.0 + r * (0.5 + r * (1.0 / 6.0 + r * (1.0 / 24.0 + r * (1.0 / 120.0 + r / 720.0)))));
+    p * f32::from_bits(((k + 127) as u32) << 23)
+}
+
+/// Class probabilities for raw measurements, in `CLASS_LABELS` order.
+pub fn probabilities(weight: f32, size: f32, width: f32, height: f32) -> [f32; CLASSES] {
+    let input = [weight, size, width, height];
+    let mut hidden = BIAS1;
+    for (((value, mean), std), weights) in input.iter().zip(&MEAN).zip(&STD).zip(&WEIGHTS1) {
+        let x = (value - mean) / std;
+        for (h, w) in hidden.iter_mut().zip(weights) {
+            *h += x * w;
+        }
+    }
+    let mut logits = BIAS2;
+    for (&h, weights) in hidden.iter().zip(&WEIGHTS2) {
+        let h = if h > 0.0 { h } else { 0.0 };
+        for (l, w) in logits.iter_mut().zip(weights) {
+            *l += h * w;
+        }
+    }
+
+    let max = logits.iter().fold(f32::NEG_INFINITY, |m, &l| if l > m { l } else { m });
+    let mut sum = 0.0;
+    for l in logits.iter_mut() {
+        *l = exp(*l - max);
+        sum += *l;
+    }
+    for l in logits.iter_mut() {
+        *l /= sum;
+    }
+    logits
+}
+
+/// The most likely class label, or \"unknown\" below `UNKNOWN_THRESHOLD`.
+pub fn classify(weight: f32, size: f32, width: f32, height: f32) -> &'static str {
+    let probabilities = probabilities(weight, size, width, height);
+    let mut best = 0;
+    for (j, &p) in probabilities.iter().enumerate().skip(1) {
+        if p > probabilities[best] {
+            best = j;
+        }
+    }
+    if probabilities[best] < UNKNOWN_THRESHOLD {
+        \"unknown\"
+    } else {
+        CLASS_LABELS[best]
+    }
+}
+",
+    );
+    Ok(source)
+}
+
+// Folded layers, refusing weights that have no float literal
+fn inference_layers(result: &TrainingResult) -> Result<(DenseLayer, DenseLayer), Box<dyn Error>> {
+    let (hidden, output) = result.model.inference_layers();
+    let normalizer = &result.normalizer;
+    let mut parameters = normalizer.mean.iter().chain(&normalizer.std);
+    let mut layers = hidden.weights.iter().chain(&hidden.bias).chain(&output.weights).chain(&output.bias);
+    if !parameters.all(|v| v.is_finite()) || !layers.all(|v| v.is_finite()) {
+        return Err("Model has non-finite parameters".into());
+    }
+    Ok((hidden, output))
+}
+
+// Shortest literal that reads back as the same float32
+fn float(value: f64) -> String {
+    format!("{:?}", value as f32)
+}
+
+// C string literal with the UTF-8 bytes of `text`. Anything outside
+// printable ASCII becomes a three-digit octal escape, which cannot run into
+// the next character, and `?` is escaped so no trigraph forms.
+fn c_string(text: &str) -> String {
+    let mut literal = String::from("\"");
+    for byte in text.bytes() {
+        match byte {
+            b'"' | b'\\' | b'?' => {
+                literal.push('\\');
+                literal.push(byte as char);
+            }
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+fn c_vector(values: &Array1<f64>) -> String {
+    let values: Vec<String> = values.iter().map(|&v| float(v) + "f").collect();
+    format!("{{{}}}", values.join(", "))
+}
+
+fn c_matrix(values: &Array2<f64>) -> String {
+    let rows: Vec<String> = values.rows().into_iter().map(|row| format!("    {}", c_vector(&row.to_owned()))).collect();
+    format!("{{\n{}\n}}", rows.join(",\n"))
+}
+
+fn rust_vector(values: &Array1<f64>) -> String {
+    let values: Vec<String> = values.iter().map(|&v| float(v)).collect();
+    format!("[{}]", values.join(", "))
+}
+
+fn rust_matrix(values: &Array2<f64>) -> String {
+    let rows: Vec<String> = values.rows().into_iter().map(|row| format!("    {},", rust_vector(&row.to_owned()))).collect();
+    format!("[\n{}\n]", rows.join("\n"))
+}
@@ -0,0 +1,74 @@
+// compare.rs
+use crate::baselines::{GaussianNaiveBayes, KNearestNeighbors, LogisticRegression, LogisticRegressionParams};
+use crate::boosting::GradientBoosting;
+use crate::classifier::{Classifier, Metrics};
+use crate::config::TrainingConfig;
+use crate::data::FruitSample;
+use crate::forest::RandomForest;
+use crate::training::{self, PreparedSplit, TrainingError};
+use crate::tree::{DecisionTree, TreeParams};
+use serde::Serialize;
+use std::time::Instant;
+
+/// Scores of one model on the shared training/validation split.
+#[derive(Debug, Clone, Serialize)]
+pub struct ModelReport {
+    pub model: String,
+    pub train: Metrics,
+    pub validation: Metrics,
+    pub fit_seconds: f64,
+}
+
+impl ModelReport {
+    fn new<C: Classifier + ?Sized>(model: &C, data: &PreparedSplit, fit_seconds: f64) -> Self {
+        ModelReport {
+            model: model.name().to_string(),
+            train: Metrics::evaluate(model, &data.train_features, &data.train_labels),
+            validation: Metrics::evaluate(model, &data.val_features, &data.val_labels),
+            fit_seconds,
+        }
+    }
+}
+
+/// Compares the baselines with the neural network on the dataset named in
+/// `config.data`.
+pub fn run_comparison(config: &TrainingConfig) -> Result<Vec<ModelReport>, TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    compare_models(&samples, config)
+}
+
+/// Fits every baseline, the tree ensembles and the neural network on the
+/// split and scaler described by `config` and scores them on the same
+/// validation set.
+pub fn compare_models(samples: &[FruitSample], config: &TrainingConfig) -> Result<Vec<ModelReport>, TrainingError> {
+    config.validate()?;
+    let mut rng = training::seeded_rng(config.seed);
+    let (train, val) = training::split_samples(samples, config, &mut rng);
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    let (x, y) = (&data.train_features, &data.train_labels);
+
+    // The network is trained first so that its initialization does not
+    // depend on how much randomness the tree ensembles consume
+    let start = Instant::now();
+    let network = training::train_prepared(data.clone(), config, &mut rng, |_| true)?;
+    let network_seconds = start.elapsed().as_secs_f64();
+
+    let mut reports = vec![
+        fit_and_report(&data, || KNearestNeighbors::fit(x, y, KNearestNeighbors::DEFAULT_K)),
+        fit_and_report(&data, || LogisticRegression::fit(x, y, &LogisticRegressionParams::default())),
+        fit_and_report(&data, || GaussianNaiveBayes::fit(x, y)),
+        fit_and_report(&data, || DecisionTree::fit(x, y, &TreeParams::default())),
+        fit_and_report(&data, || RandomForest::fit(x, y, &config.forest, &mut rng)),
+        fit_and_report(&data, || GradientBoosting::fit(x, y, &config.boosting, &mut rng)),
+    ];
+    reports.push(ModelReport::new(&network.model, &data, network_seconds));
+
+    Ok(reports)
+}
+
+fn fit_and_report<C: Classifier>(data: &PreparedSplit, fit: impl FnOnce() -> C) -> ModelReport {
+    let start = Instant::now();
+    let model = fit();
+    ModelReport::new(&model, data, start.elapsed().as_secs_f64())
+}
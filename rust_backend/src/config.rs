@@ -0,0 +1,885 @@
+// config.rs
+use crate::data::DataSchema;
+use crate::precision::Precision;
+use crate::utils::{ScalerKind, FEATURE_NAMES};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Everything needed to reproduce a training run. Every section is optional
+/// in a config file; missing values fall back to the defaults below.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TrainingConfig {
+    /// Seeds shuffling and weight initialization; random when absent
+    pub seed: Option<u64>,
+    pub data: DataConfig,
+    pub split: SplitConfig,
+    /// Rebalancing of the classes in the training split
+    pub balancing: Balancing,
+    pub augmentation: AugmentationConfig,
+    pub scaler: ScalerKind,
+    pub model: ModelConfig,
+    pub optimizer: OptimizerConfig,
+    pub schedule: ScheduleConfig,
+    pub regularization: RegularizationConfig,
+    pub loss: LossConfig,
+    pub early_stopping: EarlyStoppingConfig,
+    pub ensemble: EnsembleConfig,
+    pub forest: ForestConfig,
+    pub boosting: BoostingConfig,
+    pub importance: ImportanceConfig,
+    pub boundary: BoundaryConfig,
+    pub embedding: EmbeddingConfig,
+    pub quantization: QuantizationConfig,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DataConfig {
+    pub path: String,
+    pub schema: DataSchema,
+}
+
+impl Default for DataConfig {
+    fn default() -> Self {
+        DataConfig {
+            path: "dataset/fruits_dataset.csv".to_string(),
+            schema: DataSchema::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SplitConfig {
+    /// Fraction of the samples used for training, the rest is held out for validation
+    pub train: f64,
+    pub shuffle: bool,
+}
+
+impl Default for SplitConfig {
+    fn default() -> Self {
+        SplitConfig {
+            train: 0.8,
+            shuffle: true,
+        }
+    }
+}
+
+/// Resampling of the normalized training split until every class has the
+/// same number of samples. The validation split is never resampled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Balancing {
+    #[default]
+    None,
+    /// Duplicates random samples of the smaller classes up to the size of
+    /// the largest
+    Oversample,
+    /// Keeps a random subset of every class the size of the smallest
+    Undersample,
+    /// Adds synthetic samples to the smaller classes, interpolated between a
+    /// sample and one of its `k_neighbors` nearest neighbours of the same
+    /// class
+    Smote { k_neighbors: usize },
+}
+
+/// Perturbations of the training samples, drawn anew every epoch. Noise and
+/// rounding act on the raw measurements in grams and centimetres, in the
+/// order weight, size, width, height.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AugmentationConfig {
+    pub noise: Noise,
+    /// Probability that a measurement is rounded to its unit, as a coarser
+    /// scale or caliper would report it
+    pub rounding: f64,
+    pub rounding_units: [f64; 4],
+    /// Concentration of the Beta distribution mixup weights are drawn from;
+    /// 0 disables mixup
+    pub mixup_alpha: f64,
+}
+
+impl Default for AugmentationConfig {
+    fn default() -> Self {
+        AugmentationConfig {
+            noise: Noise::None,
+            rounding: 0.0,
+            rounding_units: [1.0, 0.5, 0.5, 0.5],
+            mixup_alpha: 0.0,
+        }
+    }
+}
+
+impl AugmentationConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.noise != Noise::None || self.rounding > 0.0 || self.mixup_alpha > 0.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Noise {
+    #[default]
+    None,
+    /// Adds zero-mean normal noise with a standard deviation per feature
+    Gaussian { std: [f64; 4] },
+    /// Multiplies each measurement by `1 + e` with `e` normal with standard
+    /// deviation `relative`
+    Multiplicative { relative: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ModelConfig {
+    pub hidden_size: usize,
+    /// Fraction of hidden activations dropped while training; 0 disables
+    /// dropout
+    pub dropout: f64,
+    /// Batch-normalize the hidden pre-activations
+    pub batch_norm: bool,
+    /// Floating-point type the network is trained in
+    pub precision: Precision,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        ModelConfig {
+            hidden_size: 16,
+            dropout: 0.0,
+            batch_norm: false,
+            precision: Precision::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OptimizerConfig {
+    pub learning_rate: f64,
+    pub batch_size: usize,
+    /// 0.0 is plain mini-batch SGD
+    pub momentum: f64,
+}
+
+impl Default for OptimizerConfig {
+    fn default() -> Self {
+        OptimizerConfig {
+            learning_rate: 0.01,
+            batch_size: 32,
+            momentum: 0.0,
+        }
+    }
+}
+
+/// Largest `schedule.epochs` a config may ask for.
+pub const MAX_EPOCHS: usize = 1_000_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScheduleConfig {
+    pub epochs: usize,
+    pub learning_rate: LrSchedule,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        ScheduleConfig {
+            epochs: 5000,
+            learning_rate: LrSchedule::Constant,
+        }
+    }
+}
+
+/// Learning-rate decay applied on top of `optimizer.learning_rate`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LrSchedule {
+    #[default]
+    Constant,
+    /// Multiplies the rate by `gamma` every `step_size` epochs
+    Step { step_size: usize, gamma: f64 },
+    /// Multiplies the rate by `gamma` every epoch
+    Exponential { gamma: f64 },
+}
+
+impl LrSchedule {
+    /// Learning rate for the 0-based `epoch`.
+    pub fn learning_rate(&self, base: f64, epoch: usize) -> f64 {
+        match *self {
+            LrSchedule::Constant => base,
+            LrSchedule::Step { step_size, gamma } => base * gamma.powi((epoch / step_size) as i32),
+            LrSchedule::Exponential { gamma } => base * gamma.powi(epoch as i32),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RegularizationConfig {
+    pub l2: f64,
+}
+
+impl Default for RegularizationConfig {
+    fn default() -> Self {
+        RegularizationConfig { l2: 0.001 }
+    }
+}
+
+/// Objective minimized by the network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LossConfig {
+    pub function: LossFunction,
+    pub class_weights: ClassWeights,
+    /// Mass moved from the true class to a uniform distribution over all
+    /// classes; 0 keeps one-hot targets
+    pub label_smoothing: f64,
+}
+
+impl Default for LossConfig {
+    fn default() -> Self {
+        LossConfig {
+            function: LossFunction::CrossEntropy,
+            class_weights: ClassWeights::None,
+            label_smoothing: 0.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum LossFunction {
+    #[default]
+    CrossEntropy,
+    /// Cross-entropy scaled by `(1 - p)^gamma`, which down-weights samples
+    /// that are already classified confidently
+    Focal { gamma: f64 },
+}
+
+/// Per-class multipliers of each sample's loss.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ClassWeights {
+    #[default]
+    None,
+    /// Inversely proportional to the class frequencies in the training
+    /// split, `n_samples / (n_classes * n_class_samples)`
+    Balanced,
+    /// Weights by class name; classes not listed keep a weight of 1
+    Manual { weights: BTreeMap<String, f64> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Monitor {
+    #[default]
+    ValLoss,
+    ValAccuracy,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EarlyStoppingConfig {
+    pub enabled: bool,
+    pub monitor: Monitor,
+    /// Epochs without improvement before training stops
+    pub patience: usize,
+    /// Smallest change of the monitored value that counts as an improvement
+    pub min_delta: f64,
+    /// Roll the model back to the best epoch when stopping
+    pub restore_best: bool,
+}
+
+impl Default for EarlyStoppingConfig {
+    fn default() -> Self {
+        EarlyStoppingConfig {
+            enabled: false,
+            monitor: Monitor::ValLoss,
+            patience: 200,
+            min_delta: 0.0,
+            restore_best: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Resampling {
+    /// Each member trains on a bootstrap sample of the training split
+    #[default]
+    Bootstrap,
+    /// Each member trains on the whole training split with its own seed
+    Seeds,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Combination {
+    /// Averages the members' class probabilities
+    #[default]
+    SoftVoting,
+    /// Feeds the members' probabilities to a logistic regression fitted on
+    /// a held-out part of the training split
+    Stacking,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EnsembleConfig {
+    /// Networks trained by `ensemble::train_ensemble`
+    pub members: usize,
+    pub resampling: Resampling,
+    pub combination: Combination,
+    /// Fraction of the training split held back for the stacking combiner
+    pub stacking_holdout: f64,
+    /// Predictions on which a larger fraction of the members disagree with
+    /// the ensemble are reported as "unknown"
+    pub max_disagreement: f64,
+}
+
+impl Default for EnsembleConfig {
+    fn default() -> Self {
+        EnsembleConfig {
+            members: 5,
+            resampling: Resampling::Bootstrap,
+            combination: Combination::SoftVoting,
+            stacking_holdout: 0.2,
+            max_disagreement: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ForestConfig {
+    pub n_trees: usize,
+    pub max_depth: usize,
+    pub min_samples_leaf: usize,
+    /// Features considered at each split; all four when absent
+    pub max_features: Option<usize>,
+    /// Train each tree on a bootstrap sample instead of the whole split
+    pub bootstrap: bool,
+}
+
+impl Default for ForestConfig {
+    fn default() -> Self {
+        ForestConfig {
+            n_trees: 100,
+            max_depth: 10,
+            min_samples_leaf: 1,
+            max_features: Some(2),
+            bootstrap: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoostingConfig {
+    /// Boosting rounds; each round adds one tree per class
+    pub n_rounds: usize,
+    /// Shrinkage applied to every tree's contribution
+    pub learning_rate: f64,
+    pub max_depth: usize,
+    pub min_samples_leaf: usize,
+    /// Fraction of the training rows drawn, without replacement, per round
+    pub subsample: f64,
+}
+
+impl Default for BoostingConfig {
+    fn default() -> Self {
+        BoostingConfig {
+            n_rounds: 100,
+            learning_rate: 0.1,
+            max_depth: 3,
+            min_samples_leaf: 1,
+            subsample: 1.0,
+        }
+    }
+}
+
+/// Permutation feature importance on the validation split.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ImportanceConfig {
+    /// Shuffles of each feature; the spread across them gives the
+    /// confidence intervals
+    pub repeats: usize,
+    /// Coverage of the confidence intervals
+    pub confidence: f64,
+    /// PNG bar chart of both importance measures
+    pub output: String,
+}
+
+impl Default for ImportanceConfig {
+    fn default() -> Self {
+        ImportanceConfig {
+            repeats: 10,
+            confidence: 0.95,
+            output: "feature_importance.png".to_string(),
+        }
+    }
+}
+
+/// Values of the two features that are not on the axes of a decision
+/// boundary plot.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum HeldFeatures {
+    /// Means of the training split
+    #[default]
+    Mean,
+    /// Raw measurements (weight, size, width, height) of one fruit
+    Sample { measurements: [f64; 4] },
+}
+
+/// Decision regions of the network over two features.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BoundaryConfig {
+    /// Feature on the horizontal axis: "weight", "size", "width" or "height"
+    pub x: String,
+    /// Feature on the vertical axis
+    pub y: String,
+    pub held: HeldFeatures,
+    /// Grid cells along each axis
+    pub resolution: usize,
+    /// Written as SVG when the extension is `.svg`, otherwise as PNG
+    pub output: String,
+}
+
+impl Default for BoundaryConfig {
+    fn default() -> Self {
+        BoundaryConfig {
+            x: "weight".to_string(),
+            y: "size".to_string(),
+            held: HeldFeatures::Mean,
+            resolution: 200,
+            output: "decision_boundary.png".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmbeddedSamples {
+    /// The held-out split, where mistakes are informative
+    #[default]
+    Validation,
+    /// Training and validation splits together
+    All,
+}
+
+/// 2-D projections of the network's hidden activations.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmbeddingConfig {
+    pub samples: EmbeddedSamples,
+    /// Effective number of neighbours each point keeps close in t-SNE
+    pub perplexity: f64,
+    /// Gradient descent steps of t-SNE
+    pub iterations: usize,
+    pub learning_rate: f64,
+    pub output: String,
+}
+
+impl Default for EmbeddingConfig {
+    fn default() -> Self {
+        EmbeddingConfig {
+            samples: EmbeddedSamples::Validation,
+            perplexity: 30.0,
+            iterations: 1000,
+            learning_rate: 200.0,
+            output: "embedding.png".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    /// One scale for a whole weight matrix
+    PerTensor,
+    /// One scale per output unit, i.e. per column of a weight matrix
+    #[default]
+    PerChannel,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantScheme {
+    /// Zero point 0 and a range symmetric around zero
+    #[default]
+    Symmetric,
+    /// The observed range mapped onto all 256 values with a zero point
+    Asymmetric,
+}
+
+/// Post-training int8 quantization of the network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct QuantizationConfig {
+    /// Scales of the weights; activations always have one scale per tensor
+    pub granularity: Granularity,
+    /// Applies to weights and activations
+    pub scheme: QuantScheme,
+    /// Training samples whose activations set the activation ranges
+    pub calibration_samples: usize,
+}
+
+impl Default for QuantizationConfig {
+    fn default() -> Self {
+        QuantizationConfig {
+            granularity: Granularity::PerChannel,
+            scheme: QuantScheme::Symmetric,
+            calibration_samples: 100,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String),
+    Parse(String),
+    Invalid { field: &'static str, message: String },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(msg) => write!(f, "{}", msg),
+            ConfigError::Parse(msg) => write!(f, "Invalid config: {}", msg),
+            ConfigError::Invalid { field, message } => write!(f, "Invalid config value `{}`: {}", field, message),
+        }
+    }
+}
+
+impl Error for ConfigError {}
+
+pub(crate) fn invalid(field: &'static str, message: impl Into<String>) -> ConfigError {
+    ConfigError::Invalid {
+        field,
+        message: message.into(),
+    }
+}
+
+/// Reads a `.toml` or `.json` file, choosing the format by extension.
+pub(crate) fn parse_file<T: DeserializeOwned>(path: &Path) -> Result<T, ConfigError> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| ConfigError::Io(format!("Failed to read config '{}': {}", path.display(), e)))?;
+
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&text).map_err(|e| ConfigError::Parse(e.to_string())),
+        Some("json") => serde_json::from_str(&text).map_err(|e| ConfigError::Parse(e.to_string())),
+        _ => Err(ConfigError::Parse(format!(
+            "'{}' must have a .toml or .json extension",
+            path.display()
+        ))),
+    }
+}
+
+impl TrainingConfig {
+    /// Loads and validates a `.toml` or `.json` config file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let config: TrainingConfig = parse_file(path.as_ref())?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let config: TrainingConfig = toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_json_str(text: &str) -> Result<Self, ConfigError> {
+        let config: TrainingConfig = serde_json::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_toml_string(&self) -> String {
+        toml::to_string_pretty(self).expect("TrainingConfig is always representable as TOML")
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.data.path.trim().is_empty() {
+            return Err(invalid("data.path", "must not be empty"));
+        }
+        let schema = &self.data.schema;
+        for (field, column) in [
+            ("data.schema.weight", &schema.weight),
+            ("data.schema.size", &schema.size),
+            ("data.schema.width", &schema.width),
+            ("data.schema.height", &schema.height),
+            ("data.schema.label", &schema.label),
+        ] {
+            if column.trim().is_empty() {
+                return Err(invalid(field, "column name must not be empty"));
+            }
+        }
+
+        if !(self.split.train > 0.0 && self.split.train < 1.0) {
+            return Err(invalid("split.train", format!("must be between 0 and 1, got {}", self.split.train)));
+        }
+        if let Balancing::Smote { k_neighbors: 0 } = self.balancing {
+            return Err(invalid("balancing.k_neighbors", "must be at least 1"));
+        }
+        let augmentation = &self.augmentation;
+        match augmentation.noise {
+            Noise::None => {}
+            Noise::Gaussian { std } => {
+                if let Some(std) = std.iter().find(|s| !(s.is_finite() && **s >= 0.0)) {
+                    return Err(invalid(
+                        "augmentation.noise.std",
+                        format!("must be non-negative, got {}", std),
+                    ));
+                }
+            }
+            Noise::Multiplicative { relative } => {
+                if !(relative.is_finite() && relative >= 0.0) {
+                    return Err(invalid(
+                        "augmentation.noise.relative",
+                        format!("must be non-negative, got {}", relative),
+                    ));
+                }
+            }
+        }
+        if !(0.0..=1.0).contains(&augmentation.rounding) {
+            return Err(invalid(
+                "augmentation.rounding",
+                format!("must be between 0 and 1, got {}", augmentation.rounding),
+            ));
+        }
+        if let Some(unit) = augmentation.rounding_units.iter().find(|u| !(u.is_finite() && **u > 0.0)) {
+            return Err(invalid(
+                "augmentation.rounding_units",
+                format!("must be positive, got {}", unit),
+            ));
+        }
+        if !(augmentation.mixup_alpha.is_finite() && augmentation.mixup_alpha >= 0.0) {
+            return Err(invalid(
+                "augmentation.mixup_alpha",
+                format!("must be non-negative, got {}", augmentation.mixup_alpha),
+            ));
+        }
+
+        if self.model.hidden_size == 0 {
+            return Err(invalid("model.hidden_size", "must be at least 1"));
+        }
+        if !(0.0..1.0).contains(&self.model.dropout) {
+            return Err(invalid("model.dropout", format!("must be in [0, 1), got {}", self.model.dropout)));
+        }
+
+        let lr = self.optimizer.learning_rate;
+        if !(lr.is_finite() && lr > 0.0) {
+            return Err(invalid("optimizer.learning_rate", format!("must be positive, got {}", lr)));
+        }
+        if self.optimizer.batch_size == 0 {
+            return Err(invalid("optimizer.batch_size", "must be at least 1"));
+        }
+        if !(0.0..1.0).contains(&self.optimizer.momentum) {
+            return Err(invalid(
+                "optimizer.momentum",
+                format!("must be in [0, 1), got {}", self.optimizer.momentum),
+            ));
+        }
+
+        if self.schedule.epochs == 0 {
+            return Err(invalid("schedule.epochs", "must be at least 1"));
+        }
+        if self.schedule.epochs > MAX_EPOCHS {
+            return Err(invalid(
+                "schedule.epochs",
+                format!("must be at most {}, got {}", MAX_EPOCHS, self.schedule.epochs),
+            ));
+        }
+        match self.schedule.learning_rate {
+            LrSchedule::Constant => {}
+            LrSchedule::Step { step_size, gamma } => {
+                if step_size == 0 {
+                    return Err(invalid("schedule.learning_rate.step_size", "must be at least 1"));
+                }
+                if !(gamma > 0.0 && gamma <= 1.0) {
+                    return Err(invalid(
+                        "schedule.learning_rate.gamma",
+                        format!("must be in (0, 1], got {}", gamma),
+                    ));
+                }
+            }
+            LrSchedule::Exponential { gamma } => {
+                if !(gamma > 0.0 && gamma <= 1.0) {
+                    return Err(invalid(
+                        "schedule.learning_rate.gamma",
+                        format!("must be in (0, 1], got {}", gamma),
+                    ));
+                }
+            }
+        }
+
+        let l2 = self.regularization.l2;
+        if !(l2.is_finite() && l2 >= 0.0) {
+            return Err(invalid("regularization.l2", format!("must be non-negative, got {}", l2)));
+        }
+
+        let loss = &self.loss;
+        if let LossFunction::Focal { gamma } = loss.function
+            && !(gamma.is_finite() && gamma >= 0.0)
+        {
+            return Err(invalid("loss.function.gamma", format!("must be non-negative, got {}", gamma)));
+        }
+        if let ClassWeights::Manual { weights } = &loss.class_weights
+            && let Some((class, weight)) = weights.iter().find(|(_, w)| !(w.is_finite() && **w > 0.0))
+        {
+            return Err(invalid(
+                "loss.class_weights.weights",
+                format!("weight of '{}' must be positive, got {}", class, weight),
+            ));
+        }
+        if !(0.0..1.0).contains(&loss.label_smoothing) {
+            return Err(invalid(
+                "loss.label_smoothing",
+                format!("must be in [0, 1), got {}", loss.label_smoothing),
+            ));
+        }
+
+        let early = &self.early_stopping;
+        if early.enabled && early.patience == 0 {
+            return Err(invalid("early_stopping.patience", "must be at least 1"));
+        }
+        if !(early.min_delta.is_finite() && early.min_delta >= 0.0) {
+            return Err(invalid(
+                "early_stopping.min_delta",
+                format!("must be non-negative, got {}", early.min_delta),
+            ));
+        }
+
+        let ensemble = &self.ensemble;
+        if ensemble.members == 0 {
+            return Err(invalid("ensemble.members", "must be at least 1"));
+        }
+        if !(ensemble.stacking_holdout > 0.0 && ensemble.stacking_holdout < 1.0) {
+            return Err(invalid(
+                "ensemble.stacking_holdout",
+                format!("must be between 0 and 1, got {}", ensemble.stacking_holdout),
+            ));
+        }
+        if !(0.0..=1.0).contains(&ensemble.max_disagreement) {
+            return Err(invalid(
+                "ensemble.max_disagreement",
+                format!("must be in [0, 1], got {}", ensemble.max_disagreement),
+            ));
+        }
+
+        let forest = &self.forest;
+        if forest.n_trees == 0 {
+            return Err(invalid("forest.n_trees", "must be at least 1"));
+        }
+        if forest.max_depth == 0 {
+            return Err(invalid("forest.max_depth", "must be at least 1"));
+        }
+        if forest.min_samples_leaf == 0 {
+            return Err(invalid("forest.min_samples_leaf", "must be at least 1"));
+        }
+        if let Some(max_features) = forest.max_features
+            && !(1..=4).contains(&max_features)
+        {
+            return Err(invalid("forest.max_features", format!("must be between 1 and 4, got {}", max_features)));
+        }
+
+        let boosting = &self.boosting;
+        if boosting.n_rounds == 0 {
+            return Err(invalid("boosting.n_rounds", "must be at least 1"));
+        }
+        if !(boosting.learning_rate.is_finite() && boosting.learning_rate > 0.0) {
+            return Err(invalid(
+                "boosting.learning_rate",
+                format!("must be positive, got {}", boosting.learning_rate),
+            ));
+        }
+        if boosting.max_depth == 0 {
+            return Err(invalid("boosting.max_depth", "must be at least 1"));
+        }
+        if boosting.min_samples_leaf == 0 {
+            return Err(invalid("boosting.min_samples_leaf", "must be at least 1"));
+        }
+        if !(boosting.subsample > 0.0 && boosting.subsample <= 1.0) {
+            return Err(invalid(
+                "boosting.subsample",
+                format!("must be in (0, 1], got {}", boosting.subsample),
+            ));
+        }
+
+        let importance = &self.importance;
+        if importance.repeats < 3 {
+            return Err(invalid("importance.repeats", "must be at least 3"));
+        }
+        if !(importance.confidence > 0.0 && importance.confidence < 1.0) {
+            return Err(invalid(
+                "importance.confidence",
+                format!("must be between 0 and 1, got {}", importance.confidence),
+            ));
+        }
+
+        let boundary = &self.boundary;
+        for (field, name) in [("boundary.x", &boundary.x), ("boundary.y", &boundary.y)] {
+            if !FEATURE_NAMES.contains(&name.as_str()) {
+                return Err(invalid(
+                    field,
+                    format!("must be one of {}, got '{}'", FEATURE_NAMES.join(", "), name),
+                ));
+            }
+        }
+        if boundary.x == boundary.y {
+            return Err(invalid("boundary.y", "must differ from boundary.x"));
+        }
+        if let HeldFeatures::Sample { measurements } = boundary.held
+            && let Some(value) = measurements.iter().find(|v| !(v.is_finite() && **v > 0.0))
+        {
+            return Err(invalid(
+                "boundary.held.measurements",
+                format!("must be positive, got {}", value),
+            ));
+        }
+        if boundary.resolution < 2 {
+            return Err(invalid("boundary.resolution", "must be at least 2"));
+        }
+
+        let embedding = &self.embedding;
+        if !(embedding.perplexity.is_finite() && embedding.perplexity >= 1.0) {
+            return Err(invalid(
+                "embedding.perplexity",
+                format!("must be at least 1, got {}", embedding.perplexity),
+            ));
+        }
+        if embedding.iterations == 0 {
+            return Err(invalid("embedding.iterations", "must be at least 1"));
+        }
+        if !(embedding.learning_rate.is_finite() && embedding.learning_rate > 0.0) {
+            return Err(invalid(
+                "embedding.learning_rate",
+                format!("must be positive, got {}", embedding.learning_rate),
+            ));
+        }
+        for (field, output) in [
+            ("importance.output", &importance.output),
+            ("boundary.output", &boundary.output),
+            ("embedding.output", &embedding.output),
+        ] {
+            if output.trim().is_empty() {
+                return Err(invalid(field, "must not be empty"));
+            }
+        }
+        if self.quantization.calibration_samples == 0 {
+            return Err(invalid("quantization.calibration_samples", "must be at least 1"));
+        }
+
+        Ok(())
+    }
+}
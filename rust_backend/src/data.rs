@@ -1,5 +1,5 @@
 use csv::ReaderBuilder;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::error::Error;
 
 #[derive(Debug, Deserialize, Clone)]
@@ -11,14 +11,84 @@ pub struct FruitSample {
     pub label: String,
 }
 
+/// Names of the CSV columns holding each measurement and the label.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DataSchema {
+    pub weight: String,
+    pub size: String,
+    pub width: String,
+    pub height: String,
+    pub label: String,
+}
+
+impl Default for DataSchema {
+    fn default() -> Self {
+        DataSchema {
+            weight: "weight".to_string(),
+            size: "size".to_string(),
+            width: "width".to_string(),
+            height: "height".to_string(),
+            label: "label".to_string(),
+        }
+    }
+}
+
 pub fn load_dataset(path: &str) -> Result<Vec<FruitSample>, Box<dyn Error>> {
+    load_dataset_with_schema(path, &DataSchema::default())
+}
+
+pub fn load_dataset_with_schema(path: &str, schema: &DataSchema) -> Result<Vec<FruitSample>, Box<dyn Error>> {
+    read_samples(path, schema, Some(&schema.label))
+}
+
+/// Reads unlabeled measurements in the order weight, size, width, height;
+/// the label column may be absent.
+pub fn load_measurements_with_schema(path: &str, schema: &DataSchema) -> Result<Vec<[f64; 4]>, Box<dyn Error>> {
+    let samples = read_samples(path, schema, None)?;
+    Ok(samples.iter().map(|s| [s.weight, s.size, s.width, s.height]).collect())
+}
+
+// Samples are left unlabeled when `label` is `None`
+fn read_samples(path: &str, schema: &DataSchema, label: Option<&str>) -> Result<Vec<FruitSample>, Box<dyn Error>> {
     let mut rdr = ReaderBuilder::new()
         .has_headers(true)
         .from_path(path)?;
 
+    let headers = rdr.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|h| h.trim() == name)
+            .ok_or_else(|| format!("Column '{}' not found in dataset", name))
+    };
+    let columns = [
+        column(&schema.weight)?,
+        column(&schema.size)?,
+        column(&schema.width)?,
+        column(&schema.height)?,
+    ];
+    let label_column = label.map(column).transpose()?;
+
     let mut samples = Vec::new();
-    for result in rdr.deserialize() {
-        let record: FruitSample = result?;
+    for (row, result) in rdr.records().enumerate() {
+        let record = result?;
+        let mut values = [0.0; 4];
+        for (value, &col) in values.iter_mut().zip(columns.iter()) {
+            let field = record.get(col).unwrap_or("").trim();
+            *value = field
+                .parse()
+                .map_err(|_| format!("Invalid number '{}' in row {}", field, row + 2))?;
+        }
+        let record = FruitSample {
+            weight: values[0],
+            size: values[1],
+            width: values[2],
+            height: values[3],
+            label: label_column
+                .map(|col| record.get(col).unwrap_or("").trim().to_string())
+                .unwrap_or_default(),
+        };
         
         // Basic validation
         if record.weight <= 0.0 || record.size <= 0.0 || 
@@ -34,4 +104,4 @@ pub fn load_dataset(path: &str) -> Result<Vec<FruitSample>, Box<dyn Error>> {
     }
 
     Ok(samples)
-}
\ No newline at end of file
+}
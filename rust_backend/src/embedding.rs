@@ -0,0 +1,332 @@
+// embedding.rs
+use crate::classifier::argmax_rows;
+use crate::config::{EmbeddedSamples, EmbeddingConfig, TrainingConfig};
+use crate::data::FruitSample;
+use crate::model::NeuralNet;
+use crate::training::{self, PreparedSplit, TrainingError, TrainingResult};
+use ndarray::{concatenate, s, Array1, Array2, Axis};
+use plotters::coord::types::RangedCoordf64;
+use plotters::coord::Shift;
+use plotters::prelude::*;
+use std::error::Error;
+use std::path::Path;
+
+/// Hidden-layer activations of a set of samples projected to two
+/// dimensions, with the true and predicted class of each sample.
+#[derive(Debug, Clone)]
+pub struct Embedding {
+    pub class_names: Vec<String>,
+    pub labels: Vec<usize>,
+    pub predictions: Vec<usize>,
+    pub pca: Array2<f64>,
+    pub tsne: Array2<f64>,
+}
+
+impl Embedding {
+    /// Projects the inference-mode hidden activations of normalized
+    /// `features` with PCA and t-SNE.
+    pub fn new(model: &NeuralNet, class_names: &[String], features: &Array2<f64>, labels: &Array2<f64>, settings: &EmbeddingConfig) -> Self {
+        let (_, hidden, output) = model.forward(features);
+        Embedding {
+            class_names: class_names.to_vec(),
+            labels: argmax_rows(labels),
+            predictions: argmax_rows(&output),
+            pca: pca(&hidden, 2),
+            tsne: tsne(&hidden, settings),
+        }
+    }
+
+    /// Misclassified samples counted per (true class, predicted class) pair,
+    /// most frequent first.
+    pub fn confusions(&self) -> Vec<(&str, &str, usize)> {
+        let n = self.class_names.len();
+        let mut counts = vec![0; n * n];
+        for (&label, &prediction) in self.labels.iter().zip(&self.predictions) {
+            if label != prediction {
+                counts[label * n + prediction] += 1;
+            }
+        }
+        let mut pairs: Vec<_> = counts
+            .iter()
+            .enumerate()
+            .filter(|&(_, &count)| count > 0)
+            .map(|(i, &count)| (self.class_names[i / n].as_str(), self.class_names[i % n].as_str(), count))
+            .collect();
+        pairs.sort_by_key(|&(_, _, count)| std::cmp::Reverse(count));
+        pairs
+    }
+}
+
+/// Projection of the rows of `data` onto its first `components` principal
+/// axes. Each axis is oriented so that its largest coordinate is positive.
+pub fn pca(data: &Array2<f64>, components: usize) -> Array2<f64> {
+    let mean = data.mean_axis(Axis(0)).expect("data is not empty");
+    let centered = data - &mean;
+    let covariance = centered.t().dot(&centered) / data.nrows().max(2).saturating_sub(1) as f64;
+
+    let (values, mut vectors) = symmetric_eigen(covariance);
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+    for mut vector in vectors.columns_mut() {
+        let largest = vector.iter().copied().fold(0.0, |m: f64, v| if v.abs() > m.abs() { v } else { m });
+        if largest < 0.0 {
+            vector.mapv_inplace(|v| -v);
+        }
+    }
+
+    let axes = Array2::from_shape_fn((data.ncols(), components), |(i, k)| {
+        order.get(k).map_or(0.0, |&column| vectors[[i, column]])
+    });
+    centered.dot(&axes)
+}
+
+// Eigenvalues and eigenvectors (as columns) of a symmetric matrix with
+// the cyclic Jacobi method
+fn symmetric_eigen(mut a: Array2<f64>) -> (Array1<f64>, Array2<f64>) {
+    let n = a.nrows();
+    let mut vectors = Array2::eye(n);
+    for _ in 0..100 {
+        let off_diagonal: f64 = (0..n).flat_map(|p| (p + 1..n).map(move |q| (p, q))).map(|(p, q)| a[[p, q]].powi(2)).sum();
+        if off_diagonal < 1e-22 {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[[p, q]].abs() < 1e-300 {
+                    continue;
+                }
+                let theta = (a[[q, q]] - a[[p, p]]) / (2.0 * a[[p, q]]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let (c, s) = (1.0 / (t * t + 1.0).sqrt(), t / (t * t + 1.0).sqrt());
+                for k in 0..n {
+                    let (akp, akq) = (a[[k, p]], a[[k, q]]);
+                    a[[k, p]] = c * akp - s * akq;
+                    a[[k, q]] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[[p, k]], a[[q, k]]);
+                    a[[p, k]] = c * apk - s * aqk;
+                    a[[q, k]] = s * apk + c * aqk;
+                }
+                for k in 0..n {
+                    let (vkp, vkq) = (vectors[[k, p]], vectors[[k, q]]);
+                    vectors[[k, p]] = c * vkp - s * vkq;
+                    vectors[[k, q]] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    (a.diag().to_owned(), vectors)
+}
+
+// Early exaggeration of the affinities and the lower momentum apply to
+// the first iterations only
+const EXAGGERATION_ITERATIONS: usize = 250;
+const EXAGGERATION: f64 = 12.0;
+
+/// Exact t-SNE of the rows of `data` into two dimensions, starting from the
+/// scaled-down PCA projection so that the result is deterministic.
+pub fn tsne(data: &Array2<f64>, settings: &EmbeddingConfig) -> Array2<f64> {
+    let n = data.nrows();
+    if n < 2 {
+        return Array2::zeros((n, 2));
+    }
+    let p = joint_probabilities(data, settings.perplexity.min((n - 1) as f64));
+
+    let mut y = pca(data, 2);
+    let std = y.column(0).std(0.0);
+    y *= if std > 0.0 { 1e-4 / std } else { 0.0 };
+    let mut velocity = Array2::<f64>::zeros((n, 2));
+    let mut gains = Array2::<f64>::ones((n, 2));
+
+    for iteration in 0..settings.iterations {
+        let early = iteration < EXAGGERATION_ITERATIONS;
+        let (exaggeration, momentum) = if early { (EXAGGERATION, 0.5) } else { (1.0, 0.8) };
+
+        // Student-t kernel between embedded points
+        let mut kernel = Array2::<f64>::zeros((n, n));
+        for i in 0..n {
+            for j in i + 1..n {
+                let d = (y[[i, 0]] - y[[j, 0]]).powi(2) + (y[[i, 1]] - y[[j, 1]]).powi(2);
+                let k = 1.0 / (1.0 + d);
+                kernel[[i, j]] = k;
+                kernel[[j, i]] = k;
+            }
+        }
+        let total = kernel.sum().max(f64::MIN_POSITIVE);
+
+        let mut gradient = Array2::<f64>::zeros((n, 2));
+        for i in 0..n {
+            for j in 0..n {
+                let k = kernel[[i, j]];
+                let force = 4.0 * (exaggeration * p[[i, j]] - (k / total).max(1e-12)) * k;
+                gradient[[i, 0]] += force * (y[[i, 0]] - y[[j, 0]]);
+                gradient[[i, 1]] += force * (y[[i, 1]] - y[[j, 1]]);
+            }
+        }
+
+        for ((gain, v), g) in gains.iter_mut().zip(velocity.iter_mut()).zip(gradient.iter()) {
+            *gain = if (*g > 0.0) != (*v > 0.0) { *gain + 0.2 } else { *gain * 0.8 };
+            *gain = gain.max(0.01);
+            *v = momentum * *v - settings.learning_rate * *gain * g;
+        }
+        y += &velocity;
+        let mean = y.mean_axis(Axis(0)).expect("n >= 2");
+        y -= &mean;
+    }
+    y
+}
+
+// Symmetrized affinities with a Gaussian bandwidth per point chosen by
+// bisection so that each conditional distribution has the given perplexity
+fn joint_probabilities(data: &Array2<f64>, perplexity: f64) -> Array2<f64> {
+    let n = data.nrows();
+    let squared_norms = data.map_axis(Axis(1), |row| row.dot(&row));
+    let mut distances = data.dot(&data.t()) * -2.0;
+    for i in 0..n {
+        for j in 0..n {
+            distances[[i, j]] = (distances[[i, j]] + squared_norms[i] + squared_norms[j]).max(0.0);
+        }
+    }
+
+    let target_entropy = perplexity.ln();
+    let mut conditional = Array2::<f64>::zeros((n, n));
+    for i in 0..n {
+        let (mut beta, mut low, mut high) = (1.0, 0.0, f64::INFINITY);
+        for _ in 0..100 {
+            let row = distances.row(i);
+            let nearest = (0..n).filter(|&j| j != i).map(|j| row[j]).fold(f64::INFINITY, f64::min);
+            let mut sum = 0.0;
+            let mut weighted = 0.0;
+            for j in (0..n).filter(|&j| j != i) {
+                let w = (-beta * (row[j] - nearest)).exp();
+                conditional[[i, j]] = w;
+                sum += w;
+                weighted += w * (row[j] - nearest);
+            }
+            conditional.row_mut(i).mapv_inplace(|w| w / sum);
+            let entropy = sum.ln() + beta * weighted / sum;
+
+            if (entropy - target_entropy).abs() < 1e-5 {
+                break;
+            }
+            if entropy > target_entropy {
+                low = beta;
+                beta = if high.is_finite() { (beta + high) / 2.0 } else { beta * 2.0 };
+            } else {
+                high = beta;
+                beta = (beta + low) / 2.0;
+            }
+        }
+    }
+
+    let joint = (&conditional + &conditional.t()) / (2.0 * n as f64);
+    joint.mapv(|v| v.max(1e-12))
+}
+
+/// Trains a network on the dataset named in `config.data` and embeds the
+/// samples chosen by `config.embedding`.
+pub fn run_embedding(config: &TrainingConfig) -> Result<(TrainingResult, Embedding), TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    network_embedding(&samples, config)
+}
+
+/// Like `run_embedding`, on samples that are already loaded.
+pub fn network_embedding(samples: &[FruitSample], config: &TrainingConfig) -> Result<(TrainingResult, Embedding), TrainingError> {
+    config.validate()?;
+    let mut rng = training::seeded_rng(config.seed);
+    let (train, val) = training::split_samples(samples, config, &mut rng);
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    let result = training::train_prepared(data.clone(), config, &mut rng, |_| true)?;
+
+    let (features, labels) = match config.embedding.samples {
+        EmbeddedSamples::Validation => (data.val_features, data.val_labels),
+        EmbeddedSamples::All => (
+            concatenate![Axis(0), data.train_features, data.val_features],
+            concatenate![Axis(0), data.train_labels, data.val_labels],
+        ),
+    };
+    let embedding = Embedding::new(&result.model, &result.class_names, &features, &labels, &config.embedding);
+    Ok((result, embedding))
+}
+
+/// Scatter plots of both projections, colored by true class on the left and
+/// by whether the network got the sample right on the right.
+pub fn plot_embedding(embedding: &Embedding, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let root = BitMapBackend::new(path.as_ref(), (1400, 1200)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let panels = root.split_evenly((2, 2));
+
+    for (row, (name, points)) in [("PCA", &embedding.pca), ("t-SNE", &embedding.tsne)].into_iter().enumerate() {
+        draw_by_class(&panels[2 * row], &format!("{} by class", name), points, embedding)?;
+        draw_by_correctness(&panels[2 * row + 1], &format!("{} by correctness", name), points, embedding)?;
+    }
+
+    root.present()?;
+    Ok(())
+}
+
+type Panel<'a> = DrawingArea<BitMapBackend<'a>, Shift>;
+type Scatter<'a, 'b> = ChartContext<'a, BitMapBackend<'b>, Cartesian2d<RangedCoordf64, RangedCoordf64>>;
+
+fn scatter_chart<'a, 'b>(panel: &'a Panel<'b>, title: &str, points: &Array2<f64>) -> Result<Scatter<'a, 'b>, Box<dyn Error>> {
+    let range = |column: usize| {
+        let values = points.slice(s![.., column]);
+        let (low, high) = values.fold((f64::INFINITY, f64::NEG_INFINITY), |(low, high), &v| (low.min(v), high.max(v)));
+        let margin = (high - low).max(1e-6) * 0.05;
+        low - margin..high + margin
+    };
+    let mut chart = ChartBuilder::on(panel)
+        .caption(title, ("sans-serif", 26))
+        .margin(10)
+        .x_label_area_size(30)
+        .y_label_area_size(50)
+        .build_cartesian_2d(range(0), range(1))?;
+    chart.configure_mesh().disable_mesh().draw()?;
+    Ok(chart)
+}
+
+fn draw_by_class(panel: &Panel, title: &str, points: &Array2<f64>, embedding: &Embedding) -> Result<(), Box<dyn Error>> {
+    let mut chart = scatter_chart(panel, title, points)?;
+    for (class, name) in embedding.class_names.iter().enumerate() {
+        let color = Palette99::pick(class);
+        chart
+            .draw_series(
+                embedding
+                    .labels
+                    .iter()
+                    .zip(points.rows())
+                    .filter(|(label, _)| **label == class)
+                    .map(|(_, p)| Circle::new((p[0], p[1]), 3, color.filled())),
+            )?
+            .label(name.as_str())
+            .legend(move |(x, y)| Circle::new((x + 10, y), 4, color.filled()));
+    }
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    Ok(())
+}
+
+fn draw_by_correctness(panel: &Panel, title: &str, points: &Array2<f64>, embedding: &Embedding) -> Result<(), Box<dyn Error>> {
+    let mut chart = scatter_chart(panel, title, points)?;
+    let outcomes = || embedding.labels.iter().zip(&embedding.predictions).zip(points.rows());
+    let correct = GREEN.mix(0.5);
+    chart
+        .draw_series(
+            outcomes()
+                .filter(|((label, prediction), _)| label == prediction)
+                .map(|(_, p)| Circle::new((p[0], p[1]), 3, correct.filled())),
+        )?
+        .label("correct")
+        .legend(move |(x, y)| Circle::new((x + 10, y), 4, correct.filled()));
+    chart
+        .draw_series(
+            outcomes()
+                .filter(|((label, prediction), _)| label != prediction)
+                .map(|(_, p)| Cross::new((p[0], p[1]), 6, RED.stroke_width(2))),
+        )?
+        .label("misclassified")
+        .legend(|(x, y)| Cross::new((x + 10, y), 5, RED.stroke_width(2)));
+    chart.configure_series_labels().background_style(WHITE.mix(0.8)).border_style(BLACK).draw()?;
+    Ok(())
+}
@@ -0,0 +1,205 @@
+// ensemble.rs
+use crate::baselines::{LogisticRegression, LogisticRegressionParams};
+use crate::classifier::{argmax_rows, Classifier, Metrics};
+use crate::config::{Combination, Resampling, TrainingConfig};
+use crate::data::FruitSample;
+use crate::model::NeuralNet;
+use crate::training::{self, PreparedSplit, TrainingError, UNKNOWN_CONFIDENCE_THRESHOLD};
+use crate::utils::{encode_labels, measurement_row, Normalizer};
+use ndarray::{concatenate, Array2, ArrayView2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, RngCore, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+/// Several networks trained on resampled data or with different seeds,
+/// sharing one normalizer and class list.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Ensemble {
+    /// The resolved configuration, including the `ensemble` section
+    pub config: TrainingConfig,
+    pub members: Vec<NeuralNet>,
+    /// Logistic regression over the concatenated member probabilities, only
+    /// present when stacking
+    combiner: Option<LogisticRegression>,
+    pub normalizer: Normalizer,
+    pub class_names: Vec<String>,
+    /// Validation accuracy of each member on its own
+    pub member_accuracies: Vec<f64>,
+    pub final_accuracy: f64,
+}
+
+/// Ensemble output for one sample.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnsemblePrediction<'a> {
+    /// Predicted class, or "unknown" when the ensemble is unsure
+    pub label: &'a str,
+    pub confidence: f64,
+    /// Fraction of the members whose own prediction differs from the
+    /// ensemble's
+    pub disagreement: f64,
+}
+
+impl Ensemble {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        training::save_json(self, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        training::load_json(path)
+    }
+
+    /// Class probabilities of every member for normalized features.
+    pub fn member_probabilities(&self, features: &Array2<f64>) -> Vec<Array2<f64>> {
+        self.members.iter().map(|m| m.predict_proba(features)).collect()
+    }
+
+    /// Per sample, the fraction of members that disagree with the
+    /// ensemble's prediction.
+    pub fn disagreement(&self, features: &Array2<f64>) -> Vec<f64> {
+        let member_probabilities = self.member_probabilities(features);
+        let predicted = argmax_rows(&self.combine(&member_probabilities));
+        disagreement(&member_probabilities, &predicted)
+    }
+
+    /// Classifies raw (unnormalized) measurements. The result is "unknown"
+    /// when the ensemble is not confident or its members disagree too much.
+    pub fn predict(&self, weight: f64, size: f64, width: f64, height: f64) -> EnsemblePrediction<'_> {
+        let mut input = measurement_row(weight, size, width, height);
+        self.normalizer.normalize(&mut input);
+
+        let member_probabilities = self.member_probabilities(&input);
+        let probabilities = self.combine(&member_probabilities);
+        let class = argmax_rows(&probabilities)[0];
+        let confidence = probabilities[[0, class]];
+        let disagreement = disagreement(&member_probabilities, &[class])[0];
+
+        let unsure = confidence < UNKNOWN_CONFIDENCE_THRESHOLD
+            || disagreement > self.config.ensemble.max_disagreement
+            || class >= self.class_names.len();
+        EnsemblePrediction {
+            label: if unsure { "unknown" } else { &self.class_names[class] },
+            confidence,
+            disagreement,
+        }
+    }
+
+    fn combine(&self, member_probabilities: &[Array2<f64>]) -> Array2<f64> {
+        match &self.combiner {
+            None => {
+                let mut mean = member_probabilities[0].clone();
+                for p in &member_probabilities[1..] {
+                    mean += p;
+                }
+                mean / member_probabilities.len() as f64
+            }
+            Some(combiner) => combiner.predict_proba(&stack(member_probabilities)),
+        }
+    }
+}
+
+impl Classifier for Ensemble {
+    fn name(&self) -> &str {
+        "ensemble"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        self.combine(&self.member_probabilities(features))
+    }
+}
+
+fn stack(member_probabilities: &[Array2<f64>]) -> Array2<f64> {
+    let views: Vec<ArrayView2<f64>> = member_probabilities.iter().map(|p| p.view()).collect();
+    concatenate(Axis(1), &views).expect("members predict the same number of rows")
+}
+
+fn disagreement(member_probabilities: &[Array2<f64>], predicted: &[usize]) -> Vec<f64> {
+    let member_predictions: Vec<Vec<usize>> = member_probabilities.iter().map(argmax_rows).collect();
+    predicted
+        .iter()
+        .enumerate()
+        .map(|(i, &class)| {
+            let dissenting = member_predictions.iter().filter(|p| p[i] != class).count();
+            dissenting as f64 / member_predictions.len() as f64
+        })
+        .collect()
+}
+
+/// Trains an ensemble on the dataset named in `config.data`.
+pub fn run_ensemble_training(config: &TrainingConfig) -> Result<Ensemble, TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    train_ensemble(&samples, config)
+}
+
+/// Trains `config.ensemble.members` networks in parallel with the
+/// hyperparameters of `config` and combines them as configured.
+pub fn train_ensemble(samples: &[FruitSample], config: &TrainingConfig) -> Result<Ensemble, TrainingError> {
+    config.validate()?;
+    let settings = &config.ensemble;
+    let mut rng = training::seeded_rng(config.seed);
+    let (mut train, val) = training::split_samples(samples, config, &mut rng);
+
+    let holdout = match settings.combination {
+        Combination::SoftVoting => Vec::new(),
+        Combination::Stacking => {
+            let keep = ((train.len() as f64) * (1.0 - settings.stacking_holdout)) as usize;
+            let holdout = train.split_off(keep.min(train.len()));
+            if train.is_empty() || holdout.is_empty() {
+                return Err(TrainingError::Dataset(format!(
+                    "{} training samples are too few to hold out data for stacking",
+                    train.len() + holdout.len()
+                )));
+            }
+            holdout
+        }
+    };
+
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    let seeds: Vec<u64> = (0..settings.members).map(|_| rng.next_u64()).collect();
+    let members = seeds
+        .par_iter()
+        .map(|&seed| {
+            let mut member_config = config.clone();
+            member_config.seed = Some(seed);
+            let mut member_rng = StdRng::seed_from_u64(seed);
+
+            let mut member_data = data.clone();
+            if settings.resampling == Resampling::Bootstrap {
+                let n = data.train_features.nrows();
+                let rows: Vec<usize> = (0..n).map(|_| member_rng.gen_range(0..n)).collect();
+                member_data.train_features = data.train_features.select(Axis(0), &rows);
+                member_data.train_labels = data.train_labels.select(Axis(0), &rows);
+            }
+            training::train_prepared(member_data, &member_config, &mut member_rng, |_| true)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let member_accuracies = members.iter().map(|m| m.final_accuracy).collect();
+    let members: Vec<NeuralNet> = members.into_iter().map(|m| m.model).collect();
+
+    let combiner = (!holdout.is_empty()).then(|| {
+        let features = data.normalizer.transform(&holdout);
+        let labels: Vec<String> = holdout.iter().map(|s| s.label.clone()).collect();
+        let stacked = stack(&members.iter().map(|m| m.predict_proba(&features)).collect::<Vec<_>>());
+        LogisticRegression::fit(
+            &stacked,
+            &encode_labels(&labels, &data.class_names),
+            &LogisticRegressionParams::default(),
+        )
+    });
+
+    let mut ensemble = Ensemble {
+        config: config.clone(),
+        members,
+        combiner,
+        normalizer: data.normalizer,
+        class_names: data.class_names,
+        member_accuracies,
+        final_accuracy: 0.0,
+    };
+    ensemble.final_accuracy = Metrics::evaluate(&ensemble, &data.val_features, &data.val_labels).accuracy;
+    Ok(ensemble)
+}
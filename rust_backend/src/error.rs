@@ -0,0 +1,106 @@
+// error.rs
+use std::any::Any;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+/// Category of the most recent failure, see `fc_last_error_code`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FcErrorCode {
+    None = 0,
+    NullPointer = 1,
+    InvalidUtf8 = 2,
+    Dataset = 3,
+    Training = 4,
+    Panic = 5,
+    Io = 6,
+    Config = 7,
+    InvalidArgument = 8,
+}
+
+#[derive(Debug)]
+pub(crate) struct FfiError {
+    pub(crate) code: FcErrorCode,
+    pub(crate) message: String,
+}
+
+impl FfiError {
+    pub(crate) fn new(code: FcErrorCode, message: impl Into<String>) -> Self {
+        FfiError {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<(FcErrorCode, CString)>> = const { RefCell::new(None) };
+}
+
+pub(crate) fn set_last_error(err: FfiError) {
+    // Interior NULs would truncate the message on the C side anyway
+    let message = CString::new(err.message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some((err.code, message)));
+}
+
+pub(crate) fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        format!("internal panic: {}", s)
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        format!("internal panic: {}", s)
+    } else {
+        "internal panic".to_string()
+    }
+}
+
+/// Runs the body of an exported function, turning both `Err` results and
+/// panics into the thread's last error and returning `on_error` instead.
+pub(crate) fn ffi_call<T>(on_error: T, body: impl FnOnce() -> Result<T, FfiError>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            set_last_error(err);
+            on_error
+        }
+        Err(payload) => {
+            set_last_error(FfiError::new(FcErrorCode::Panic, panic_message(payload)));
+            on_error
+        }
+    }
+}
+
+pub(crate) fn check_not_null<T>(ptr: *const T, name: &str) -> Result<(), FfiError> {
+    if ptr.is_null() {
+        Err(FfiError::new(FcErrorCode::NullPointer, format!("`{}` must not be null", name)))
+    } else {
+        Ok(())
+    }
+}
+
+/// # Safety
+///
+/// `ptr` must be null or a valid NUL-terminated string that outlives `'a`.
+pub(crate) unsafe fn str_arg<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    check_not_null(ptr, name)?;
+    unsafe { CStr::from_ptr(ptr) }
+        .to_str()
+        .map_err(|_| FfiError::new(FcErrorCode::InvalidUtf8, format!("`{}` is not valid UTF-8", name)))
+}
+
+/// Code of the most recent failed call on this thread, or `None` if no call
+/// has failed yet.
+#[unsafe(no_mangle)]
+pub extern "C" fn fc_last_error_code() -> FcErrorCode {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(FcErrorCode::None, |(code, _)| *code))
+}
+
+/// Human-readable description of the most recent failed call on this thread,
+/// or null if no call has failed yet. The string is owned by the library and
+/// stays valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn fc_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |(_, message)| message.as_ptr()))
+}
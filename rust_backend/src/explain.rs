@@ -0,0 +1,189 @@
+// explain.rs
+use crate::classifier::{argmax_rows, Classifier};
+use crate::model::NeuralNet;
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Steps of the integrated-gradients path integral.
+pub const INTEGRATED_GRADIENTS_STEPS: usize = 50;
+
+/// How the attributions of a single prediction are computed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExplainMethod {
+    /// Gradient of the class probability times the normalized input
+    GradientTimesInput,
+    /// Gradients averaged along the straight path from the baseline to the
+    /// input, times the distance travelled
+    #[default]
+    IntegratedGradients,
+    /// Shapley values from the weighted regression over feature coalitions,
+    /// with absent features taken from the baseline
+    KernelShap,
+}
+
+const METHODS: [ExplainMethod; 3] = [
+    ExplainMethod::GradientTimesInput,
+    ExplainMethod::IntegratedGradients,
+    ExplainMethod::KernelShap,
+];
+
+impl ExplainMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExplainMethod::GradientTimesInput => "gradient_times_input",
+            ExplainMethod::IntegratedGradients => "integrated_gradients",
+            ExplainMethod::KernelShap => "kernel_shap",
+        }
+    }
+}
+
+impl fmt::Display for ExplainMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for ExplainMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        METHODS.into_iter().find(|m| m.name() == s).ok_or_else(|| {
+            let names: Vec<&str> = METHODS.iter().map(|m| m.name()).collect();
+            format!("Unknown explanation method '{}' (expected one of {})", s, names.join(", "))
+        })
+    }
+}
+
+/// Contribution of each feature to the probability of one class.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Explanation {
+    pub method: ExplainMethod,
+    /// Explained class, the most likely one
+    pub class: usize,
+    /// Probability of `class` for the input
+    pub probability: f64,
+    /// Probability of `class` at the baseline; for integrated gradients and
+    /// KernelSHAP the attributions add up to `probability - base_value`
+    pub base_value: f64,
+    /// One value per feature in the order weight, size, width, height
+    pub attributions: Vec<f64>,
+}
+
+/// Explains the network's most likely class for one normalized `input`
+/// row relative to `baseline`, e.g. the normalized training mean.
+pub fn explain(model: &NeuralNet, input: ArrayView1<f64>, baseline: ArrayView1<f64>, method: ExplainMethod) -> Explanation {
+    let x = input.to_owned().insert_axis(Axis(0));
+    let b = baseline.to_owned().insert_axis(Axis(0));
+    let probabilities = model.predict_proba(&x);
+    let class = argmax_rows(&probabilities)[0];
+
+    let attributions = match method {
+        ExplainMethod::GradientTimesInput => gradient_times_input(model, &x, &b, class),
+        ExplainMethod::IntegratedGradients => integrated_gradients(model, &x, &b, class, INTEGRATED_GRADIENTS_STEPS),
+        ExplainMethod::KernelShap => kernel_shap(model, x.row(0), &b, class),
+    };
+    Explanation {
+        method,
+        class,
+        probability: probabilities[[0, class]],
+        base_value: model.predict_proba(&b)[[0, class]],
+        attributions: attributions.to_vec(),
+    }
+}
+
+/// `(x - baseline) * d p_class / dx` at the input.
+pub fn gradient_times_input(model: &NeuralNet, x: &Array2<f64>, baseline: &Array2<f64>, class: usize) -> Array1<f64> {
+    let gradient = model.input_gradient(x, class);
+    ((x - baseline) * gradient).row(0).to_owned()
+}
+
+/// Integrated gradients with the midpoint rule over `steps` points of the
+/// path from `baseline` to `x`.
+pub fn integrated_gradients(model: &NeuralNet, x: &Array2<f64>, baseline: &Array2<f64>, class: usize, steps: usize) -> Array1<f64> {
+    let delta = (x - baseline).row(0).to_owned();
+    let path = Array2::from_shape_fn((steps, delta.len()), |(k, j)| {
+        baseline[[0, j]] + (k as f64 + 0.5) / steps as f64 * delta[j]
+    });
+    let mean_gradient = model.input_gradient(&path, class).mean_axis(Axis(0)).expect("steps > 0");
+    delta * mean_gradient
+}
+
+/// KernelSHAP for any classifier. The value of a coalition of features is
+/// the mean probability of `class` over the `background` rows with the
+/// coalition's features taken from `x`. Every coalition is enumerated, which
+/// is cheap for four features and makes the result the exact Shapley values.
+pub fn kernel_shap<C: Classifier + ?Sized>(model: &C, x: ArrayView1<f64>, background: &Array2<f64>, class: usize) -> Array1<f64> {
+    let m = x.len();
+    let value = |mask: usize| {
+        let mut rows = background.clone();
+        for mut row in rows.rows_mut() {
+            for j in (0..m).filter(|j| mask & (1 << j) != 0) {
+                row[j] = x[j];
+            }
+        }
+        model.predict_proba(&rows).column(class).mean().expect("background is not empty")
+    };
+    let full = (1 << m) - 1;
+    let (base, total) = (value(0), value(full));
+    if m == 1 {
+        return Array1::from_elem(1, total - base);
+    }
+
+    // The last attribution is fixed by the efficiency constraint, leaving a
+    // weighted least-squares problem in the first m - 1
+    let k = m - 1;
+    let mut normal = Array2::<f64>::zeros((k, k));
+    let mut rhs = Array1::<f64>::zeros(k);
+    for mask in 1..full {
+        let size = mask.count_ones() as usize;
+        let weight = (m - 1) as f64 / (binomial(m, size) * (size * (m - size)) as f64);
+        let last = ((mask >> k) & 1) as f64;
+        let row: Vec<f64> = (0..k).map(|j| ((mask >> j) & 1) as f64 - last).collect();
+        let target = value(mask) - base - last * (total - base);
+        for a in 0..k {
+            rhs[a] += weight * row[a] * target;
+            for b in 0..k {
+                normal[[a, b]] += weight * row[a] * row[b];
+            }
+        }
+    }
+
+    let mut phi = solve(normal, rhs);
+    let last = total - base - phi.sum();
+    phi.append(Axis(0), Array1::from_elem(1, last).view()).expect("1-D append");
+    phi
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+// Gaussian elimination with partial pivoting for the small normal equations
+fn solve(mut a: Array2<f64>, mut b: Array1<f64>) -> Array1<f64> {
+    let n = b.len();
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|&i, &j| a[[i, col]].abs().total_cmp(&a[[j, col]].abs()))
+            .expect("non-empty range");
+        for j in 0..n {
+            a.swap([col, j], [pivot, j]);
+        }
+        b.swap(col, pivot);
+        for row in col + 1..n {
+            let factor = a[[row, col]] / a[[col, col]];
+            for j in col..n {
+                a[[row, j]] -= factor * a[[col, j]];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+    let mut x = Array1::zeros(n);
+    for row in (0..n).rev() {
+        let sum: f64 = (row + 1..n).map(|j| a[[row, j]] * x[j]).sum();
+        x[row] = (b[row] - sum) / a[[row, row]];
+    }
+    x
+}
@@ -0,0 +1,736 @@
+// ffi.rs
+use std::borrow::Cow;
+use std::ffi::{CString, c_void};
+use std::os::raw::c_char;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+use crate::error::{FcErrorCode, FfiError, check_not_null, ffi_call, panic_message, str_arg};
+use crate::config::{TrainingConfig, MAX_EPOCHS};
+use crate::explain::ExplainMethod;
+use crate::precision::Precision;
+use crate::training::{self, EpochMetrics, TrainingError};
+
+/// Array of doubles allocated by the library. Release it with
+/// `fc_f64_array_free`, which needs both the pointer and the length.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FcF64Array {
+    pub ptr: *mut f64,
+    pub len: usize,
+}
+
+impl FcF64Array {
+    pub const EMPTY: FcF64Array = FcF64Array {
+        ptr: ptr::null_mut(),
+        len: 0,
+    };
+}
+
+impl From<Vec<f64>> for FcF64Array {
+    fn from(values: Vec<f64>) -> Self {
+        // Shrinks to the exact length so that ptr/len describe the allocation
+        let boxed = values.into_boxed_slice();
+        let len = boxed.len();
+        FcF64Array {
+            ptr: Box::into_raw(boxed) as *mut f64,
+            len,
+        }
+    }
+}
+
+/// Per-epoch metrics filled in by `train_network`. Release it with
+/// `free_training_result`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct TrainingResult {
+    pub accuracies: FcF64Array,
+    pub losses: FcF64Array,
+    pub final_accuracy: f64,
+}
+
+/// Called after every epoch with the 1-based epoch number, the training loss,
+/// the training accuracy and the validation accuracy. Return `false` to stop
+/// training early; the metrics gathered so far are still returned.
+pub type EpochCallback = Option<
+    unsafe extern "C" fn(
+        user_data: *mut c_void,
+        epoch: usize,
+        loss: f64,
+        train_accuracy: f64,
+        val_accuracy: f64,
+    ) -> bool,
+>;
+
+impl From<TrainingError> for FfiError {
+    fn from(err: TrainingError) -> Self {
+        let code = match err {
+            TrainingError::Config(_) => FcErrorCode::Config,
+            TrainingError::Dataset(_) => FcErrorCode::Dataset,
+            TrainingError::Diverged { .. } | TrainingError::TrialsFailed { .. } => FcErrorCode::Training,
+        };
+        FfiError::new(code, err.to_string())
+    }
+}
+
+// Default hyperparameters with the caller's dataset and epoch count
+fn default_config(path: &str, epochs: usize) -> Result<TrainingConfig, FfiError> {
+    if !(1..=MAX_EPOCHS).contains(&epochs) {
+        return Err(FfiError::new(
+            FcErrorCode::InvalidArgument,
+            format!("epochs must be between 1 and {}, got {}", MAX_EPOCHS, epochs),
+        ));
+    }
+    let mut config = TrainingConfig::default();
+    config.data.path = path.to_owned();
+    config.schedule.epochs = epochs;
+    Ok(config)
+}
+
+fn run_training<F>(config: &TrainingConfig, on_epoch: F) -> Result<training::TrainingResult, FfiError>
+where
+    F: FnMut(&EpochMetrics) -> bool,
+{
+    Ok(training::run_training_from_csv_with_callback(&config.data.path, config, on_epoch)?)
+}
+
+/// Trains a network on the CSV at `dataset_path` and stores the per-epoch
+/// accuracy and loss arrays in `result`, which must be released with
+/// `free_training_result`. `epochs` must be between 1 and `MAX_EPOCHS`.
+/// Returns `false` on failure, see `fc_last_error_message`.
+///
+/// # Safety
+///
+/// `dataset_path` must be null or a valid NUL-terminated string and `result`
+/// must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn train_network(
+    dataset_path: *const c_char,
+    epochs: usize,
+    result: *mut TrainingResult,
+) -> bool {
+    unsafe { train_network_with_callback(dataset_path, epochs, None, ptr::null_mut(), result) }
+}
+
+/// Same as `train_network`, but invokes `callback` with `user_data` after
+/// every epoch so the caller can display live progress or stop early.
+///
+/// # Safety
+///
+/// Same requirements as `train_network`. `callback` is called on the calling
+/// thread and must be safe to invoke with `user_data`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn train_network_with_callback(
+    dataset_path: *const c_char,
+    epochs: usize,
+    callback: EpochCallback,
+    user_data: *mut c_void,
+    result: *mut TrainingResult,
+) -> bool {
+    ffi_call(false, || {
+        let path = unsafe { str_arg(dataset_path, "dataset_path")? };
+        check_not_null(result, "result")?;
+
+        let output = run_training(&default_config(path, epochs)?, |m| match callback {
+            Some(cb) => unsafe { cb(user_data, m.epoch, m.loss, m.train_accuracy, m.val_accuracy) },
+            None => true,
+        })?;
+
+        let training_result = TrainingResult {
+            accuracies: output.accuracies().into(),
+            losses: output.losses().into(),
+            final_accuracy: output.final_accuracy,
+        };
+        unsafe { result.write(training_result) };
+        Ok(true)
+    })
+}
+
+/// Snapshot of a background training job, filled in by `fc_train_poll`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FcTrainProgress {
+    pub epoch: usize,
+    pub total_epochs: usize,
+    pub loss: f64,
+    pub train_accuracy: f64,
+    pub val_accuracy: f64,
+    pub finished: bool,
+}
+
+#[derive(Default)]
+struct JobState {
+    progress: Mutex<FcTrainProgress>,
+    // Every finished epoch, since polling only sees the latest one
+    history: Mutex<Vec<EpochMetrics>>,
+    cancel: AtomicBool,
+}
+
+impl JobState {
+    // A panic while holding the lock only leaves a stale snapshot behind
+    fn progress(&self) -> MutexGuard<'_, FcTrainProgress> {
+        self.progress.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn history(&self) -> MutexGuard<'_, Vec<EpochMetrics>> {
+        self.history.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Opaque handle to a training run started with `fc_train_start`.
+pub struct FcTrainJob {
+    state: Arc<JobState>,
+    handle: JoinHandle<Result<training::TrainingResult, FfiError>>,
+}
+
+/// Opaque handle to a trained model returned by `fc_train_join` or
+/// `fc_model_load`.
+pub struct FcModel {
+    result: ModelResult,
+}
+
+// A model kept in the precision it was trained in
+enum ModelResult {
+    F64(training::TrainingResult),
+    F32(training::TrainingResult<f32>),
+}
+
+impl FcModel {
+    // Training and loading yield `f64` results; an `f32` model converts
+    // back exactly
+    fn new(result: training::TrainingResult) -> Self {
+        let result = match result.config.model.precision {
+            Precision::F64 => ModelResult::F64(result),
+            Precision::F32 => ModelResult::F32(result.cast()),
+        };
+        FcModel { result }
+    }
+
+    fn precision(&self) -> FcPrecision {
+        match self.result {
+            ModelResult::F64(_) => FcPrecision::F64,
+            ModelResult::F32(_) => FcPrecision::F32,
+        }
+    }
+
+    fn final_accuracy(&self) -> f64 {
+        match &self.result {
+            ModelResult::F64(result) => result.final_accuracy,
+            ModelResult::F32(result) => result.final_accuracy,
+        }
+    }
+
+    fn predict(&self, weight: f64, size: f64, width: f64, height: f64) -> &str {
+        match &self.result {
+            ModelResult::F64(result) => result.predict(weight, size, width, height).0,
+            ModelResult::F32(result) => result.predict(weight, size, width, height).0,
+        }
+    }
+
+    fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        match &self.result {
+            ModelResult::F64(result) => result.save(path),
+            ModelResult::F32(result) => result.save(path),
+        }
+    }
+
+    fn to_f64(&self) -> Cow<'_, training::TrainingResult> {
+        match &self.result {
+            ModelResult::F64(result) => Cow::Borrowed(result),
+            ModelResult::F32(result) => Cow::Owned(result.cast()),
+        }
+    }
+}
+
+fn spawn_training(config: TrainingConfig) -> Result<*mut FcTrainJob, FfiError> {
+    let state = Arc::new(JobState::default());
+    state.progress().total_epochs = config.schedule.epochs;
+
+    let worker_state = Arc::clone(&state);
+    let handle = thread::Builder::new()
+        .name("fc-train".to_string())
+        .spawn(move || {
+            let result = run_training(&config, |m| {
+                worker_state.history().push(*m);
+                let mut progress = worker_state.progress();
+                progress.epoch = m.epoch;
+                progress.loss = m.loss;
+                progress.train_accuracy = m.train_accuracy;
+                progress.val_accuracy = m.val_accuracy;
+                !worker_state.cancel.load(Ordering::Relaxed)
+            });
+            worker_state.progress().finished = true;
+            result
+        })
+        .map_err(|e| FfiError::new(FcErrorCode::Training, format!("Failed to spawn training thread: {}", e)))?;
+
+    Ok(Box::into_raw(Box::new(FcTrainJob { state, handle })))
+}
+
+/// Starts training on a Rust-owned thread and returns immediately. The job
+/// must eventually be released with `fc_train_join`. Returns null on failure,
+/// e.g. for `epochs` outside 1 to `MAX_EPOCHS`, see `fc_last_error_message`;
+/// dataset errors are reported by the join.
+///
+/// # Safety
+///
+/// `dataset_path` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_train_start(dataset_path: *const c_char, epochs: usize) -> *mut FcTrainJob {
+    ffi_call(ptr::null_mut(), || {
+        let path = unsafe { str_arg(dataset_path, "dataset_path")? };
+        spawn_training(default_config(path, epochs)?)
+    })
+}
+
+/// Like `fc_train_start`, but takes every setting from a TOML or JSON
+/// training config. Invalid configs are rejected here, before a thread is
+/// started.
+///
+/// # Safety
+///
+/// `config_path` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_train_start_with_config(config_path: *const c_char) -> *mut FcTrainJob {
+    ffi_call(ptr::null_mut(), || {
+        let path = unsafe { str_arg(config_path, "config_path")? };
+        let config = TrainingConfig::from_file(path).map_err(|e| FfiError::new(FcErrorCode::Config, e.to_string()))?;
+        spawn_training(config)
+    })
+}
+
+/// State of a background training job, returned by `fc_train_poll`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FcTrainStatus {
+    Running = 0,
+    /// The thread is done; `fc_train_join` returns the model or the error
+    Finished = 1,
+    /// The poll itself failed, see `fc_last_error_code`
+    Error = 2,
+}
+
+/// Copies the latest progress of `job` into `progress` and reports whether
+/// the training thread is still running. Safe to call from any thread.
+/// Returns `FcTrainStatus::Error` without touching `progress` if either
+/// pointer is null.
+///
+/// # Safety
+///
+/// `job` must be null or a live handle from `fc_train_start` and `progress`
+/// must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_train_poll(job: *const FcTrainJob, progress: *mut FcTrainProgress) -> FcTrainStatus {
+    ffi_call(FcTrainStatus::Error, || {
+        check_not_null(job, "job")?;
+        check_not_null(progress, "progress")?;
+        let snapshot = *unsafe { &*job }.state.progress();
+        unsafe { *progress = snapshot };
+        Ok(if snapshot.finished {
+            FcTrainStatus::Finished
+        } else {
+            FcTrainStatus::Running
+        })
+    })
+}
+
+/// Copies the per-epoch training accuracy and loss of every epoch `job` has
+/// finished so far into `accuracies` and `losses`, which must be released
+/// with `fc_f64_array_free`. Their previous contents are overwritten, not
+/// freed. Safe to call from any thread. Returns `false` on failure, see
+/// `fc_last_error_message`.
+///
+/// # Safety
+///
+/// `job` must be null or a live handle from `fc_train_start`, and
+/// `accuracies` and `losses` must be null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_train_history(
+    job: *const FcTrainJob,
+    accuracies: *mut FcF64Array,
+    losses: *mut FcF64Array,
+) -> bool {
+    ffi_call(false, || {
+        check_not_null(job, "job")?;
+        check_not_null(accuracies, "accuracies")?;
+        check_not_null(losses, "losses")?;
+        let history = unsafe { &*job }.state.history();
+        unsafe {
+            accuracies.write(history.iter().map(|m| m.train_accuracy).collect::<Vec<_>>().into());
+            losses.write(history.iter().map(|m| m.loss).collect::<Vec<_>>().into());
+        }
+        Ok(true)
+    })
+}
+
+/// Asks the training thread to stop after the current epoch. The job still
+/// has to be joined. Safe to call from any thread.
+///
+/// # Safety
+///
+/// `job` must be null or a live handle from `fc_train_start`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_train_cancel(job: *const FcTrainJob) {
+    ffi_call((), || {
+        check_not_null(job, "job")?;
+        unsafe { &*job }.state.cancel.store(true, Ordering::Relaxed);
+        Ok(())
+    })
+}
+
+/// Waits for the training thread, releases `job` and returns the trained
+/// model, or null if training failed (see `fc_last_error_message`). A
+/// cancelled job returns the model as trained so far. The model must be
+/// released with `fc_model_free`.
+///
+/// # Safety
+///
+/// `job` must be null or a handle from `fc_train_start` that has not been
+/// joined yet, and no other thread may use it during or after this call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_train_join(job: *mut FcTrainJob) -> *mut FcModel {
+    ffi_call(ptr::null_mut(), || {
+        check_not_null(job, "job")?;
+        let job = unsafe { Box::from_raw(job) };
+        let output = job
+            .handle
+            .join()
+            .map_err(|payload| FfiError::new(FcErrorCode::Panic, panic_message(payload)))??;
+
+        Ok(Box::into_raw(Box::new(FcModel::new(output))))
+    })
+}
+
+/// Validation accuracy of `model` at the end of training, or NaN if `model`
+/// is null.
+///
+/// # Safety
+///
+/// `model` must be null or a live model handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_final_accuracy(model: *const FcModel) -> f64 {
+    ffi_call(f64::NAN, || {
+        check_not_null(model, "model")?;
+        Ok(unsafe { &*model }.final_accuracy())
+    })
+}
+
+/// Classifies one fruit with a trained model. The returned string must be
+/// released with `free_string`. Returns null on failure.
+///
+/// # Safety
+///
+/// `model` must be null or a live model handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_predict(
+    model: *const FcModel,
+    weight: f64,
+    size: f64,
+    width: f64,
+    height: f64,
+) -> *mut c_char {
+    ffi_call(ptr::null_mut(), || {
+        check_not_null(model, "model")?;
+        let label = unsafe { &*model }.predict(weight, size, width, height);
+        Ok(CString::new(label).unwrap().into_raw())
+    })
+}
+
+/// `fc_model_predict` for callers that keep measurements as floats. Works
+/// with models of either precision.
+///
+/// # Safety
+///
+/// `model` must be null or a live model handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_predict_f32(
+    model: *const FcModel,
+    weight: f32,
+    size: f32,
+    width: f32,
+    height: f32,
+) -> *mut c_char {
+    unsafe { fc_model_predict(model, weight as f64, size as f64, width as f64, height as f64) }
+}
+
+/// Floating-point type a model computes in, see `fc_model_precision` and
+/// `fc_model_convert`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FcPrecision {
+    F64 = 0,
+    F32 = 1,
+}
+
+fn precision(value: u32) -> Result<Precision, FfiError> {
+    match value {
+        v if v == FcPrecision::F64 as u32 => Ok(Precision::F64),
+        v if v == FcPrecision::F32 as u32 => Ok(Precision::F32),
+        _ => Err(FfiError::new(FcErrorCode::InvalidArgument, format!("Unknown precision {}", value))),
+    }
+}
+
+/// One of the `FcPrecision` values, or -1 if `model` is null. Models have
+/// the precision of the config they were trained with.
+///
+/// # Safety
+///
+/// `model` must be null or a live model handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_precision(model: *const FcModel) -> i32 {
+    ffi_call(-1, || {
+        check_not_null(model, "model")?;
+        Ok(unsafe { &*model }.precision() as i32)
+    })
+}
+
+/// Copies `model` into a new handle computing in `precision`, one of the
+/// `FcPrecision` values. Converting to `f32` rounds every parameter. The
+/// copy must be released with `fc_model_free`; returns null on failure.
+///
+/// # Safety
+///
+/// `model` must be null or a live model handle.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_convert(model: *const FcModel, precision: u32) -> *mut FcModel {
+    ffi_call(ptr::null_mut(), || {
+        check_not_null(model, "model")?;
+        let mut result = unsafe { &*model }.to_f64().into_owned();
+        result.config.model.precision = self::precision(precision)?;
+        Ok(Box::into_raw(Box::new(FcModel::new(result))))
+    })
+}
+
+/// Attribution method for `fc_model_explain`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FcExplainMethod {
+    GradientTimesInput = 0,
+    IntegratedGradients = 1,
+    KernelShap = 2,
+}
+
+fn explain_method(value: u32) -> Result<ExplainMethod, FfiError> {
+    match value {
+        v if v == FcExplainMethod::GradientTimesInput as u32 => Ok(ExplainMethod::GradientTimesInput),
+        v if v == FcExplainMethod::IntegratedGradients as u32 => Ok(ExplainMethod::IntegratedGradients),
+        v if v == FcExplainMethod::KernelShap as u32 => Ok(ExplainMethod::KernelShap),
+        _ => Err(FfiError::new(FcErrorCode::InvalidArgument, format!("Unknown explanation method {}", value))),
+    }
+}
+
+/// A prediction with the contribution of each measurement, filled in by
+/// `fc_model_explain`. Release it with `fc_explanation_free`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct FcExplanation {
+    /// Predicted class, or "unknown" when the model is unsure
+    pub label: *mut c_char,
+    pub confidence: f64,
+    /// Probability of the predicted class at the baseline input
+    pub base_value: f64,
+    /// Contributions of weight, size, width and height
+    pub attributions: [f64; 4],
+}
+
+/// Classifies one fruit like `fc_model_predict` and explains the prediction
+/// with `method`, one of the `FcExplainMethod` values. Returns `false` on
+/// failure.
+///
+/// # Safety
+///
+/// `model` must be null or a live model handle and `explanation` must be
+/// null or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_explain(
+    model: *const FcModel,
+    weight: f64,
+    size: f64,
+    width: f64,
+    height: f64,
+    method: u32,
+    explanation: *mut FcExplanation,
+) -> bool {
+    ffi_call(false, || {
+        check_not_null(model, "model")?;
+        check_not_null(explanation, "explanation")?;
+        let method = explain_method(method)?;
+        // Explanations are computed in f64 whatever the model's precision
+        let model = unsafe { &*model }.to_f64();
+        let (label, result) = model.explain(weight, size, width, height, method);
+
+        let mut attributions = [0.0; 4];
+        attributions.copy_from_slice(&result.attributions);
+        let output = FcExplanation {
+            label: CString::new(label).unwrap().into_raw(),
+            confidence: result.probability,
+            base_value: result.base_value,
+            attributions,
+        };
+        unsafe { explanation.write(output) };
+        Ok(true)
+    })
+}
+
+/// Saves `model` together with the config it was trained with as JSON.
+/// Returns `false` on failure.
+///
+/// # Safety
+///
+/// `model` must be null or a live model handle and `path` must be null or a
+/// valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_save(model: *const FcModel, path: *const c_char) -> bool {
+    ffi_call(false, || {
+        check_not_null(model, "model")?;
+        let path = unsafe { str_arg(path, "path")? };
+        unsafe { &*model }
+            .save(path)
+            .map_err(|e| FfiError::new(FcErrorCode::Io, format!("Failed to save model to '{}': {}", path, e)))?;
+        Ok(true)
+    })
+}
+
+/// Loads a model written by `fc_model_save`. Returns null on failure. The
+/// model must be released with `fc_model_free`.
+///
+/// # Safety
+///
+/// `path` must be null or a valid NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_load(path: *const c_char) -> *mut FcModel {
+    ffi_call(ptr::null_mut(), || {
+        let path = unsafe { str_arg(path, "path")? };
+        let result = training::TrainingResult::load(path)
+            .map_err(|e| FfiError::new(FcErrorCode::Io, format!("Failed to load model from '{}': {}", path, e)))?;
+        Ok(Box::into_raw(Box::new(FcModel::new(result))))
+    })
+}
+
+/// # Safety
+///
+/// `model` must be null or a model handle that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_model_free(model: *mut FcModel) {
+    ffi_call((), || {
+        if !model.is_null() {
+            unsafe {
+                let _ = Box::from_raw(model);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Rule-based fallback classifier for when no trained model is available.
+/// The returned string must be released with `free_string`.
+#[unsafe(no_mangle)]
+pub extern "C" fn predict(weight: f64, size: f64, width: f64, height: f64) -> *mut c_char {
+    ffi_call(ptr::null_mut(), || {
+        // The rules only look at weight and size
+        let _ = (width, height);
+
+        // This is a simplified prediction for demo
+        // In a real application, you would:
+        // 1. Load the trained model
+        // 2. Normalize the input features
+        // 3. Run forward pass
+        // 4. Return the predicted class
+
+        let prediction = if weight > 200.0 && size > 8.0 {
+            "watermelon"
+        } else if weight > 100.0 && size > 6.0 {
+            "apple"
+        } else if size > 3.0 && size < 6.0 {
+            "orange"
+        } else if size <= 3.0 {
+            "grape"
+        } else {
+            "unknown"
+        };
+
+        Ok(CString::new(prediction).unwrap().into_raw())
+    })
+}
+
+/// Releases an array returned by the library and resets it to empty, so
+/// freeing the same array twice is harmless.
+///
+/// # Safety
+///
+/// `array` must be null, or point to an `FcF64Array` that is empty or whose
+/// `ptr` and `len` are unchanged from when the library returned it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_f64_array_free(array: *mut FcF64Array) {
+    ffi_call((), || {
+        if array.is_null() {
+            return Ok(());
+        }
+        let array = unsafe { &mut *array };
+        if !array.ptr.is_null() {
+            unsafe {
+                let _ = Box::from_raw(ptr::slice_from_raw_parts_mut(array.ptr, array.len));
+            }
+        }
+        *array = FcF64Array::EMPTY;
+        Ok(())
+    })
+}
+
+/// Releases both arrays of a `TrainingResult` filled in by `train_network`.
+///
+/// # Safety
+///
+/// Same requirements as `fc_f64_array_free` for both arrays of `result`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_training_result(result: *mut TrainingResult) {
+    ffi_call((), || {
+        if !result.is_null() {
+            unsafe {
+                fc_f64_array_free(&mut (*result).accuracies);
+                fc_f64_array_free(&mut (*result).losses);
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Releases the label of an explanation filled in by `fc_model_explain` and
+/// resets it to null, so freeing twice is harmless.
+///
+/// # Safety
+///
+/// `explanation` must be null, or point to an `FcExplanation` whose `label`
+/// is null or unchanged from when the library returned it.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn fc_explanation_free(explanation: *mut FcExplanation) {
+    ffi_call((), || {
+        if !explanation.is_null() {
+            unsafe {
+                free_string((*explanation).label);
+                (*explanation).label = ptr::null_mut();
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Releases a label returned by the library.
+///
+/// # Safety
+///
+/// `ptr` must be null or a string returned by `predict`, `fc_model_predict`
+/// or `fc_model_predict_f32` that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn free_string(ptr: *mut c_char) {
+    ffi_call((), || {
+        if !ptr.is_null() {
+            unsafe {
+                let _ = CString::from_raw(ptr);
+            }
+        }
+        Ok(())
+    })
+}
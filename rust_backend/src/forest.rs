@@ -0,0 +1,97 @@
+// forest.rs
+use crate::classifier::{Classifier, TrainedModel};
+use crate::config::{ForestConfig, TrainingConfig};
+use crate::data::FruitSample;
+use crate::training::{self, TrainingError};
+use crate::tree::{normalized_importances, DecisionTree, TreeParams};
+use ndarray::{Array2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Random forest: CART trees on bootstrap samples with a random subset of
+/// the features at every split, averaged by soft voting.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RandomForest {
+    trees: Vec<DecisionTree>,
+    n_classes: usize,
+}
+
+impl RandomForest {
+    /// Fits the trees in parallel on normalized features and one-hot labels.
+    pub fn fit<R: Rng>(features: &Array2<f64>, labels: &Array2<f64>, config: &ForestConfig, rng: &mut R) -> Self {
+        let params = TreeParams {
+            max_depth: config.max_depth,
+            min_samples_leaf: config.min_samples_leaf,
+            max_features: config.max_features,
+            ..TreeParams::default()
+        };
+
+        let seeds: Vec<u64> = (0..config.n_trees).map(|_| rng.next_u64()).collect();
+        let trees = seeds
+            .par_iter()
+            .map(|&seed| {
+                let mut tree_rng = StdRng::seed_from_u64(seed);
+                if config.bootstrap {
+                    let n = features.nrows();
+                    let rows: Vec<usize> = (0..n).map(|_| tree_rng.gen_range(0..n)).collect();
+                    let (x, y) = (features.select(Axis(0), &rows), labels.select(Axis(0), &rows));
+                    DecisionTree::fit_with_rng(&x, &y, &params, &mut tree_rng)
+                } else {
+                    DecisionTree::fit_with_rng(features, labels, &params, &mut tree_rng)
+                }
+            })
+            .collect();
+
+        RandomForest {
+            trees,
+            n_classes: labels.ncols(),
+        }
+    }
+
+    pub fn trees(&self) -> &[DecisionTree] {
+        &self.trees
+    }
+
+    /// Mean decrease in impurity per feature, averaged over the trees and
+    /// scaled to sum to 1.
+    pub fn feature_importances(&self) -> Vec<f64> {
+        let mut total = vec![0.0; self.trees.first().map_or(0, |t| t.feature_importances().len())];
+        for tree in &self.trees {
+            for (t, v) in total.iter_mut().zip(tree.feature_importances()) {
+                *t += v;
+            }
+        }
+        normalized_importances(&total)
+    }
+}
+
+impl Classifier for RandomForest {
+    fn name(&self) -> &str {
+        "random_forest"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        let mut probabilities = Array2::zeros((features.nrows(), self.n_classes));
+        for tree in &self.trees {
+            probabilities += &tree.predict_proba(features);
+        }
+        probabilities / self.trees.len().max(1) as f64
+    }
+}
+
+/// Trains a random forest on the dataset named in `config.data`.
+pub fn run_forest_training(config: &TrainingConfig) -> Result<TrainedModel<RandomForest>, TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    train_forest(&samples, config)
+}
+
+/// Grows `config.forest.n_trees` trees on the training split of `samples`
+/// and scores the forest on the validation split.
+pub fn train_forest(samples: &[FruitSample], config: &TrainingConfig) -> Result<TrainedModel<RandomForest>, TrainingError> {
+    TrainedModel::fit(samples, config, |data, rng| {
+        RandomForest::fit(&data.train_features, &data.train_labels, &config.forest, rng)
+    })
+}
@@ -0,0 +1,211 @@
+// importance.rs
+use crate::classifier::{Classifier, Metrics};
+use crate::config::{ImportanceConfig, TrainingConfig};
+use crate::data::FruitSample;
+use crate::training::{self, PreparedSplit, TrainingError, TrainingResult};
+use crate::utils::FEATURE_NAMES;
+use ndarray::Array2;
+use plotters::prelude::*;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Serialize;
+use std::error::Error;
+use std::path::Path;
+
+/// Mean of a statistic over the repeats with a confidence interval for it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Estimate {
+    pub mean: f64,
+    pub std: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Estimate {
+    /// Student-t interval for the mean of `values`.
+    pub fn from_repeats(values: &[f64], confidence: f64) -> Self {
+        let n = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / n;
+        let std = if values.len() > 1 {
+            (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            0.0
+        };
+        let half_width = if values.len() > 1 {
+            student_t_quantile(0.5 + confidence / 2.0, n - 1.0) * std / n.sqrt()
+        } else {
+            0.0
+        };
+        Estimate {
+            mean,
+            std,
+            lower: mean - half_width,
+            upper: mean + half_width,
+        }
+    }
+}
+
+/// How much the model's scores degrade when one feature is shuffled.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeatureImportance {
+    pub feature: String,
+    /// Baseline accuracy minus the accuracy with the feature shuffled
+    pub accuracy_drop: Estimate,
+    /// Log loss with the feature shuffled minus the baseline log loss
+    pub log_loss_increase: Estimate,
+}
+
+/// Shuffles each column of the held-out `features` `settings.repeats`
+/// times and measures the change in accuracy and log loss.
+pub fn permutation_importance<C: Classifier + ?Sized, R: Rng>(
+    model: &C,
+    features: &Array2<f64>,
+    labels: &Array2<f64>,
+    settings: &ImportanceConfig,
+    rng: &mut R,
+) -> Vec<FeatureImportance> {
+    let baseline = Metrics::evaluate(model, features, labels);
+
+    (0..features.ncols())
+        .map(|column| {
+            let mut accuracy_drops = Vec::with_capacity(settings.repeats);
+            let mut log_loss_increases = Vec::with_capacity(settings.repeats);
+            let mut shuffled = features.clone();
+            for _ in 0..settings.repeats {
+                let mut values = features.column(column).to_vec();
+                values.shuffle(rng);
+                for (target, value) in shuffled.column_mut(column).iter_mut().zip(values) {
+                    *target = value;
+                }
+                let metrics = Metrics::evaluate(model, &shuffled, labels);
+                accuracy_drops.push(baseline.accuracy - metrics.accuracy);
+                log_loss_increases.push(metrics.log_loss - baseline.log_loss);
+            }
+
+            FeatureImportance {
+                feature: FEATURE_NAMES.get(column).map_or_else(|| format!("feature {}", column), |n| n.to_string()),
+                accuracy_drop: Estimate::from_repeats(&accuracy_drops, settings.confidence),
+                log_loss_increase: Estimate::from_repeats(&log_loss_increases, settings.confidence),
+            }
+        })
+        .collect()
+}
+
+/// Trains a network on the dataset named in `config.data` and computes the
+/// permutation importance of its features on the validation split.
+pub fn run_importance(config: &TrainingConfig) -> Result<(TrainingResult, Vec<FeatureImportance>), TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    network_importance(&samples, config)
+}
+
+/// Like `run_importance`, on samples that are already loaded.
+pub fn network_importance(
+    samples: &[FruitSample],
+    config: &TrainingConfig,
+) -> Result<(TrainingResult, Vec<FeatureImportance>), TrainingError> {
+    config.validate()?;
+    let mut rng = training::seeded_rng(config.seed);
+    let (train, val) = training::split_samples(samples, config, &mut rng);
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    let result = training::train_prepared(data.clone(), config, &mut rng, |_| true)?;
+    let importances = permutation_importance(&result.model, &data.val_features, &data.val_labels, &config.importance, &mut rng);
+    Ok((result, importances))
+}
+
+/// Bar charts of the accuracy drop and log-loss increase per feature, with
+/// the confidence intervals as error bars.
+pub fn plot_importances(importances: &[FeatureImportance], path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let root = BitMapBackend::new(path.as_ref(), (1200, 600)).into_drawing_area();
+    root.fill(&WHITE)?;
+    let panels = root.split_evenly((1, 2));
+
+    let accuracy: Vec<Estimate> = importances.iter().map(|f| f.accuracy_drop).collect();
+    let log_loss: Vec<Estimate> = importances.iter().map(|f| f.log_loss_increase).collect();
+    let names: Vec<&str> = importances.iter().map(|f| f.feature.as_str()).collect();
+    draw_importance_panel(&panels[0], "Accuracy drop", &names, &accuracy, RED)?;
+    draw_importance_panel(&panels[1], "Log loss increase", &names, &log_loss, BLUE)?;
+
+    root.present()?;
+    Ok(())
+}
+
+fn draw_importance_panel(
+    panel: &DrawingArea<BitMapBackend, plotters::coord::Shift>,
+    title: &str,
+    names: &[&str],
+    estimates: &[Estimate],
+    color: RGBColor,
+) -> Result<(), Box<dyn Error>> {
+    let top = estimates.iter().map(|e| e.upper.max(e.mean)).fold(0.0, f64::max);
+    let bottom = estimates.iter().map(|e| e.lower.min(e.mean)).fold(0.0, f64::min);
+    let margin = (top - bottom).max(1e-3) * 0.1;
+
+    let mut chart = ChartBuilder::on(panel)
+        .caption(title, ("sans-serif", 30))
+        .margin(10)
+        .x_label_area_size(40)
+        .y_label_area_size(60)
+        .build_cartesian_2d(-0.5f64..names.len() as f64 - 0.5, bottom - margin..top + margin)?;
+    chart
+        .configure_mesh()
+        .disable_x_mesh()
+        .x_labels(names.len())
+        .x_label_formatter(&|x| {
+            let i = x.round() as usize;
+            if (x - i as f64).abs() < 1e-6 {
+                names.get(i).map_or_else(String::new, |n| n.to_string())
+            } else {
+                String::new()
+            }
+        })
+        .draw()?;
+
+    chart.draw_series(estimates.iter().enumerate().map(|(i, e)| {
+        let x = i as f64;
+        Rectangle::new([(x - 0.3, 0.0), (x + 0.3, e.mean)], color.filled())
+    }))?;
+    chart.draw_series(
+        estimates
+            .iter()
+            .enumerate()
+            .map(|(i, e)| ErrorBar::new_vertical(i as f64, e.lower, e.mean, e.upper, BLACK.stroke_width(2), 12)),
+    )?;
+    Ok(())
+}
+
+/// Quantile of Student's t distribution with `df` degrees of freedom, from
+/// the Cornish-Fisher expansion around the normal quantile.
+fn student_t_quantile(p: f64, df: f64) -> f64 {
+    let z = normal_quantile(p);
+    let (z2, z3) = (z * z, z * z * z);
+    let (z5, z7, z9) = (z3 * z2, z3 * z2 * z2, z3 * z3 * z3);
+    z + (z3 + z) / (4.0 * df)
+        + (5.0 * z5 + 16.0 * z3 + 3.0 * z) / (96.0 * df.powi(2))
+        + (3.0 * z7 + 19.0 * z5 + 17.0 * z3 - 15.0 * z) / (384.0 * df.powi(3))
+        + (79.0 * z9 + 776.0 * z7 + 1482.0 * z5 - 1920.0 * z3 - 945.0 * z) / (92160.0 * df.powi(4))
+}
+
+/// Inverse of the standard normal CDF (Acklam's rational approximation).
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-39.69683028665376, 220.9460984245205, -275.9285104469687, 138.357751867269, -30.66479806614716, 2.506628277459239];
+    const B: [f64; 5] = [-54.47609879822406, 161.5858368580409, -155.6989798598866, 66.80131188771972, -13.28068155288572];
+    const C: [f64; 6] = [-0.007784894002430293, -0.3223964580411365, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [0.007784695709041462, 0.3224671290700398, 2.445134137142996, 3.754408661907416];
+    const LOW: f64 = 0.02425;
+
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5])
+            / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
@@ -0,0 +1,196 @@
+// layers.rs
+use crate::precision::Real;
+use ndarray::{Array1, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+/// Whether a layer behaves as during training (random dropout masks,
+/// batch statistics) or as during inference (identity, running statistics).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    Training,
+    Inference,
+}
+
+/// Inverted dropout: during training each activation is zeroed with
+/// probability `rate` and the rest are scaled by `1 / (1 - rate)`, so the
+/// layer is the identity at inference.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dropout {
+    rate: f64,
+    seed: u64,
+    /// Mask generator, restarted from `seed` after loading
+    #[serde(skip)]
+    rng: Option<StdRng>,
+}
+
+impl Dropout {
+    pub fn new(rate: f64, seed: u64) -> Self {
+        Dropout { rate, seed, rng: None }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Applies the layer and returns the output with the mask it used;
+    /// the mask is already scaled and is `None` at inference.
+    pub fn forward<F: Real>(&mut self, x: &Array2<F>, mode: Mode) -> (Array2<F>, Option<Array2<F>>) {
+        if mode == Mode::Inference || self.rate == 0.0 {
+            return (x.clone(), None);
+        }
+        let keep = 1.0 - self.rate;
+        let scale = F::cast_from(1.0 / keep);
+        let rng = self.rng.get_or_insert_with(|| StdRng::seed_from_u64(self.seed));
+        let mask = Array2::from_shape_fn(x.raw_dim(), |_| if rng.gen_bool(keep) { scale } else { F::zero() });
+        (x * &mask, Some(mask))
+    }
+
+    pub fn backward<F: Real>(grad: &Array2<F>, mask: Option<&Array2<F>>) -> Array2<F> {
+        match mask {
+            Some(mask) => grad * mask,
+            None => grad.clone(),
+        }
+    }
+}
+
+/// Batch normalization over the feature columns with a learned scale and
+/// shift. Training normalizes with the statistics of the batch and updates
+/// exponential running averages that inference uses instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "F: Deserialize<'de>"))]
+pub struct BatchNorm<F = f64> {
+    gamma: Array1<F>,
+    beta: Array1<F>,
+    running_mean: Array1<F>,
+    running_var: Array1<F>,
+    /// Weight of the newest batch in the running averages
+    momentum: f64,
+    epsilon: f64,
+    #[serde(skip)]
+    velocity: Option<(Array1<F>, Array1<F>)>,
+}
+
+/// Values from a training forward pass needed for the backward pass.
+#[derive(Debug, Clone)]
+pub struct BatchNormCache<F = f64> {
+    normalized: Array2<F>,
+    inv_std: Array1<F>,
+}
+
+/// Gradients of the scale and shift.
+#[derive(Debug, Clone)]
+pub struct BatchNormGrads<F = f64> {
+    pub gamma: Array1<F>,
+    pub beta: Array1<F>,
+}
+
+impl<F: Real> BatchNorm<F> {
+    pub fn new(size: usize) -> Self {
+        BatchNorm {
+            gamma: Array1::ones(size),
+            beta: Array1::zeros(size),
+            running_mean: Array1::zeros(size),
+            running_var: Array1::ones(size),
+            momentum: 0.1,
+            epsilon: 1e-5,
+            velocity: None,
+        }
+    }
+
+    pub fn running_mean(&self) -> &Array1<F> {
+        &self.running_mean
+    }
+
+    pub fn running_var(&self) -> &Array1<F> {
+        &self.running_var
+    }
+
+    fn epsilon(&self) -> F {
+        F::cast_from(self.epsilon)
+    }
+
+    /// Per-feature factor inference-mode normalization multiplies its input
+    /// by, i.e. its derivative.
+    pub fn scale(&self) -> Array1<F> {
+        &self.gamma / &self.running_var.mapv(|v| (v + self.epsilon()).sqrt())
+    }
+
+    /// Per-feature offset inference-mode normalization adds after scaling,
+    /// so that `apply(x) == x * scale() + shift()`.
+    pub fn shift(&self) -> Array1<F> {
+        &self.beta - &(&self.running_mean * &self.scale())
+    }
+
+    /// Inference-mode normalization, which leaves the layer unchanged.
+    pub fn apply(&self, x: &Array2<F>) -> Array2<F> {
+        let inv_std = self.running_var.mapv(|v| (v + self.epsilon()).sqrt().recip());
+        (x - &self.running_mean) * &inv_std * &self.gamma + &self.beta
+    }
+
+    /// Applies the layer; in training mode it also updates the running
+    /// statistics and returns what `backward` needs.
+    pub fn forward(&mut self, x: &Array2<F>, mode: Mode) -> (Array2<F>, Option<BatchNormCache<F>>) {
+        if mode == Mode::Inference {
+            return (self.apply(x), None);
+        }
+        let mean = x.mean_axis(Axis(0)).expect("batch is not empty");
+        let var = x.var_axis(Axis(0), F::zero());
+        let (keep, momentum) = (F::cast_from(1.0 - self.momentum), F::cast_from(self.momentum));
+        self.running_mean = &self.running_mean * keep + &mean * momentum;
+        self.running_var = &self.running_var * keep + &var * momentum;
+
+        let inv_std = var.mapv(|v| (v + self.epsilon()).sqrt().recip());
+        let normalized = (x - &mean) * &inv_std;
+        let output = &normalized * &self.gamma + &self.beta;
+        (output, Some(BatchNormCache { normalized, inv_std }))
+    }
+
+    /// Gradient with respect to the input of a training-mode pass, plus the
+    /// parameter gradients averaged over the batch.
+    pub fn backward(&self, grad: &Array2<F>, cache: &BatchNormCache<F>) -> (Array2<F>, BatchNormGrads<F>) {
+        let n = F::cast_from(grad.nrows() as f64);
+        let grads = BatchNormGrads {
+            gamma: (grad * &cache.normalized).sum_axis(Axis(0)) / n,
+            beta: grad.sum_axis(Axis(0)) / n,
+        };
+        let grad_normalized = grad * &self.gamma;
+        let sum = grad_normalized.sum_axis(Axis(0));
+        let dot = (&grad_normalized * &cache.normalized).sum_axis(Axis(0));
+        let input_grad = (&grad_normalized * n - &sum - &cache.normalized * &dot) * &cache.inv_std / n;
+        (input_grad, grads)
+    }
+
+    /// One SGD step on the scale and shift, with classical momentum when
+    /// `momentum` is positive.
+    pub fn update(&mut self, grads: BatchNormGrads<F>, learning_rate: f64, momentum: f64) {
+        let (learning_rate, momentum) = (F::cast_from(learning_rate), F::cast_from(momentum));
+        if momentum > F::zero() {
+            let (gamma_velocity, beta_velocity) = self
+                .velocity
+                .get_or_insert_with(|| (Array1::zeros(self.gamma.raw_dim()), Array1::zeros(self.beta.raw_dim())));
+            *gamma_velocity = &*gamma_velocity * momentum + grads.gamma * learning_rate;
+            *beta_velocity = &*beta_velocity * momentum + grads.beta * learning_rate;
+            self.gamma -= &*gamma_velocity;
+            self.beta -= &*beta_velocity;
+        } else {
+            self.gamma -= &(grads.gamma * learning_rate);
+            self.beta -= &(grads.beta * learning_rate);
+        }
+    }
+
+    /// The same layer in another precision; momentum buffers restart.
+    pub fn cast<G: Real>(&self) -> BatchNorm<G> {
+        let cast = |x: &Array1<F>| x.mapv(|v| G::cast_from(v.into_f64()));
+        BatchNorm {
+            gamma: cast(&self.gamma),
+            beta: cast(&self.beta),
+            running_mean: cast(&self.running_mean),
+            running_var: cast(&self.running_var),
+            momentum: self.momentum,
+            epsilon: self.epsilon,
+            velocity: None,
+        }
+    }
+}
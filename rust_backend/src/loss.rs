@@ -0,0 +1,113 @@
+// loss.rs
+use crate::classifier::argmax_rows;
+use crate::config::{invalid, ClassWeights, ConfigError, LossConfig, LossFunction};
+use crate::precision::Real;
+use ndarray::{Array1, Array2, Axis};
+use serde::{Deserialize, Serialize};
+
+/// Training objective of the network: cross-entropy or focal loss on
+/// optionally smoothed targets, with optional per-class weights. The
+/// default is plain cross-entropy.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Loss {
+    function: LossFunction,
+    /// Indexed like the class columns; absent means every weight is 1
+    class_weights: Option<Array1<f64>>,
+    label_smoothing: f64,
+}
+
+impl Loss {
+    pub fn new(function: LossFunction, class_weights: Option<Array1<f64>>, label_smoothing: f64) -> Self {
+        Loss {
+            function,
+            class_weights,
+            label_smoothing,
+        }
+    }
+
+    /// Resolves `config` against the classes of a training split;
+    /// `labels` are its one-hot labels, used for balanced weights.
+    pub fn from_config(config: &LossConfig, class_names: &[String], labels: &Array2<f64>) -> Result<Self, ConfigError> {
+        let class_weights = match &config.class_weights {
+            ClassWeights::None => None,
+            ClassWeights::Balanced => {
+                let counts = labels.sum_axis(Axis(0));
+                let n_classes = class_names.len() as f64;
+                Some(counts.mapv(|c| labels.nrows() as f64 / (n_classes * c.max(1.0))))
+            }
+            ClassWeights::Manual { weights } => {
+                if let Some(name) = weights.keys().find(|name| !class_names.contains(name)) {
+                    return Err(invalid(
+                        "loss.class_weights.weights",
+                        format!("'{}' is not a class of the training data", name),
+                    ));
+                }
+                Some(class_names.iter().map(|name| weights.get(name).copied().unwrap_or(1.0)).collect())
+            }
+        };
+        Ok(Loss::new(config.function, class_weights, config.label_smoothing))
+    }
+
+    pub fn class_weights(&self) -> Option<&Array1<f64>> {
+        self.class_weights.as_ref()
+    }
+
+    /// One-hot targets moved towards the uniform distribution.
+    fn smoothed<F: Real>(&self, targets: &Array2<F>) -> Array2<F> {
+        if self.label_smoothing == 0.0 {
+            return targets.clone();
+        }
+        let uniform = F::cast_from(self.label_smoothing / targets.ncols() as f64);
+        let keep = F::cast_from(1.0 - self.label_smoothing);
+        targets.mapv(|t| t * keep + uniform)
+    }
+
+    /// Weight of each sample, from the class of its one-hot target.
+    fn sample_weights<F: Real>(&self, targets: &Array2<F>) -> Array1<F> {
+        match &self.class_weights {
+            Some(weights) => argmax_rows(targets).into_iter().map(|c| F::cast_from(weights[c])).collect(),
+            None => Array1::ones(targets.nrows()),
+        }
+    }
+
+    fn gamma(&self) -> f64 {
+        match self.function {
+            LossFunction::CrossEntropy => 0.0,
+            LossFunction::Focal { gamma } => gamma,
+        }
+    }
+
+    /// Mean loss over the samples for softmax `probabilities`.
+    pub fn value<F: Real>(&self, probabilities: &Array2<F>, targets: &Array2<F>) -> f64 {
+        let gamma = self.gamma();
+        let per_class = probabilities.mapv(|p| {
+            let p = p.into_f64();
+            -(1.0 - p).max(0.0).powf(gamma) * (p + 1e-15).ln()
+        });
+        let per_sample = (self.smoothed(targets).mapv(F::into_f64) * per_class).sum_axis(Axis(1));
+        (per_sample * self.sample_weights(targets).mapv(F::into_f64)).sum() / targets.nrows() as f64
+    }
+
+    /// Gradient of each sample's loss with respect to the softmax inputs,
+    /// not yet divided by the batch size.
+    pub fn gradient<F: Real>(&self, probabilities: &Array2<F>, targets: &Array2<F>) -> Array2<F> {
+        let weights = self.sample_weights(targets).insert_axis(Axis(1));
+        let targets = &self.smoothed(targets);
+        if self.gamma() == 0.0 {
+            // Softmax and cross-entropy combine to p - t
+            return (probabilities - targets) * &weights;
+        }
+        let gamma = F::cast_from(self.gamma());
+        let (one, epsilon) = (F::one(), F::cast_from(1e-15));
+
+        // Chain the derivative with respect to each probability through the
+        // softmax Jacobian: dz_j = p_j * (g_j - sum_k g_k p_k)
+        let p = probabilities.mapv(|p| p.max(epsilon).min(one - epsilon));
+        let g = Array2::from_shape_fn(p.raw_dim(), |(i, k)| {
+            let (p, t) = (p[[i, k]], targets[[i, k]]);
+            -t * ((one - p).powf(gamma) / p - gamma * (one - p).powf(gamma - one) * p.ln())
+        });
+        let expected = (&g * &p).sum_axis(Axis(1)).insert_axis(Axis(1));
+        &p * &(g - expected) * &weights
+    }
+}
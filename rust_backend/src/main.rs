@@ -1,102 +1,506 @@
-mod data;
-mod model;
-mod utils;
-
-use ndarray::{Array2, Axis};
+use fruit_classifier::boosting;
+use fruit_classifier::boundary;
+use fruit_classifier::classifier::{Classifier, TrainedModel};
+use fruit_classifier::codegen;
+use fruit_classifier::compare;
+use fruit_classifier::config::TrainingConfig;
+use fruit_classifier::data;
+use fruit_classifier::embedding;
+use fruit_classifier::ensemble;
+use fruit_classifier::explain::{ExplainMethod, Explanation};
+use fruit_classifier::forest;
+use fruit_classifier::importance;
+use fruit_classifier::onnx;
+use fruit_classifier::precision::Precision;
+use fruit_classifier::quantization;
+use fruit_classifier::training::{self, TrainingResult};
+use fruit_classifier::tuning::{self, TuningConfig};
+use fruit_classifier::utils::FEATURE_NAMES;
+use std::env;
 use std::io;
-use utils::{encode_labels, Normalizer};
+use std::process;
 use plotters::prelude::*;
 
-#[derive(Debug)]
-pub struct TrainingResult {
-    pub model: model::NeuralNet,
-    pub accuracies: Vec<f64>,
-    pub losses: Vec<f64>,
-    pub final_accuracy: f64,
-    pub class_names: Vec<String>,
-}
-
-pub fn train_model() -> TrainingResult {
-    // Load and shuffle dataset
-    let mut samples = data::load_dataset("dataset/fruits_dataset.csv")
-        .expect("Failed to load dataset");
-    rand::seq::index::sample(&mut rand::thread_rng(), samples.len(), samples.len())
-        .into_iter()
-        .for_each(|i| samples.swap(i, 0));
-
-    // Split data
-    let split_index = (samples.len() as f64 * 0.8) as usize;
-    let (train, test) = samples.split_at(split_index);
-
-    // Initialize and fit normalizer
-    let mut normalizer = Normalizer::new();
-    normalizer.fit(train);
-    let train_features = normalizer.transform(train);
-    let test_features = normalizer.transform(test);
+const USAGE: &str = "Usage: fruit_classifier [--config <training.toml>] [--save <model.json>] [--explain <method>]
+       fruit_classifier predict <model.json> [--input <measurements.csv>] [--explain <method>]
+       fruit_classifier export <model.json> [--onnx <model.onnx>] [--c <model.c>] [--rust <model.rs>]
+       fruit_classifier tune <tuning.toml> [--leaderboard <leaderboard.csv>] [--save <model.json>]
+       fruit_classifier compare [--config <training.toml>]
+       fruit_classifier ensemble [--config <training.toml>] [--save <ensemble.json>]
+       fruit_classifier forest [--config <training.toml>] [--save <forest.json>]
+       fruit_classifier boosting [--config <training.toml>] [--save <boosting.json>]
+       fruit_classifier importance [--config <training.toml>] [--save <model.json>]
+       fruit_classifier boundary [--config <training.toml>] [--save <model.json>]
+       fruit_classifier embedding [--config <training.toml>] [--save <model.json>]
+       fruit_classifier quantize [--config <training.toml>] [--save <model.json>]";
 
-    println!("Normalization parameters:");
-    println!("Means: {:?}", normalizer.mean);
-    println!("Std devs: {:?}", normalizer.std);
-
-    // Prepare labels
-    let train_labels: Vec<String> = train.iter().map(|s| s.label.clone()).collect();
-    let test_labels: Vec<String> = test.iter().map(|s| s.label.clone()).collect();
-
-    let class_names = {
-        let mut temp = train_labels.clone();
-        temp.sort();
-        temp.dedup();
-        if let Some(pos) = temp.iter().position(|x| x == "unknown") {
-            temp.swap_remove(pos);
-            temp.push("unknown".to_string());
-        }
-        temp
+#[derive(Default, PartialEq)]
+enum Command {
+    #[default]
+    Train,
+    /// Hyperparameter search driven by a tuning config
+    Tune(String),
+    Compare,
+    Ensemble,
+    Forest,
+    Boosting,
+    /// Permutation feature importance of a freshly trained network
+    Importance,
+    /// Decision regions of a freshly trained network over two features
+    Boundary,
+    /// PCA and t-SNE projections of a freshly trained network's hidden layer
+    Embedding,
+    /// Int8 post-training quantization of a freshly trained network
+    Quantize,
+    /// Predictions of a saved network, interactively or for a CSV file
+    Predict(String),
+    /// Conversion of a saved network for other runtimes
+    Export(String),
+}
+
+#[derive(Default)]
+struct Args {
+    command: Command,
+    config: Option<String>,
+    save: Option<String>,
+    leaderboard: Option<String>,
+    input: Option<String>,
+    onnx: Option<String>,
+    c: Option<String>,
+    rust: Option<String>,
+    explain: Option<ExplainMethod>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args::default();
+    let mut iter = env::args().skip(1).peekable();
+    match iter.peek().map(String::as_str) {
+        Some("tune") => {
+            iter.next();
+            args.command = Command::Tune(iter.next().ok_or("tune expects a tuning config path")?);
+        }
+        Some("compare") => {
+            iter.next();
+            args.command = Command::Compare;
+        }
+        Some("ensemble") => {
+            iter.next();
+            args.command = Command::Ensemble;
+        }
+        Some("forest") => {
+            iter.next();
+            args.command = Command::Forest;
+        }
+        Some("boosting") => {
+            iter.next();
+            args.command = Command::Boosting;
+        }
+        Some("importance") => {
+            iter.next();
+            args.command = Command::Importance;
+        }
+        Some("boundary") => {
+            iter.next();
+            args.command = Command::Boundary;
+        }
+        Some("embedding") => {
+            iter.next();
+            args.command = Command::Embedding;
+        }
+        Some("quantize") => {
+            iter.next();
+            args.command = Command::Quantize;
+        }
+        Some("predict") => {
+            iter.next();
+            args.command = Command::Predict(iter.next().ok_or("predict expects a model path")?);
+        }
+        Some("export") => {
+            iter.next();
+            args.command = Command::Export(iter.next().ok_or("export expects a model path")?);
+        }
+        _ => {}
+    }
+
+    while let Some(arg) = iter.next() {
+        let is_tune = matches!(args.command, Command::Tune(_));
+        let is_predict = matches!(args.command, Command::Predict(_));
+        let is_export = matches!(args.command, Command::Export(_));
+        if arg == "--explain" && (is_predict || args.command == Command::Train) {
+            let method = iter.next().ok_or("--explain expects a method")?;
+            args.explain = Some(method.parse()?);
+            continue;
+        }
+        let slot = match arg.as_str() {
+            "--config" if !is_tune && !is_predict && !is_export => &mut args.config,
+            "--leaderboard" if is_tune => &mut args.leaderboard,
+            "--input" if is_predict => &mut args.input,
+            "--onnx" if is_export => &mut args.onnx,
+            "--c" if is_export => &mut args.c,
+            "--rust" if is_export => &mut args.rust,
+            "--save" if args.command != Command::Compare && !is_predict && !is_export => &mut args.save,
+            _ => return Err(format!("Unknown argument '{}'", arg)),
+        };
+        *slot = Some(iter.next().ok_or_else(|| format!("{} expects a path", arg))?);
+    }
+    if matches!(args.command, Command::Export(_)) && args.onnx.is_none() && args.c.is_none() && args.rust.is_none() {
+        return Err("export expects --onnx, --c or --rust <path>".to_string());
+    }
+    Ok(args)
+}
+
+fn exit_with_error(err: impl std::fmt::Display) -> ! {
+    eprintln!("Error: {}", err);
+    process::exit(1);
+}
+
+fn save_model(result: &TrainingResult, path: &str) {
+    // Models trained in f32 keep f32 parameters on disk
+    let saved = match result.config.model.precision {
+        Precision::F64 => result.save(path),
+        Precision::F32 => result.cast::<f32>().save(path),
     };
+    match saved {
+        Ok(()) => println!("Model saved to {}", path),
+        Err(e) => eprintln!("Error saving model to {}: {}", path, e),
+    }
+}
 
-    let train_encoded = encode_labels(&train_labels, &class_names);
-    let test_encoded = encode_labels(&test_labels, &class_names);
+fn compare(config: &TrainingConfig) {
+    println!("Comparing models on a {:.0}/{:.0} split...", config.split.train * 100.0, (1.0 - config.split.train) * 100.0);
+    let reports = compare::run_comparison(config).unwrap_or_else(|e| exit_with_error(e));
 
-    // Training parameters
-    let input_size = 4;
-    let hidden_size = 16;
-    let output_size = class_names.len();
-    let learning_rate = 0.01;
-    let epochs = 5000;
-    let batch_size = 32;
+    println!(
+        "\n{:<20} {:>10} {:>10} {:>10} {:>10} {:>10}",
+        "Model", "Train Acc", "Val Acc", "Log Loss", "Macro F1", "Fit (s)"
+    );
+    for report in &reports {
+        println!(
+            "{:<20} {:>9.2}% {:>9.2}% {:>10.4} {:>10.4} {:>10.3}",
+            report.model,
+            report.train.accuracy * 100.0,
+            report.validation.accuracy * 100.0,
+            report.validation.log_loss,
+            report.validation.macro_f1,
+            report.fit_seconds
+        );
+    }
+}
 
-    let mut nn = model::NeuralNet::new(input_size, hidden_size, output_size, learning_rate);
-    
-    println!("Training started for {} epochs...", epochs);
-    for epoch in 0..epochs {
-        nn.train_one_epoch(&train_features, &train_encoded, batch_size);
+fn ensemble(config: &TrainingConfig, save: Option<&str>) {
+    let settings = &config.ensemble;
+    println!(
+        "Training an ensemble of {} networks ({:?}, {:?}) for {} epochs each...",
+        settings.members, settings.resampling, settings.combination, config.schedule.epochs
+    );
+    let ensemble = ensemble::run_ensemble_training(config).unwrap_or_else(|e| exit_with_error(e));
 
-        if epoch % 50 == 0 || epoch == epochs - 1 {
-            let accuracy = nn.evaluate(&train_features, &train_encoded);
-            let test_accuracy = nn.evaluate(&test_features, &test_encoded);
-            let loss = nn.losses.last().copied().unwrap_or(0.0);
-            
-            println!("Epoch {}/{} - Loss: {:.4} - Train Acc: {:.2}% - Test Acc: {:.2}%", 
-                epoch + 1, epochs, loss, accuracy * 100.0, test_accuracy * 100.0);
+    for (i, accuracy) in ensemble.member_accuracies.iter().enumerate() {
+        println!("Member {} - Test Acc: {:.2}%", i + 1, accuracy * 100.0);
+    }
+    println!("Ensemble test accuracy: {:.2}%", ensemble.final_accuracy * 100.0);
+
+    if let Some(path) = save {
+        match ensemble.save(path) {
+            Ok(()) => println!("Ensemble saved to {}", path),
+            Err(e) => eprintln!("Error saving ensemble to {}: {}", path, e),
         }
     }
 
-    let final_accuracy = nn.evaluate(&test_features, &test_encoded);
-    println!("Training completed. Final test accuracy: {:.2}%", final_accuracy * 100.0);
+    manual_testing(|m| {
+        let p = ensemble.predict(m[0], m[1], m[2], m[3]);
+        format!(
+            "Prediction: {} ({:.1}% confidence, {:.0}% of members disagree)",
+            p.label,
+            p.confidence * 100.0,
+            p.disagreement * 100.0
+        )
+    });
+}
 
-    // Clone necessary fields before moving nn
-    let accuracies = nn.accuracies.clone();
-    let losses = nn.losses.clone();
-    
-    TrainingResult {
-        model: nn,
-        accuracies,
-        losses,
-        final_accuracy,
-        class_names,
+/// Reports and optionally saves a fitted tree ensemble, then starts the
+/// manual testing loop with it.
+fn tree_model<M: Classifier + serde::Serialize + serde::de::DeserializeOwned>(
+    trained: TrainedModel<M>,
+    importances: &[f64],
+    save: Option<&str>,
+) {
+    println!("Test accuracy: {:.2}%", trained.final_accuracy * 100.0);
+    println!("\n{:<10} {:>10}", "Feature", "Importance");
+    for (name, importance) in FEATURE_NAMES.iter().zip(importances) {
+        println!("{:<10} {:>10.4}", name, importance);
     }
+
+    if let Some(path) = save {
+        match trained.save(path) {
+            Ok(()) => println!("Model saved to {}", path),
+            Err(e) => eprintln!("Error saving model to {}: {}", path, e),
+        }
+    }
+
+    manual_testing(|m| {
+        let (prediction, confidence) = trained.predict(m[0], m[1], m[2], m[3]);
+        format!("Prediction: {} ({:.1}% confidence)", prediction, confidence * 100.0)
+    });
 }
 
-fn plot_training_results(accuracies: &[f64], losses: &[f64]) -> Result<(), Box<dyn std::error::Error>> {
+fn feature_importance(config: &TrainingConfig, save: Option<&str>) {
+    let settings = &config.importance;
+    println!(
+        "Training for {} epochs, then shuffling each feature {} times on the validation split...",
+        config.schedule.epochs, settings.repeats
+    );
+    let (result, importances) = importance::run_importance(config).unwrap_or_else(|e| exit_with_error(e));
+    println!("Test accuracy: {:.2}%", result.final_accuracy * 100.0);
+
+    let interval = format!("{:.0}% CI", settings.confidence * 100.0);
+    println!(
+        "\n{:<10} {:>10} {:>20} {:>12} {:>20}",
+        "Feature", "Acc Drop", interval, "Loss Incr", interval
+    );
+    for f in &importances {
+        println!(
+            "{:<10} {:>9.2}% {:>20} {:>12.4} {:>20}",
+            f.feature,
+            f.accuracy_drop.mean * 100.0,
+            format!("[{:.2}%, {:.2}%]", f.accuracy_drop.lower * 100.0, f.accuracy_drop.upper * 100.0),
+            f.log_loss_increase.mean,
+            format!("[{:.4}, {:.4}]", f.log_loss_increase.lower, f.log_loss_increase.upper)
+        );
+    }
+
+    match importance::plot_importances(&importances, &settings.output) {
+        Ok(()) => println!("Importance chart saved to {}", settings.output),
+        Err(e) => eprintln!("Error plotting feature importance: {}", e),
+    }
+    if let Some(path) = save {
+        save_model(&result, path);
+    }
+}
+
+/// Prediction line for the manual testing loop, followed by the
+/// contribution of each measurement when an explanation method is set.
+fn describe_prediction(result: &TrainingResult, m: &[f64], explain: Option<ExplainMethod>) -> String {
+    let Some(method) = explain else {
+        let (prediction, confidence) = result.predict(m[0], m[1], m[2], m[3]);
+        return format!("Prediction: {} ({:.1}% confidence)", prediction, confidence * 100.0);
+    };
+    let (prediction, explanation) = result.explain(m[0], m[1], m[2], m[3], method);
+    let mut text = format!(
+        "Prediction: {} ({:.1}% confidence)\n{} relative to the baseline ({:.1}%):",
+        prediction,
+        explanation.probability * 100.0,
+        method,
+        explanation.base_value * 100.0
+    );
+    for (name, attribution) in FEATURE_NAMES.iter().zip(&explanation.attributions) {
+        text.push_str(&format!("\n  {:<8} {:>+8.2}%", name, attribution * 100.0));
+    }
+    text
+}
+
+/// Loads a saved network and classifies every row of `args.input` as CSV on
+/// stdout, or starts the manual testing loop when there is no input file.
+fn predict(path: &str, args: &Args) {
+    let result = TrainingResult::load(path)
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to load model from '{}': {}", path, e)));
+    let Some(input) = &args.input else {
+        manual_testing(|m| describe_prediction(&result, m, args.explain));
+        return;
+    };
+
+    let rows = data::load_measurements_with_schema(input, &result.config.data.schema)
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to load measurements '{}': {}", input, e)));
+    let mut header = format!("{},label,confidence", FEATURE_NAMES.join(","));
+    if args.explain.is_some() {
+        header.push_str(",base_value");
+        for name in FEATURE_NAMES {
+            header.push_str(&format!(",{}_attribution", name));
+        }
+    }
+    println!("{}", header);
+
+    for m in rows {
+        let values: Vec<String> = m.iter().map(|v| v.to_string()).collect();
+        let line = match args.explain {
+            None => {
+                let (label, confidence) = result.predict(m[0], m[1], m[2], m[3]);
+                format!("{},{},{:.6}", values.join(","), label, confidence)
+            }
+            Some(method) => {
+                let (label, explanation) = result.explain(m[0], m[1], m[2], m[3], method);
+                format!("{},{},{:.6},{}", values.join(","), label, explanation.probability, explanation_columns(&explanation))
+            }
+        };
+        println!("{}", line);
+    }
+}
+
+fn explanation_columns(explanation: &Explanation) -> String {
+    let attributions: Vec<String> = explanation.attributions.iter().map(|a| format!("{:.6}", a)).collect();
+    format!("{:.6},{}", explanation.base_value, attributions.join(","))
+}
+
+fn decision_boundary(config: &TrainingConfig, save: Option<&str>) {
+    let settings = &config.boundary;
+    println!("Training for {} epochs, then plotting {} against {}...", config.schedule.epochs, settings.x, settings.y);
+    let (result, train) = boundary::run_boundary(config).unwrap_or_else(|e| exit_with_error(e));
+    println!("Test accuracy: {:.2}%", result.final_accuracy * 100.0);
+
+    match boundary::plot_decision_boundary(&result, &train, settings) {
+        Ok(()) => println!("Decision regions saved to {}", settings.output),
+        Err(e) => eprintln!("Error plotting decision regions: {}", e),
+    }
+    if let Some(path) = save {
+        save_model(&result, path);
+    }
+}
+
+fn hidden_embedding(config: &TrainingConfig, save: Option<&str>) {
+    println!(
+        "Training for {} epochs, then projecting the hidden layer with PCA and t-SNE ({} iterations)...",
+        config.schedule.epochs, config.embedding.iterations
+    );
+    let (result, embedding) = embedding::run_embedding(config).unwrap_or_else(|e| exit_with_error(e));
+    println!("Test accuracy: {:.2}%", result.final_accuracy * 100.0);
+
+    let confusions = embedding.confusions();
+    if confusions.is_empty() {
+        println!("Every embedded sample is classified correctly");
+    } else {
+        println!("\n{:<12} {:<12} {:>6}", "True", "Predicted", "Count");
+        for (label, prediction, count) in confusions {
+            println!("{:<12} {:<12} {:>6}", label, prediction, count);
+        }
+    }
+
+    let output = &config.embedding.output;
+    match embedding::plot_embedding(&embedding, output) {
+        Ok(()) => println!("Embedding plots saved to {}", output),
+        Err(e) => eprintln!("Error plotting the embedding: {}", e),
+    }
+    if let Some(path) = save {
+        save_model(&result, path);
+    }
+}
+
+fn quantize_model(config: &TrainingConfig, save: Option<&str>) {
+    let settings = &config.quantization;
+    println!(
+        "Training for {} epochs, then quantizing to int8 ({:?}, {:?}) with {} calibration samples...",
+        config.schedule.epochs, settings.granularity, settings.scheme, settings.calibration_samples
+    );
+    let model = quantization::run_quantization(config).unwrap_or_else(|e| exit_with_error(e));
+
+    let report = &model.report;
+    println!("Float test accuracy: {:.2}%", report.float_accuracy * 100.0);
+    println!("Int8 test accuracy:  {:.2}%", report.quantized_accuracy * 100.0);
+    println!("Accuracy drop:       {:.2}%", report.accuracy_drop * 100.0);
+    println!("Agreement:           {:.2}%", report.agreement * 100.0);
+    println!("Max probability error: {:.4}", report.max_probability_error);
+
+    if let Some(path) = save {
+        match model.save(path) {
+            Ok(()) => println!("Float and int8 models saved to {}", path),
+            Err(e) => eprintln!("Error saving model to {}: {}", path, e),
+        }
+    }
+
+    manual_testing(|m| {
+        let (float, float_confidence) = model.float.predict(m[0], m[1], m[2], m[3]);
+        let (int8, int8_confidence) = model.predict(m[0], m[1], m[2], m[3]);
+        format!(
+            "Prediction: {} ({:.1}% confidence), int8: {} ({:.1}% confidence)",
+            float,
+            float_confidence * 100.0,
+            int8,
+            int8_confidence * 100.0
+        )
+    });
+}
+
+/// Writes a saved network in the formats requested on the command line.
+fn export(path: &str, args: &Args) {
+    let result = TrainingResult::load(path)
+        .unwrap_or_else(|e| exit_with_error(format!("Failed to load model from '{}': {}", path, e)));
+    if let Some(output) = &args.onnx {
+        match onnx::export_onnx(&result, output) {
+            Ok(()) => println!("ONNX model saved to {}", output),
+            Err(e) => exit_with_error(format!("Failed to export ONNX model to '{}': {}", output, e)),
+        }
+    }
+    if let Some(output) = &args.c {
+        match codegen::write_c(&result, output) {
+            Ok(()) => println!("C source saved to {} (with its header)", output),
+            Err(e) => exit_with_error(format!("Failed to generate C source '{}': {}", output, e)),
+        }
+    }
+    if let Some(output) = &args.rust {
+        match codegen::write_rust(&result, output) {
+            Ok(()) => println!("Rust source saved to {}", output),
+            Err(e) => exit_with_error(format!("Failed to generate Rust source '{}': {}", output, e)),
+        }
+    }
+}
+
+fn tune(path: &str, args: &Args) {
+    let tuning = TuningConfig::from_file(path).unwrap_or_else(|e| exit_with_error(e));
+
+    println!("Hyperparameter search started...");
+    let search = tuning::run_search_with_callback(&tuning, |t| match &t.error {
+        None => println!(
+            "Candidate {} ({} epochs) - hidden: {} lr: {:.5} batch: {} momentum: {:.2} l2: {:.5} - Score: {:.2}% (± {:.2})",
+            t.candidate, t.epochs, t.params.hidden_size, t.params.learning_rate, t.params.batch_size,
+            t.params.momentum, t.params.l2, t.score * 100.0, t.score_std * 100.0
+        ),
+        Some(e) => println!("Candidate {} ({} epochs) failed: {}", t.candidate, t.epochs, e),
+    })
+    .unwrap_or_else(|e| exit_with_error(e));
+
+    let leaderboard = args.leaderboard.as_deref().unwrap_or("leaderboard.csv");
+    match search.write_leaderboard(leaderboard) {
+        Ok(()) => println!("Leaderboard saved to {}", leaderboard),
+        Err(e) => eprintln!("Error writing leaderboard to {}: {}", leaderboard, e),
+    }
+
+    let (Some(best), Some(config)) = (search.best(), search.best_config()) else {
+        return;
+    };
+    println!("Best candidate: {} with {:.2}% validation accuracy", best.candidate, best.score * 100.0);
+
+    if let Some(save) = &args.save {
+        println!("Retraining the best candidate for {} epochs...", config.schedule.epochs);
+        let result = training::run_training(&config).unwrap_or_else(|e| exit_with_error(e));
+        println!("Final test accuracy: {:.2}%", result.final_accuracy * 100.0);
+        save_model(&result, save);
+    }
+}
+
+pub fn train_model(config: &TrainingConfig) -> TrainingResult {
+    let epochs = config.schedule.epochs;
+
+    println!("Training started for {} epochs...", epochs);
+    let result = training::run_training_from_csv_with_callback(&config.data.path, config, |m| {
+        if m.epoch % 50 == 1 || m.epoch == epochs {
+            println!("Epoch {}/{} - Loss: {:.4} - Train Acc: {:.2}% - Test Acc: {:.2}%",
+                m.epoch, epochs, m.loss, m.train_accuracy * 100.0, m.val_accuracy * 100.0);
+        }
+        true
+    })
+    .unwrap_or_else(|e| exit_with_error(e));
+
+    println!("Normalization parameters:");
+    println!("Means: {:?}", result.normalizer.mean);
+    println!("Std devs: {:?}", result.normalizer.std);
+    println!("Training completed. Final test accuracy: {:.2}%", result.final_accuracy * 100.0);
+
+    result
+}
+
+fn plot_training_results(result: &TrainingResult) -> Result<(), Box<dyn std::error::Error>> {
+    let (accuracies, losses) = (result.accuracies(), result.losses());
+    let (val_accuracies, val_losses) = (result.val_accuracies(), result.val_losses());
+
     let root = BitMapBackend::new("training_plots.png", (1200, 800)).into_drawing_area();
     root.fill(&WHITE)?;
 
@@ -117,24 +521,43 @@ fn plot_training_results(accuracies: &[f64], losses: &[f64]) -> Result<(), Box<d
     chart
         .draw_series(LineSeries::new(
             accuracies.iter().enumerate().map(|(x, y)| (x, *y)),
-            &RED,
+            RED,
         ))?
         .label("Accuracy")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &RED));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], RED));
 
     // Plot loss
     chart
         .draw_series(LineSeries::new(
             losses.iter().enumerate().map(|(x, y)| (x, *y)),
-            &BLUE,
+            BLUE,
         ))?
         .label("Loss")
-        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &BLUE));
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLUE));
+
+    // Plot validation accuracy and loss
+    let val_accuracy_color = RED.mix(0.4);
+    chart
+        .draw_series(LineSeries::new(
+            val_accuracies.iter().enumerate().map(|(x, y)| (x, *y)),
+            val_accuracy_color,
+        ))?
+        .label("Val Accuracy")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], val_accuracy_color));
+
+    let val_loss_color = BLUE.mix(0.4);
+    chart
+        .draw_series(LineSeries::new(
+            val_losses.iter().enumerate().map(|(x, y)| (x, *y)),
+            val_loss_color,
+        ))?
+        .label("Val Loss")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], val_loss_color));
 
     chart
         .configure_series_labels()
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
         .draw()?;
 
     root.present()?;
@@ -143,35 +566,98 @@ fn plot_training_results(accuracies: &[f64], losses: &[f64]) -> Result<(), Box<d
 }
 
 fn main() {
-    let result = train_model();
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("Error: {}", e);
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    });
+    if let Command::Tune(path) = &args.command {
+        tune(path, &args);
+        return;
+    }
+    if let Command::Predict(path) = &args.command {
+        predict(path, &args);
+        return;
+    }
+    if let Command::Export(path) = &args.command {
+        export(path, &args);
+        return;
+    }
+
+    let config = match &args.config {
+        Some(path) => TrainingConfig::from_file(path).unwrap_or_else(|e| exit_with_error(e)),
+        None => TrainingConfig::default(),
+    };
+    if args.command == Command::Compare {
+        compare(&config);
+        return;
+    }
+    if args.command == Command::Ensemble {
+        ensemble(&config, args.save.as_deref());
+        return;
+    }
+    if args.command == Command::Importance {
+        feature_importance(&config, args.save.as_deref());
+        return;
+    }
+    if args.command == Command::Boundary {
+        decision_boundary(&config, args.save.as_deref());
+        return;
+    }
+    if args.command == Command::Embedding {
+        hidden_embedding(&config, args.save.as_deref());
+        return;
+    }
+    if args.command == Command::Quantize {
+        quantize_model(&config, args.save.as_deref());
+        return;
+    }
+    if args.command == Command::Forest {
+        println!("Training a random forest of {} trees...", config.forest.n_trees);
+        let trained = forest::run_forest_training(&config).unwrap_or_else(|e| exit_with_error(e));
+        let importances = trained.model.feature_importances();
+        tree_model(trained, &importances, args.save.as_deref());
+        return;
+    }
+    if args.command == Command::Boosting {
+        println!("Training gradient-boosted trees for {} rounds...", config.boosting.n_rounds);
+        let trained = boosting::run_boosting_training(&config).unwrap_or_else(|e| exit_with_error(e));
+        let importances = trained.model.feature_importances();
+        tree_model(trained, &importances, args.save.as_deref());
+        return;
+    }
+
+    let result = train_model(&config);
+    if let Some(path) = &args.save {
+        save_model(&result, path);
+    }
     
     // Plot training results
-    if let Err(e) = plot_training_results(&result.accuracies, &result.losses) {
+    if let Err(e) = plot_training_results(&result) {
         eprintln!("Error plotting training results: {}", e);
     }
-    
+
+    manual_testing(|m| describe_prediction(&result, m, args.explain));
+}
+
+/// Reads measurements from stdin until 'q' or end of input and prints what
+/// `predict` reports for each.
+fn manual_testing(predict: impl Fn(&[f64]) -> String) {
     println!("\nManual Testing Mode");
     println!("Format: weight(g) size(cm) width(cm) height(cm)");
     println!("Example: 150 7 6 6");
     println!("Enter 'q' to quit\n");
     
-    let samples = data::load_dataset("dataset/fruits_dataset.csv").expect("Failed to load dataset");
-    let mut normalizer = Normalizer::new();
-    normalizer.fit(&samples);
-    
     loop {
         print!("Enter measurements > ");
         io::Write::flush(&mut io::stdout()).unwrap();
         
         let mut input = String::new();
-        io::stdin().read_line(&mut input).unwrap();
-        
-        if input.trim() == "q" {
+        if io::stdin().read_line(&mut input).unwrap() == 0 || input.trim() == "q" {
             break;
         }
         
         let parts: Vec<f64> = match input
-            .trim()
             .split_whitespace()
             .map(|s| s.parse())
             .collect::<Result<Vec<_>, _>>()
@@ -192,42 +678,6 @@ fn main() {
             println!("Warning: Values seem unusually large - expected weight(g), size/cm");
         }
 
-        let mut input_data = Array2::zeros((1, 4));
-        input_data[[0, 0]] = parts[0];
-        input_data[[0, 1]] = parts[1];
-        input_data[[0, 2]] = parts[2];
-        input_data[[0, 3]] = parts[3];
-        
-        normalizer.normalize(&mut input_data);
-
-        let (_, _, output) = result.model.forward(&input_data);
-        
-        let prediction = output
-            .map_axis(Axis(1), |row| {
-                row.iter()
-                    .enumerate()
-                    .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
-                    .map(|(i, &val)| (i, val))
-            })
-            .into_raw_vec()
-            .into_iter()
-            .next()
-            .flatten();
-
-        match prediction {
-            Some((predicted_class, confidence)) => {
-                let final_prediction = if confidence < 0.5 {
-                    "unknown"
-                } else {
-                    &result.class_names[predicted_class]
-                };
-                println!("Prediction: {} ({:.1}% confidence)\n", 
-                    final_prediction, 
-                    confidence * 100.0);
-            }
-            None => {
-                println!("Error: Failed to make prediction");
-            }
-        }
+        println!("{}\n", predict(&parts));
     }
 }
\ No newline at end of file
@@ -1,32 +1,80 @@
 // model.rs
-use ndarray::{Array2, Array1, Axis};
+use crate::layers::{BatchNorm, BatchNormCache, Dropout, Mode};
+use crate::loss::Loss;
+use crate::precision::Real;
+use ndarray::{Array, Array2, Array1, Axis, Dimension};
 use rand::Rng;
 use ndarray::s;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone)]
-pub struct NeuralNet {
-    weights1: Array2<f64>,
-    bias1: Array1<f64>,
-    weights2: Array2<f64>,
-    bias2: Array1<f64>,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(deserialize = "F: Deserialize<'de>"))]
+pub struct NeuralNet<F = f64> {
+    weights1: Array2<F>,
+    bias1: Array1<F>,
+    weights2: Array2<F>,
+    bias2: Array1<F>,
     learning_rate: f64,
+    l2_lambda: f64,
+    momentum: f64,
+    /// Normalizes the hidden pre-activations before the ReLU
+    #[serde(default)]
+    batch_norm: Option<BatchNorm<F>>,
+    /// Applied to the hidden activations while training
+    #[serde(default)]
+    dropout: Option<Dropout>,
+    /// Objective minimized by `train_one_epoch`
+    #[serde(default)]
+    loss: Loss,
+    #[serde(skip)]
+    velocity: Option<Velocity<F>>,
     pub accuracies: Vec<f64>,
     pub losses: Vec<f64>,
 }
 
-impl NeuralNet {
+/// A dense layer computing `x.dot(weights) + bias`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DenseLayer<F = f64> {
+    pub weights: Array2<F>,
+    pub bias: Array1<F>,
+}
+
+// Momentum buffers, only allocated once training with momentum starts
+#[derive(Debug, Clone)]
+struct Velocity<F> {
+    weights1: Array2<F>,
+    bias1: Array1<F>,
+    weights2: Array2<F>,
+    bias2: Array1<F>,
+}
+
+// Intermediate values of a training-mode forward pass
+struct TrainingPass<F> {
+    batch_norm: Option<BatchNormCache<F>>,
+    hidden_output: Array2<F>,
+    dropout_mask: Option<Array2<F>>,
+    /// Hidden activations after dropout, as seen by the output layer
+    dropped: Array2<F>,
+    output: Array2<F>,
+}
+
+impl<F: Real> NeuralNet<F> {
     pub fn new(input_size: usize, hidden_size: usize, output_size: usize, learning_rate: f64) -> Self {
-        let mut rng = rand::thread_rng();
-        
+        Self::with_rng(input_size, hidden_size, output_size, learning_rate, &mut rand::thread_rng())
+    }
+
+    /// Initial weights are drawn in `f64`, so both precisions start from
+    /// the same network for the same generator.
+    pub fn with_rng<R: Rng>(input_size: usize, hidden_size: usize, output_size: usize, learning_rate: f64, rng: &mut R) -> Self {
         // Xavier/Glorot initialization
         let weights1 = Array2::from_shape_fn((input_size, hidden_size), |_| {
-            rng.gen_range(-1.0..1.0) * (2.0 / (input_size + hidden_size) as f64).sqrt()
+            F::cast_from(rng.gen_range(-1.0..1.0) * (2.0 / (input_size + hidden_size) as f64).sqrt())
         });
         
         let bias1 = Array1::zeros(hidden_size);
         
         let weights2 = Array2::from_shape_fn((hidden_size, output_size), |_| {
-            rng.gen_range(-1.0..1.0) * (1.0 / (hidden_size + output_size) as f64).sqrt()
+            F::cast_from(rng.gen_range(-1.0..1.0) * (1.0 / (hidden_size + output_size) as f64).sqrt())
         });
         
         let bias2 = Array1::zeros(output_size);
@@ -37,67 +85,234 @@ impl NeuralNet {
             weights2,
             bias2,
             learning_rate,
+            l2_lambda: 0.001,
+            momentum: 0.0,
+            batch_norm: None,
+            dropout: None,
+            loss: Loss::default(),
+            velocity: None,
             accuracies: Vec::new(),
             losses: Vec::new(),
         }
     }
 
-    fn relu(&self, x: &Array2<f64>) -> Array2<f64> {
-        x.mapv(|v| if v > 0.0 { v } else { 0.0 })
+    pub fn learning_rate(&self) -> f64 {
+        self.learning_rate
+    }
+
+    pub fn set_learning_rate(&mut self, learning_rate: f64) {
+        self.learning_rate = learning_rate;
+    }
+
+    pub fn set_l2_lambda(&mut self, l2_lambda: f64) {
+        self.l2_lambda = l2_lambda;
+    }
+
+    /// Classical momentum; 0.0 is plain mini-batch SGD.
+    pub fn set_momentum(&mut self, momentum: f64) {
+        self.momentum = momentum;
+    }
+
+    /// Adds or removes batch normalization of the hidden layer.
+    pub fn set_batch_norm(&mut self, enabled: bool) {
+        self.batch_norm = enabled.then(|| BatchNorm::new(self.bias1.len()));
+    }
+
+    pub fn batch_norm(&self) -> Option<&BatchNorm<F>> {
+        self.batch_norm.as_ref()
     }
 
-    fn softmax(&self, x: &Array2<f64>) -> Array2<f64> {
-        let max_x = x.fold_axis(Axis(1), f64::NEG_INFINITY, |&max, &val| max.max(val));
-        let exp_x = (x - &max_x.insert_axis(Axis(1))).mapv(f64::exp);
+    /// Dropout on the hidden layer with masks drawn from `seed`; a rate of
+    /// 0.0 removes it.
+    pub fn set_dropout(&mut self, rate: f64, seed: u64) {
+        self.dropout = (rate > 0.0).then(|| Dropout::new(rate, seed));
+    }
+
+    pub fn dropout_rate(&self) -> f64 {
+        self.dropout.as_ref().map_or(0.0, Dropout::rate)
+    }
+
+    pub fn set_loss(&mut self, loss: Loss) {
+        self.loss = loss;
+    }
+
+    pub fn loss(&self) -> &Loss {
+        &self.loss
+    }
+
+    fn relu(&self, x: &Array2<F>) -> Array2<F> {
+        x.mapv(|v| if v > F::zero() { v } else { F::zero() })
+    }
+
+    fn softmax(&self, x: &Array2<F>) -> Array2<F> {
+        let max_x = x.fold_axis(Axis(1), F::neg_infinity(), |&max, &val| max.max(val));
+        let exp_x = (x - &max_x.insert_axis(Axis(1))).mapv(F::exp);
         let sum_exp = exp_x.sum_axis(Axis(1)).insert_axis(Axis(1));
         exp_x / sum_exp
     }
 
-    pub fn forward(&self, x: &Array2<f64>) -> (Array2<f64>, Array2<f64>, Array2<f64>) {
-        let hidden_input = x.dot(&self.weights1) + &self.bias1;
+    /// Inference-mode forward pass returning the hidden pre-activations,
+    /// the hidden activations and the class probabilities.
+    pub fn forward(&self, x: &Array2<F>) -> (Array2<F>, Array2<F>, Array2<F>) {
+        let mut hidden_input = x.dot(&self.weights1) + &self.bias1;
+        if let Some(batch_norm) = &self.batch_norm {
+            hidden_input = batch_norm.apply(&hidden_input);
+        }
         let hidden_output = self.relu(&hidden_input);
         let output_input = hidden_output.dot(&self.weights2) + &self.bias2;
         let output = self.softmax(&output_input);
         (hidden_input, hidden_output, output)
     }
 
-    pub fn train_one_epoch(&mut self, x: &Array2<f64>, y: &Array2<f64>, batch_size: usize) {
-        let l2_lambda = 0.001; // L2 regularization
-        
-        for batch in 0..(x.shape()[0] / batch_size) {
-            let start = batch * batch_size;
-            let end = (batch + 1) * batch_size;
+    /// The hidden and output layers as inference applies them, with batch
+    /// normalization folded into the hidden layer. Exporters only need
+    /// these, a ReLU after the first and a softmax after the second.
+    pub fn inference_layers(&self) -> (DenseLayer<F>, DenseLayer<F>) {
+        let hidden = match &self.batch_norm {
+            Some(batch_norm) => {
+                let scale = batch_norm.scale();
+                DenseLayer {
+                    weights: &self.weights1 * &scale,
+                    bias: &self.bias1 * &scale + batch_norm.shift(),
+                }
+            }
+            None => DenseLayer {
+                weights: self.weights1.clone(),
+                bias: self.bias1.clone(),
+            },
+        };
+        let output = DenseLayer {
+            weights: self.weights2.clone(),
+            bias: self.bias2.clone(),
+        };
+        (hidden, output)
+    }
+
+    /// Gradient of the inference-mode probability of `class` with respect to
+    /// each input feature, one row per sample.
+    pub fn input_gradient(&self, x: &Array2<F>, class: usize) -> Array2<F> {
+        let (hidden_input, _, output) = self.forward(x);
+        // d p_c / d z_k = p_c * (delta_ck - p_k)
+        let mut output_grad = output.mapv(|p| -p);
+        output_grad.column_mut(class).mapv_inplace(|v| v + F::one());
+        output_grad *= &output.column(class).insert_axis(Axis(1));
+
+        let mut hidden_grad = output_grad.dot(&self.weights2.t()) *
+            hidden_input.mapv(|v| if v > F::zero() { F::one() } else { F::zero() });
+        if let Some(batch_norm) = &self.batch_norm {
+            hidden_grad *= &batch_norm.scale();
+        }
+        hidden_grad.dot(&self.weights1.t())
+    }
+
+    /// Forward pass in the given mode. In training mode dropout masks are
+    /// drawn and the batch normalization statistics are updated, so the
+    /// hidden activations returned are the ones after dropout.
+    pub fn forward_with_mode(&mut self, x: &Array2<F>, mode: Mode) -> (Array2<F>, Array2<F>, Array2<F>) {
+        match mode {
+            Mode::Inference => self.forward(x),
+            Mode::Training => {
+                let (hidden_input, pass) = self.forward_training(x);
+                (hidden_input, pass.dropped, pass.output)
+            }
+        }
+    }
+
+    fn forward_training(&mut self, x: &Array2<F>) -> (Array2<F>, TrainingPass<F>) {
+        let mut hidden_input = x.dot(&self.weights1) + &self.bias1;
+        let mut batch_norm = None;
+        if let Some(layer) = &mut self.batch_norm {
+            let (normalized, cache) = layer.forward(&hidden_input, Mode::Training);
+            hidden_input = normalized;
+            batch_norm = cache;
+        }
+        let hidden_output = self.relu(&hidden_input);
+        let (dropped, dropout_mask) = match &mut self.dropout {
+            Some(layer) => layer.forward(&hidden_output, Mode::Training),
+            None => (hidden_output.clone(), None),
+        };
+        let output = self.softmax(&(dropped.dot(&self.weights2) + &self.bias2));
+        let pass = TrainingPass {
+            batch_norm,
+            hidden_output,
+            dropout_mask,
+            dropped,
+            output,
+        };
+        (hidden_input, pass)
+    }
+
+    pub fn train_one_epoch(&mut self, x: &Array2<F>, y: &Array2<F>, batch_size: usize) -> (f64, f64) {
+        let l2_lambda = F::cast_from(self.l2_lambda);
+        let (learning_rate, momentum) = (F::cast_from(self.learning_rate), F::cast_from(self.momentum));
+        let rows = x.shape()[0];
+
+        // The last batch takes the leftover rows, so every row trains
+        for start in (0..rows).step_by(batch_size) {
+            let end = (start + batch_size).min(rows);
+            let n = F::cast_from((end - start) as f64);
             let x_batch = x.slice(s![start..end, ..]).to_owned();
             let y_batch = y.slice(s![start..end, ..]).to_owned();
 
-            let (_hidden_input, hidden_output, output) = self.forward(&x_batch);
+            let (_hidden_input, pass) = self.forward_training(&x_batch);
 
-            let output_error = &output - &y_batch;
-            let hidden_error = output_error.dot(&self.weights2.t()) * 
-                hidden_output.mapv(|v| if v > 0.0 { 1.0 } else { 0.0 });
+            let output_error = self.loss.gradient(&pass.output, &y_batch);
+            let dropped_error = Dropout::backward(&output_error.dot(&self.weights2.t()), pass.dropout_mask.as_ref());
+            let mut hidden_error = dropped_error * 
+                pass.hidden_output.mapv(|v| if v > F::zero() { F::one() } else { F::zero() });
+            if let (Some(batch_norm), Some(cache)) = (&mut self.batch_norm, &pass.batch_norm) {
+                let (input_error, grads) = batch_norm.backward(&hidden_error, cache);
+                batch_norm.update(grads, self.learning_rate, self.momentum);
+                hidden_error = input_error;
+            }
 
             // Add L2 regularization
-            let weights2_grad = hidden_output.t().dot(&output_error) / batch_size as f64 + 
+            let weights2_grad = pass.dropped.t().dot(&output_error) / n + 
                 &self.weights2 * l2_lambda;
-            let weights1_grad = x_batch.t().dot(&hidden_error) / batch_size as f64 + 
+            let weights1_grad = x_batch.t().dot(&hidden_error) / n + 
                 &self.weights1 * l2_lambda;
+            let bias2_grad = output_error.sum_axis(Axis(0)) / n;
+            let bias1_grad = hidden_error.sum_axis(Axis(0)) / n;
+
+            if self.momentum > 0.0 {
+                let velocity = self.velocity.get_or_insert_with(|| Velocity {
+                    weights1: Array2::zeros(self.weights1.raw_dim()),
+                    bias1: Array1::zeros(self.bias1.raw_dim()),
+                    weights2: Array2::zeros(self.weights2.raw_dim()),
+                    bias2: Array1::zeros(self.bias2.raw_dim()),
+                });
+                velocity.weights2 = &velocity.weights2 * momentum + weights2_grad * learning_rate;
+                velocity.bias2 = &velocity.bias2 * momentum + bias2_grad * learning_rate;
+                velocity.weights1 = &velocity.weights1 * momentum + weights1_grad * learning_rate;
+                velocity.bias1 = &velocity.bias1 * momentum + bias1_grad * learning_rate;
 
-            self.weights2 -= &(weights2_grad * self.learning_rate);
-            self.bias2 -= &(output_error.sum_axis(Axis(0)) * (self.learning_rate / batch_size as f64));
-            self.weights1 -= &(weights1_grad * self.learning_rate);
-            self.bias1 -= &(hidden_error.sum_axis(Axis(0)) * (self.learning_rate / batch_size as f64));
+                self.weights2 -= &velocity.weights2;
+                self.bias2 -= &velocity.bias2;
+                self.weights1 -= &velocity.weights1;
+                self.bias1 -= &velocity.bias1;
+            } else {
+                self.weights2 -= &(weights2_grad * learning_rate);
+                self.bias2 -= &(bias2_grad * learning_rate);
+                self.weights1 -= &(weights1_grad * learning_rate);
+                self.bias1 -= &(bias1_grad * learning_rate);
+            }
         }
 
         let (_, _, output) = self.forward(x);
-        self.losses.push(self.cross_entropy_loss(&output, y));
-        self.accuracies.push(self.evaluate(x, y));
+        let loss = self.loss.value(&output, y);
+        let accuracy = self.evaluate(x, y);
+        self.losses.push(loss);
+        self.accuracies.push(accuracy);
+
+        (loss, accuracy)
     }
 
-    pub fn cross_entropy_loss(&self, y_pred: &Array2<f64>, y_true: &Array2<f64>) -> f64 {
-        -(y_true * &y_pred.mapv(|v| (v + 1e-15).ln())).sum() / y_true.shape()[0] as f64
+    pub fn cross_entropy_loss(&self, y_pred: &Array2<F>, y_true: &Array2<F>) -> f64 {
+        let epsilon = F::cast_from(1e-15);
+        -(y_true * &y_pred.mapv(|v| (v + epsilon).ln())).sum().into_f64() / y_true.shape()[0] as f64
     }
 
-    pub fn evaluate(&self, x: &Array2<f64>, y: &Array2<f64>) -> f64 {
+    pub fn evaluate(&self, x: &Array2<F>, y: &Array2<F>) -> f64 {
         let (_, _, output) = self.forward(x);
         let predictions = output.map_axis(Axis(1), |row| {
             row.iter()
@@ -120,4 +335,28 @@ impl NeuralNet {
             .filter(|&(p, t)| p == t)
             .count() as f64 / y.shape()[0] as f64
     }
+
+    /// The same network in another precision. Momentum buffers restart, so
+    /// training continues as after loading a saved model.
+    pub fn cast<G: Real>(&self) -> NeuralNet<G> {
+        NeuralNet {
+            weights1: cast(&self.weights1),
+            bias1: cast(&self.bias1),
+            weights2: cast(&self.weights2),
+            bias2: cast(&self.bias2),
+            learning_rate: self.learning_rate,
+            l2_lambda: self.l2_lambda,
+            momentum: self.momentum,
+            batch_norm: self.batch_norm.as_ref().map(BatchNorm::cast),
+            dropout: self.dropout.clone(),
+            loss: self.loss.clone(),
+            velocity: None,
+            accuracies: self.accuracies.clone(),
+            losses: self.losses.clone(),
+        }
+    }
+}
+
+fn cast<F: Real, G: Real, D: Dimension>(x: &Array<F, D>) -> Array<G, D> {
+    x.mapv(|v| G::cast_from(v.into_f64()))
 }
\ No newline at end of file
@@ -0,0 +1,189 @@
+// onnx.rs
+use crate::model::DenseLayer;
+use crate::training::TrainingResult;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+// IR version of ONNX 1.8, whose default operator set is 13
+const IR_VERSION: i64 = 7;
+const OPSET_VERSION: i64 = 13;
+
+// Graph input and outputs
+const INPUT: &str = "measurements";
+const PROBABILITIES: &str = "probabilities";
+const CLASS: &str = "class";
+
+// TensorProto.DataType
+const FLOAT: i64 = 1;
+const INT64: i64 = 7;
+// AttributeProto.AttributeType
+const ATTRIBUTE_INT: i64 = 2;
+
+/// Writes `result` as an ONNX model with the input `measurements` (raw
+/// weight, size, width and height, one row per fruit) and the outputs
+/// `probabilities` and `class`. The graph normalizes the measurements with
+/// Sub/Div, applies both dense layers (MatMul/Add) with a ReLU in between
+/// and ends in a Softmax and an ArgMax. Weights are stored as float32. The
+/// class names are in the `class_labels` metadata entry as a JSON array, in
+/// the order of the probability columns.
+pub fn export_onnx(result: &TrainingResult, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    fs::write(path, encode_model(result)?)?;
+    Ok(())
+}
+
+/// The serialized `ModelProto` written by `export_onnx`.
+pub fn encode_model(result: &TrainingResult) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (hidden, output) = result.model.inference_layers();
+    let class_labels = serde_json::to_string(&result.class_names)?;
+
+    let mut model = Message::default();
+    model.int64(1, IR_VERSION);
+    model.string(2, "fruit_classifier");
+    model.string(3, env!("CARGO_PKG_VERSION"));
+    model.message(7, &graph(result, &hidden, &output));
+    model.message(8, &{
+        let mut opset = Message::default();
+        opset.string(1, "");
+        opset.int64(2, OPSET_VERSION);
+        opset
+    });
+    for (key, value) in [("class_labels", class_labels.as_str()), ("feature_names", "weight,size,width,height")] {
+        let mut entry = Message::default();
+        entry.string(1, key);
+        entry.string(2, value);
+        model.message(14, &entry);
+    }
+    Ok(model.bytes)
+}
+
+fn graph(result: &TrainingResult, hidden: &DenseLayer, output: &DenseLayer) -> Message {
+    let mut graph = Message::default();
+    let nodes = [
+        node("Sub", &[INPUT, "mean"], "centered", &[]),
+        node("Div", &["centered", "std"], "normalized", &[]),
+        node("MatMul", &["normalized", "weights1"], "hidden_product", &[]),
+        node("Add", &["hidden_product", "bias1"], "hidden_input", &[]),
+        node("Relu", &["hidden_input"], "hidden", &[]),
+        node("MatMul", &["hidden", "weights2"], "output_product", &[]),
+        node("Add", &["output_product", "bias2"], "logits", &[]),
+        node("Softmax", &["logits"], PROBABILITIES, &[("axis", 1)]),
+        node("ArgMax", &[PROBABILITIES], CLASS, &[("axis", 1), ("keepdims", 0)]),
+    ];
+    for node in &nodes {
+        graph.message(1, node);
+    }
+    graph.string(2, "fruit_classifier");
+
+    let normalizer = &result.normalizer;
+    let initializers = [
+        tensor("mean", &[normalizer.mean.len()], normalizer.mean.iter()),
+        tensor("std", &[normalizer.std.len()], normalizer.std.iter()),
+        tensor("weights1", hidden.weights.shape(), hidden.weights.iter()),
+        tensor("bias1", hidden.bias.shape(), hidden.bias.iter()),
+        tensor("weights2", output.weights.shape(), output.weights.iter()),
+        tensor("bias2", output.bias.shape(), output.bias.iter()),
+    ];
+    for initializer in &initializers {
+        graph.message(5, initializer);
+    }
+
+    graph.message(11, &value_info(INPUT, FLOAT, &[None, Some(normalizer.mean.len() as i64)]));
+    graph.message(12, &value_info(PROBABILITIES, FLOAT, &[None, Some(output.bias.len() as i64)]));
+    graph.message(12, &value_info(CLASS, INT64, &[None]));
+    graph
+}
+
+// Integer attributes only; the graph needs no others
+fn node(op_type: &str, inputs: &[&str], output: &str, attributes: &[(&str, i64)]) -> Message {
+    let mut node = Message::default();
+    for input in inputs {
+        node.string(1, input);
+    }
+    node.string(2, output);
+    node.string(3, output);
+    node.string(4, op_type);
+    for &(name, value) in attributes {
+        let mut attr = Message::default();
+        attr.string(1, name);
+        attr.int64(3, value);
+        attr.int64(20, ATTRIBUTE_INT);
+        node.message(5, &attr);
+    }
+    node
+}
+
+// Row-major float32 tensor in `raw_data`
+fn tensor<'a>(name: &str, dims: &[usize], values: impl Iterator<Item = &'a f64>) -> Message {
+    let mut tensor = Message::default();
+    for &dim in dims {
+        tensor.int64(1, dim as i64);
+    }
+    tensor.int64(2, FLOAT);
+    tensor.string(8, name);
+    let raw: Vec<u8> = values.flat_map(|&v| (v as f32).to_le_bytes()).collect();
+    tensor.bytes(9, &raw);
+    tensor
+}
+
+// A tensor type with a symbolic batch dimension where `dims` has `None`
+fn value_info(name: &str, elem_type: i64, dims: &[Option<i64>]) -> Message {
+    let mut shape = Message::default();
+    for dim in dims {
+        let mut dimension = Message::default();
+        match dim {
+            Some(size) => dimension.int64(1, *size),
+            None => dimension.string(2, "batch"),
+        }
+        shape.message(1, &dimension);
+    }
+    let mut tensor_type = Message::default();
+    tensor_type.int64(1, elem_type);
+    tensor_type.message(2, &shape);
+    let mut type_proto = Message::default();
+    type_proto.message(1, &tensor_type);
+
+    let mut info = Message::default();
+    info.string(1, name);
+    info.message(2, &type_proto);
+    info
+}
+
+// Protocol buffers wire format, just the parts ONNX needs
+#[derive(Default)]
+struct Message {
+    bytes: Vec<u8>,
+}
+
+impl Message {
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+
+    fn key(&mut self, field: u32, wire_type: u32) {
+        self.varint(((field << 3) | wire_type) as u64);
+    }
+
+    fn int64(&mut self, field: u32, value: i64) {
+        self.key(field, 0);
+        self.varint(value as u64);
+    }
+
+    fn bytes(&mut self, field: u32, value: &[u8]) {
+        self.key(field, 2);
+        self.varint(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+    }
+
+    fn string(&mut self, field: u32, value: &str) {
+        self.bytes(field, value.as_bytes());
+    }
+
+    fn message(&mut self, field: u32, value: &Message) {
+        self.bytes(field, &value.bytes);
+    }
+}
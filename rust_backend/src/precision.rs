@@ -0,0 +1,54 @@
+// precision.rs
+use ndarray::NdFloat;
+use num_traits::FromPrimitive;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// Floating-point type a network is trained and evaluated in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Precision {
+    /// Half the memory and usually faster, at about 7 significant digits
+    F32,
+    #[default]
+    F64,
+}
+
+/// Element type of `NeuralNet`, `Normalizer` and `TrainingResult`: `f32` or
+/// `f64`. Measurements, hyperparameters and reported metrics stay `f64` and
+/// are converted at the boundary.
+pub trait Real: NdFloat + FromPrimitive + Default + Serialize + DeserializeOwned {
+    fn precision() -> Precision;
+
+    fn cast_from(value: f64) -> Self;
+
+    fn into_f64(self) -> f64;
+}
+
+impl Real for f32 {
+    fn precision() -> Precision {
+        Precision::F32
+    }
+
+    fn cast_from(value: f64) -> Self {
+        value as f32
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
+
+impl Real for f64 {
+    fn precision() -> Precision {
+        Precision::F64
+    }
+
+    fn cast_from(value: f64) -> Self {
+        value
+    }
+
+    fn into_f64(self) -> f64 {
+        self
+    }
+}
@@ -0,0 +1,267 @@
+// quantization.rs
+use crate::classifier::{argmax_rows, Classifier, Metrics};
+use crate::config::{Granularity, QuantScheme, QuantizationConfig, TrainingConfig};
+use crate::data::FruitSample;
+use crate::model::{DenseLayer, NeuralNet};
+use crate::training::{self, PreparedSplit, TrainingError, TrainingResult, UNKNOWN_CONFIDENCE_THRESHOLD};
+use crate::utils::measurement_row;
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use rand::seq::index;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+
+/// Affine mapping between reals and int8: `x = scale * (q - zero_point)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuantParams {
+    pub scale: f64,
+    pub zero_point: i32,
+}
+
+impl QuantParams {
+    /// Parameters covering `[min, max]`, widened to include zero so that
+    /// zero (ReLU outputs, padding) is represented exactly.
+    pub fn from_range(min: f64, max: f64, scheme: QuantScheme) -> Self {
+        let (min, max) = (min.min(0.0), max.max(0.0));
+        // A constant zero tensor still needs a usable scale
+        let nonzero = |scale: f64| if scale > 0.0 { scale } else { 1.0 };
+        match scheme {
+            QuantScheme::Symmetric => QuantParams {
+                scale: nonzero(max.max(-min) / 127.0),
+                zero_point: 0,
+            },
+            QuantScheme::Asymmetric => {
+                let scale = nonzero((max - min) / 255.0);
+                QuantParams {
+                    scale,
+                    zero_point: (-128.0 - min / scale).round().clamp(-128.0, 127.0) as i32,
+                }
+            }
+        }
+    }
+
+    fn from_values<'a>(values: impl Iterator<Item = &'a f64>, scheme: QuantScheme) -> Self {
+        let (min, max) = values.fold((0.0f64, 0.0f64), |(min, max), &v| (min.min(v), max.max(v)));
+        Self::from_range(min, max, scheme)
+    }
+
+    /// Nearest int8 value, saturating outside the range.
+    pub fn quantize(&self, x: f64) -> i8 {
+        ((x / self.scale).round() + self.zero_point as f64).clamp(-128.0, 127.0) as i8
+    }
+
+    pub fn dequantize(&self, q: i8) -> f64 {
+        self.scale * (q as i32 - self.zero_point) as f64
+    }
+}
+
+/// A dense layer with int8 weights and an int32 bias, evaluated with integer
+/// multiply-accumulates.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedLayer {
+    pub weights: Array2<i8>,
+    /// One entry per output unit, or a single one for per-tensor scales
+    pub weight_params: Vec<QuantParams>,
+    /// Bias in units of the input scale times each output's weight scale
+    pub bias: Array1<i32>,
+}
+
+impl QuantizedLayer {
+    /// Quantizes `layer` for inputs quantized with `input`.
+    pub fn new(layer: &DenseLayer, input: QuantParams, settings: &QuantizationConfig) -> Self {
+        let weight_params: Vec<QuantParams> = match settings.granularity {
+            Granularity::PerTensor => vec![QuantParams::from_values(layer.weights.iter(), settings.scheme)],
+            Granularity::PerChannel => layer
+                .weights
+                .columns()
+                .into_iter()
+                .map(|column| QuantParams::from_values(column.iter(), settings.scheme))
+                .collect(),
+        };
+        let mut quantized = QuantizedLayer {
+            weights: Array2::zeros(layer.weights.dim()),
+            bias: Array1::zeros(layer.bias.len()),
+            weight_params,
+        };
+        for ((i, j), &w) in layer.weights.indexed_iter() {
+            quantized.weights[[i, j]] = quantized.params(j).quantize(w);
+        }
+        // A nearly dead unit gets a tiny weight scale and so a huge bias;
+        // leave room for every product so the sum stays within int32
+        let limit = (i32::MAX as i64 - layer.weights.nrows() as i64 * 255 * 255).max(0) as f64;
+        for (j, &b) in layer.bias.iter().enumerate() {
+            let bias = (b / (input.scale * quantized.params(j).scale)).round();
+            quantized.bias[j] = bias.clamp(-limit, limit) as i32;
+        }
+        quantized
+    }
+
+    fn params(&self, output: usize) -> QuantParams {
+        self.weight_params[output.min(self.weight_params.len() - 1)]
+    }
+
+    /// Real-valued `x.dot(weights) + bias` for an input quantized with
+    /// `input`. `new` keeps the sum within int32; the accumulator is int64
+    /// so layers built by hand cannot overflow either.
+    pub fn forward(&self, x: ArrayView1<i8>, input: QuantParams) -> Array1<f64> {
+        Array1::from_shape_fn(self.bias.len(), |j| {
+            let params = self.params(j);
+            let accumulator = x.iter().zip(self.weights.column(j)).fold(self.bias[j] as i64, |acc, (&q, &w)| {
+                acc + ((q as i32 - input.zero_point) * (w as i32 - params.zero_point)) as i64
+            });
+            accumulator as f64 * input.scale * params.scale
+        })
+    }
+}
+
+/// `NeuralNet` after post-training quantization: int8 weights, int8 inputs
+/// and hidden activations, with batch normalization folded into the hidden
+/// layer. Logits are dequantized for the softmax.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QuantizedNet {
+    pub granularity: Granularity,
+    pub scheme: QuantScheme,
+    /// Normalized features
+    pub input: QuantParams,
+    pub hidden: QuantizedLayer,
+    /// Hidden activations after the ReLU
+    pub activation: QuantParams,
+    pub output: QuantizedLayer,
+}
+
+impl QuantizedNet {
+    /// Quantizes `model`, taking the activation ranges from normalized
+    /// `calibration` features.
+    pub fn quantize(model: &NeuralNet, calibration: &Array2<f64>, settings: &QuantizationConfig) -> Self {
+        let (hidden, output) = model.inference_layers();
+        let input = QuantParams::from_values(calibration.iter(), settings.scheme);
+        let (_, activations, _) = model.forward(calibration);
+        let activation = QuantParams::from_values(activations.iter(), settings.scheme);
+        QuantizedNet {
+            granularity: settings.granularity,
+            scheme: settings.scheme,
+            input,
+            hidden: QuantizedLayer::new(&hidden, input, settings),
+            activation,
+            output: QuantizedLayer::new(&output, activation, settings),
+        }
+    }
+
+    /// Class probabilities for normalized features, one row per sample.
+    pub fn forward(&self, features: &Array2<f64>) -> Array2<f64> {
+        let mut probabilities = Array2::zeros((features.nrows(), self.output.bias.len()));
+        for (row, mut out) in features.rows().into_iter().zip(probabilities.rows_mut()) {
+            let x = row.mapv(|v| self.input.quantize(v));
+            let hidden = self.hidden.forward(x.view(), self.input);
+            let h = hidden.mapv(|v| self.activation.quantize(v.max(0.0)));
+            let logits = self.output.forward(h.view(), self.activation);
+
+            let max = logits.fold(f64::NEG_INFINITY, |m, &v| m.max(v));
+            let exp = logits.mapv(|v| (v - max).exp());
+            out.assign(&(&exp / exp.sum()));
+        }
+        probabilities
+    }
+}
+
+impl Classifier for QuantizedNet {
+    fn name(&self) -> &str {
+        "int8_net"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        self.forward(features)
+    }
+}
+
+/// How much quantization costs on the validation split.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct QuantizationReport {
+    pub float_accuracy: f64,
+    pub quantized_accuracy: f64,
+    /// Float minus int8 accuracy; negative when quantization happens to help
+    pub accuracy_drop: f64,
+    /// Fraction of samples where both predict the same class
+    pub agreement: f64,
+    /// Largest absolute difference between the two sets of probabilities
+    pub max_probability_error: f64,
+}
+
+impl QuantizationReport {
+    pub fn compare(float: &NeuralNet, quantized: &QuantizedNet, features: &Array2<f64>, labels: &Array2<f64>) -> Self {
+        let float_probabilities = float.predict_proba(features);
+        let quantized_probabilities = quantized.predict_proba(features);
+        let float_accuracy = Metrics::from_probabilities(&float_probabilities, labels).accuracy;
+        let quantized_accuracy = Metrics::from_probabilities(&quantized_probabilities, labels).accuracy;
+
+        let (float_classes, quantized_classes) = (argmax_rows(&float_probabilities), argmax_rows(&quantized_probabilities));
+        let same = float_classes.iter().zip(&quantized_classes).filter(|(a, b)| a == b).count();
+        QuantizationReport {
+            float_accuracy,
+            quantized_accuracy,
+            accuracy_drop: float_accuracy - quantized_accuracy,
+            agreement: same as f64 / features.nrows().max(1) as f64,
+            max_probability_error: (&float_probabilities - &quantized_probabilities).fold(0.0, |m, d| m.max(d.abs())),
+        }
+    }
+}
+
+/// A trained network saved together with its int8 version. The float
+/// fields are stored at the top level, so the same file also loads with
+/// `TrainingResult::load`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuantizedModel {
+    #[serde(flatten)]
+    pub float: TrainingResult,
+    pub quantized: QuantizedNet,
+    pub report: QuantizationReport,
+}
+
+impl QuantizedModel {
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        training::save_json(self, path)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        training::load_json(path)
+    }
+
+    /// Like `TrainingResult::predict`, through the int8 network.
+    pub fn predict(&self, weight: f64, size: f64, width: f64, height: f64) -> (&str, f64) {
+        let mut input = measurement_row(weight, size, width, height);
+        self.float.normalizer.normalize(&mut input);
+
+        let probabilities = self.quantized.forward(&input);
+        let class = argmax_rows(&probabilities)[0];
+        let confidence = probabilities[[0, class]];
+        if confidence < UNKNOWN_CONFIDENCE_THRESHOLD || class >= self.float.class_names.len() {
+            ("unknown", confidence)
+        } else {
+            (&self.float.class_names[class], confidence)
+        }
+    }
+}
+
+pub fn run_quantization(config: &TrainingConfig) -> Result<QuantizedModel, TrainingError> {
+    config.validate()?;
+    let samples = training::load_samples(&config.data)?;
+    network_quantization(&samples, config)
+}
+
+/// Trains a network, quantizes it with a random calibration subset of the
+/// training split and compares both on the validation split.
+pub fn network_quantization(samples: &[FruitSample], config: &TrainingConfig) -> Result<QuantizedModel, TrainingError> {
+    config.validate()?;
+    let mut rng = training::seeded_rng(config.seed);
+    let (train, val) = training::split_samples(samples, config, &mut rng);
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    let float = training::train_prepared(data.clone(), config, &mut rng, |_| true)?;
+
+    let settings = &config.quantization;
+    let n = data.train_features.nrows();
+    let rows = index::sample(&mut rng, n, settings.calibration_samples.min(n)).into_vec();
+    let calibration = data.train_features.select(Axis(0), &rows);
+    let quantized = QuantizedNet::quantize(&float.model, &calibration, settings);
+    let report = QuantizationReport::compare(&float.model, &quantized, &data.val_features, &data.val_labels);
+    Ok(QuantizedModel { float, quantized, report })
+}
@@ -1,49 +1,557 @@
 use crate::{data::FruitSample, model::NeuralNet};
-use ndarray::Array2;
-use crate::utils::{encode_labels, normalize_features, split_dataset};
+use crate::config::{ConfigError, DataConfig, Monitor, TrainingConfig};
+use crate::augmentation::Augmenter;
+use crate::balancing;
+use crate::explain::{self, ExplainMethod, Explanation};
+use crate::loss::Loss;
+use crate::model::DenseLayer;
+use crate::precision::{Precision, Real};
+use crate::utils::{class_names, encode_labels, measurement_row, Normalizer, ScalerKind};
+use fruit_core::{Dense, Network, Scaler, FEATURES};
+use ndarray::{Array1, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{RngCore, SeedableRng};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::Path;
 
-pub struct TrainingResult {
-    pub accuracies: Vec<f64>,
-    pub losses: Vec<f64>,
+/// Predictions below this softmax probability are reported as "unknown".
+pub const UNKNOWN_CONFIDENCE_THRESHOLD: f64 = 0.5;
+
+/// Version of the JSON layout written by `TrainingResult::save`.
+pub const MODEL_FORMAT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct EpochMetrics {
+    /// 1-based epoch number
+    pub epoch: usize,
+    pub loss: f64,
+    pub train_accuracy: f64,
+    pub val_loss: f64,
+    pub val_accuracy: f64,
+}
+
+#[derive(Debug)]
+pub enum TrainingError {
+    Config(ConfigError),
+    Dataset(String),
+    Diverged { epoch: usize, loss: f64 },
+    /// A hyperparameter search where no trial trained successfully
+    TrialsFailed { trials: usize, error: String },
+}
+
+impl From<ConfigError> for TrainingError {
+    fn from(err: ConfigError) -> Self {
+        TrainingError::Config(err)
+    }
+}
+
+impl fmt::Display for TrainingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrainingError::Config(err) => write!(f, "{}", err),
+            TrainingError::Dataset(msg) => write!(f, "{}", msg),
+            TrainingError::Diverged { epoch, loss } => {
+                write!(f, "Training diverged at epoch {} (loss is {})", epoch, loss)
+            }
+            TrainingError::TrialsFailed { trials, error } => {
+                write!(f, "All {} trials failed, e.g. {}", trials, error)
+            }
+        }
+    }
+}
+
+impl Error for TrainingError {}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrainingResult<T = f64> {
+    /// The resolved configuration the model was trained with
+    pub config: TrainingConfig,
+    pub model: NeuralNet<T>,
+    pub normalizer: Normalizer<T>,
+    pub class_names: Vec<String>,
+    pub history: Vec<EpochMetrics>,
     pub final_accuracy: f64,
 }
 
-pub fn run_training_from_csv(path: &str) -> TrainingResult {
-    let samples = crate::data::load_dataset(path).expect("Failed to load dataset");
-    run_training_from_samples(&samples)
+#[derive(Serialize)]
+struct SavedModelRef<'a, T> {
+    format_version: u32,
+    #[serde(flatten)]
+    model: &'a T,
+}
+
+#[derive(Deserialize)]
+struct SavedModel<T> {
+    format_version: u32,
+    #[serde(flatten)]
+    model: T,
+}
+
+/// Writes `model` as JSON tagged with `MODEL_FORMAT_VERSION`.
+pub(crate) fn save_json<T: Serialize>(model: &T, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+    let writer = BufWriter::new(File::create(path)?);
+    let saved = SavedModelRef {
+        format_version: MODEL_FORMAT_VERSION,
+        model,
+    };
+    serde_json::to_writer_pretty(writer, &saved)?;
+    Ok(())
+}
+
+pub(crate) fn load_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, Box<dyn Error>> {
+    let reader = BufReader::new(File::open(path)?);
+    let saved: SavedModel<T> = serde_json::from_reader(reader)?;
+    if saved.format_version != MODEL_FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported model format version {} (expected {})",
+            saved.format_version, MODEL_FORMAT_VERSION
+        )
+        .into());
+    }
+    Ok(saved.model)
+}
+
+impl<T: Real> TrainingResult<T> {
+    /// Writes the model, its preprocessing and the config it was trained
+    /// with to a JSON file.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        save_json(self, path)
+    }
+
+    pub fn accuracies(&self) -> Vec<f64> {
+        self.history.iter().map(|m| m.train_accuracy).collect()
+    }
+
+    pub fn losses(&self) -> Vec<f64> {
+        self.history.iter().map(|m| m.loss).collect()
+    }
+
+    pub fn val_accuracies(&self) -> Vec<f64> {
+        self.history.iter().map(|m| m.val_accuracy).collect()
+    }
+
+    pub fn val_losses(&self) -> Vec<f64> {
+        self.history.iter().map(|m| m.val_loss).collect()
+    }
+
+    /// Classifies raw (unnormalized) measurements and returns the predicted
+    /// class and its probability, or "unknown" when the network is unsure.
+    pub fn predict(&self, weight: f64, size: f64, width: f64, height: f64) -> (&str, f64) {
+        let mut input = measurement_row(weight, size, width, height).mapv(T::cast_from);
+        self.normalizer.normalize(&mut input);
+
+        let (_, _, output) = self.model.forward(&input);
+        let (class, confidence) = output
+            .index_axis(Axis(0), 0)
+            .iter()
+            .map(|&p| p.into_f64())
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+            .unwrap_or((0, 0.0));
+
+        if confidence < UNKNOWN_CONFIDENCE_THRESHOLD || class >= self.class_names.len() {
+            ("unknown", confidence)
+        } else {
+            (&self.class_names[class], confidence)
+        }
+    }
+
+    /// The same model in another precision. Going from `f32` to `f64` is
+    /// exact; the other way rounds every parameter to `f32`. `config` still
+    /// records the precision the model was trained in.
+    pub fn cast<U: Real>(&self) -> TrainingResult<U> {
+        TrainingResult {
+            config: self.config.clone(),
+            model: self.model.cast(),
+            normalizer: self.normalizer.cast(),
+            class_names: self.class_names.clone(),
+            history: self.history.clone(),
+            final_accuracy: self.final_accuracy,
+        }
+    }
+}
+
+impl TrainingResult {
+    /// Loads a model saved in either precision; `f32` parameters are read
+    /// exactly, so `cast::<f32>()` recovers an `f32` model.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        load_json(path)
+    }
+
+    /// Like `predict`, with the contributions of each measurement to the
+    /// predicted class's probability. The baseline is the input that the
+    /// normalizer maps to zero, the training mean for the standard scaler.
+    pub fn explain(&self, weight: f64, size: f64, width: f64, height: f64, method: ExplainMethod) -> (&str, Explanation) {
+        let mut input = measurement_row(weight, size, width, height);
+        self.normalizer.normalize(&mut input);
+
+        let baseline = Array1::zeros(input.ncols());
+        let explanation = explain::explain(&self.model, input.row(0), baseline.view(), method);
+        let label = if explanation.probability < UNKNOWN_CONFIDENCE_THRESHOLD || explanation.class >= self.class_names.len() {
+            "unknown"
+        } else {
+            &self.class_names[explanation.class]
+        };
+        (label, explanation)
+    }
+
+    /// The network and normalizer as fixed-size arrays for the `no_std`
+    /// `fruit_core` crate, with batch normalization folded into the hidden
+    /// layer. Fails unless the network has `H` hidden units and `C` classes.
+    pub fn to_core<const H: usize, const C: usize>(&self) -> Result<Network<H, C>, Box<dyn Error>> {
+        let (hidden, output) = self.model.inference_layers();
+        let (Some(hidden_layer), Some(output_layer)) = (core_dense(&hidden), core_dense(&output)) else {
+            return Err(format!(
+                "Expected {} hidden units and {} classes, the network has {} and {}",
+                H,
+                C,
+                hidden.bias.len(),
+                output.bias.len()
+            )
+            .into());
+        };
+        let mut scaler = Scaler {
+            mean: [0.0; FEATURES],
+            std: [0.0; FEATURES],
+        };
+        scaler.mean.copy_from_slice(self.normalizer.mean.as_slice().ok_or("Normalizer mean is not contiguous")?);
+        scaler.std.copy_from_slice(self.normalizer.std.as_slice().ok_or("Normalizer std is not contiguous")?);
+        Ok(Network {
+            scaler,
+            hidden: hidden_layer,
+            output: output_layer,
+        })
+    }
+}
+
+// Copies a layer into fixed-size arrays if its weights are `I` x `O`
+fn core_dense<const I: usize, const O: usize>(layer: &DenseLayer) -> Option<Dense<I, O>> {
+    if layer.weights.dim() != (I, O) {
+        return None;
+    }
+    let mut dense = Dense {
+        weights: [[0.0; O]; I],
+        bias: [0.0; O],
+    };
+    for ((i, j), &weight) in layer.weights.indexed_iter() {
+        dense.weights[i][j] = weight;
+    }
+    for (bias, &value) in dense.bias.iter_mut().zip(&layer.bias) {
+        *bias = value;
+    }
+    Some(dense)
+}
+
+/// Trains on the dataset named in `config.data`.
+pub fn run_training(config: &TrainingConfig) -> Result<TrainingResult, TrainingError> {
+    run_training_from_csv(&config.data.path, config)
+}
+
+pub fn run_training_from_csv(path: &str, config: &TrainingConfig) -> Result<TrainingResult, TrainingError> {
+    run_training_from_csv_with_callback(path, config, |_| true)
+}
+
+pub fn run_training_from_samples(samples: &[FruitSample], config: &TrainingConfig) -> Result<TrainingResult, TrainingError> {
+    run_training_with_callback(samples, config, |_| true)
+}
+
+pub fn run_training_from_csv_with_callback<F>(
+    path: &str,
+    config: &TrainingConfig,
+    on_epoch: F,
+) -> Result<TrainingResult, TrainingError>
+where
+    F: FnMut(&EpochMetrics) -> bool,
+{
+    config.validate()?;
+    let data = DataConfig {
+        path: path.to_owned(),
+        ..config.data.clone()
+    };
+    run_training_with_callback(&load_samples(&data)?, config, on_epoch)
+}
+
+/// Loads the dataset `data` names, reporting failures as dataset errors.
+pub(crate) fn load_samples(data: &DataConfig) -> Result<Vec<FruitSample>, TrainingError> {
+    crate::data::load_dataset_with_schema(&data.path, &data.schema)
+        .map_err(|e| TrainingError::Dataset(format!("Failed to load dataset '{}': {}", data.path, e)))
+}
+
+struct EarlyStopping<T> {
+    best: f64,
+    best_model: Option<NeuralNet<T>>,
+    epochs_without_improvement: usize,
+}
+
+impl<T: Real> EarlyStopping<T> {
+    /// Records the epoch and returns whether training should stop.
+    fn update(&mut self, config: &TrainingConfig, metrics: &EpochMetrics, model: &NeuralNet<T>) -> bool {
+        let early = &config.early_stopping;
+        if !early.enabled {
+            return false;
+        }
+
+        // Compare as "higher is better"
+        let value = match early.monitor {
+            Monitor::ValLoss => -metrics.val_loss,
+            Monitor::ValAccuracy => metrics.val_accuracy,
+        };
+        if value > self.best + early.min_delta {
+            self.best = value;
+            self.epochs_without_improvement = 0;
+            if early.restore_best {
+                self.best_model = Some(model.clone());
+            }
+            false
+        } else {
+            self.epochs_without_improvement += 1;
+            self.epochs_without_improvement >= early.patience
+        }
+    }
 }
 
-pub fn run_training_from_samples(samples: &[FruitSample]) -> TrainingResult {
-    let mut features = Array2::<f64>::zeros((samples.len(), 2));
-    let mut labels = Vec::new();
+/// RNG for shuffling and initialization, reproducible when `seed` is set.
+pub(crate) fn seeded_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
 
-    for (i, sample) in samples.iter().enumerate() {
-        features[[i, 0]] = sample.weight;
-        features[[i, 1]] = sample.size;
-        labels.push(sample.label.clone());
+/// Shuffles (unless disabled) and splits the samples as `config.split`
+/// describes, returning the training and validation parts.
+pub fn split_samples(samples: &[FruitSample], config: &TrainingConfig, rng: &mut StdRng) -> (Vec<FruitSample>, Vec<FruitSample>) {
+    let mut samples = samples.to_vec();
+    if config.split.shuffle {
+        samples.shuffle(rng);
     }
 
-    normalize_features(&mut features);
-    let encoded_labels = encode_labels(&labels);
+    let split_index = ((samples.len() as f64 * config.split.train) as usize).min(samples.len());
+    let val = samples.split_off(split_index);
+    (samples, val)
+}
 
-    let (train_features, train_labels, test_features, test_labels) =
-        split_dataset(features, encoded_labels, 0.8);
+// Visits the training rows in a fresh order every epoch, so the batches
+// differ from one epoch to the next
+fn shuffle_rows<T: Clone>(x: &Array2<T>, y: &Array2<T>, rng: &mut StdRng) -> (Array2<T>, Array2<T>) {
+    let mut order: Vec<usize> = (0..x.nrows()).collect();
+    order.shuffle(rng);
+    (x.select(Axis(0), &order), y.select(Axis(0), &order))
+}
 
-    let input_size = 2;
-    let hidden_size = 8;
-    let output_size = 3;
-    let learning_rate = 0.01;
-    let epochs = 5000;
-    let batch_size = 16;
+/// Normalized features and one-hot labels of a training/validation split.
+/// The normalizer and the class list are fitted on the training part only.
+#[derive(Debug, Clone)]
+pub struct PreparedSplit {
+    pub normalizer: Normalizer,
+    pub class_names: Vec<String>,
+    pub train_features: Array2<f64>,
+    pub train_labels: Array2<f64>,
+    pub val_features: Array2<f64>,
+    pub val_labels: Array2<f64>,
+}
 
-    let mut nn = NeuralNet::new(input_size, hidden_size, output_size, learning_rate);
-    let (accuracies, losses) = nn.train(&train_features, &train_labels, epochs, batch_size);
+impl PreparedSplit {
+    pub fn new(train: &[FruitSample], val: &[FruitSample], scaler: ScalerKind) -> Result<Self, TrainingError> {
+        if train.is_empty() || val.is_empty() {
+            return Err(TrainingError::Dataset(format!(
+                "{} samples are too few to split into training and validation sets",
+                train.len() + val.len()
+            )));
+        }
 
-    let final_accuracy = nn.evaluate(&test_features, &test_labels);
+        let mut normalizer = Normalizer::new();
+        normalizer.fit_with(scaler, train);
 
-    TrainingResult {
-        accuracies,
-        losses,
-        final_accuracy,
+        let train_labels: Vec<String> = train.iter().map(|s| s.label.clone()).collect();
+        let val_labels: Vec<String> = val.iter().map(|s| s.label.clone()).collect();
+        let class_names = class_names(&train_labels);
+
+        Ok(PreparedSplit {
+            train_features: normalizer.transform(train),
+            val_features: normalizer.transform(val),
+            train_labels: encode_labels(&train_labels, &class_names),
+            val_labels: encode_labels(&val_labels, &class_names),
+            normalizer,
+            class_names,
+        })
+    }
+}
+
+/// Trains a network and calls `on_epoch` after every epoch; returning
+/// `false` from it stops training early with the model trained so far.
+pub fn run_training_with_callback<F>(
+    samples: &[FruitSample],
+    config: &TrainingConfig,
+    on_epoch: F,
+) -> Result<TrainingResult, TrainingError>
+where
+    F: FnMut(&EpochMetrics) -> bool,
+{
+    config.validate()?;
+    let mut rng = seeded_rng(config.seed);
+    let (train, val) = split_samples(samples, config, &mut rng);
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    train_prepared(data, config, &mut rng, on_epoch)
+}
+
+/// Like `run_training_with_callback`, but with the training and validation
+/// sets chosen by the caller, e.g. the folds of a cross-validation. The
+/// `split` section of the config is ignored.
+pub fn run_training_on_split<F>(
+    train: &[FruitSample],
+    val: &[FruitSample],
+    config: &TrainingConfig,
+    on_epoch: F,
+) -> Result<TrainingResult, TrainingError>
+where
+    F: FnMut(&EpochMetrics) -> bool,
+{
+    config.validate()?;
+    let mut rng = seeded_rng(config.seed);
+    let data = PreparedSplit::new(train, val, config.scaler)?;
+    train_prepared(data, config, &mut rng, on_epoch)
+}
+
+/// Like `run_training_with_callback`, in precision `T` whatever
+/// `config.model.precision` says. The result's config records `T`.
+pub fn run_training_as<T, F>(
+    samples: &[FruitSample],
+    config: &TrainingConfig,
+    on_epoch: F,
+) -> Result<TrainingResult<T>, TrainingError>
+where
+    T: Real,
+    F: FnMut(&EpochMetrics) -> bool,
+{
+    config.validate()?;
+    let mut rng = seeded_rng(config.seed);
+    let (train, val) = split_samples(samples, config, &mut rng);
+    let data = PreparedSplit::new(&train, &val, config.scaler)?;
+    train_prepared_as(data, config, &mut rng, on_epoch)
+}
+
+/// Trains a network on an already prepared split in the precision
+/// `config.model.precision` selects. An `f32` network is returned converted
+/// to `f64`, which is exact.
+pub fn train_prepared<F>(
+    data: PreparedSplit,
+    config: &TrainingConfig,
+    rng: &mut StdRng,
+    on_epoch: F,
+) -> Result<TrainingResult, TrainingError>
+where
+    F: FnMut(&EpochMetrics) -> bool,
+{
+    match config.model.precision {
+        Precision::F64 => train_prepared_as(data, config, rng, on_epoch),
+        Precision::F32 => train_prepared_as::<f32, _>(data, config, rng, on_epoch).map(|result| result.cast()),
     }
-}
\ No newline at end of file
+}
+
+/// Trains a network in precision `T` on an already prepared split, ignoring
+/// `config.model.precision`. Balancing and augmentation still run on the
+/// `f64` features, and the results are converted before every epoch.
+pub fn train_prepared_as<T, F>(
+    data: PreparedSplit,
+    config: &TrainingConfig,
+    rng: &mut StdRng,
+    mut on_epoch: F,
+) -> Result<TrainingResult<T>, TrainingError>
+where
+    T: Real,
+    F: FnMut(&EpochMetrics) -> bool,
+{
+    let PreparedSplit {
+        normalizer,
+        class_names,
+        train_features,
+        train_labels: train_encoded,
+        val_features,
+        val_labels: val_encoded,
+    } = data;
+
+    let base_lr = config.optimizer.learning_rate;
+    let mut nn = NeuralNet::<T>::with_rng(4, config.model.hidden_size, class_names.len(), base_lr, rng);
+    nn.set_l2_lambda(config.regularization.l2);
+    nn.set_momentum(config.optimizer.momentum);
+    // Class weights come from the real class frequencies, which resampling
+    // would otherwise flatten out
+    nn.set_loss(Loss::from_config(&config.loss, &class_names, &train_encoded)?);
+    // Only the training split is resampled, so validation stays honest
+    let (train_features, train_encoded) = balancing::rebalance(&train_features, &train_encoded, config.balancing, rng);
+    nn.set_batch_norm(config.model.batch_norm);
+    if config.model.dropout > 0.0 {
+        nn.set_dropout(config.model.dropout, rng.next_u64());
+    }
+    let (train_x, train_y) = (train_features.mapv(T::cast_from), train_encoded.mapv(T::cast_from));
+    let (val_features, val_encoded) = (val_features.mapv(T::cast_from), val_encoded.mapv(T::cast_from));
+    let mut augmenter = config
+        .augmentation
+        .is_enabled()
+        .then(|| Augmenter::new(&config.augmentation, &normalizer, rng.next_u64()));
+
+    let mut history = Vec::new();
+    let mut early_stopping = EarlyStopping {
+        best: f64::NEG_INFINITY,
+        best_model: None,
+        epochs_without_improvement: 0,
+    };
+
+    for epoch in 1..=config.schedule.epochs {
+        nn.set_learning_rate(config.schedule.learning_rate.learning_rate(base_lr, epoch - 1));
+        let batch_size = config.optimizer.batch_size;
+        // With augmentation the training metrics describe the perturbed epoch
+        let (epoch_x, epoch_y) = match &mut augmenter {
+            Some(augmenter) => {
+                let (features, labels) = augmenter.augment(&train_features, &train_encoded);
+                shuffle_rows(&features.mapv(T::cast_from), &labels.mapv(T::cast_from), rng)
+            }
+            None => shuffle_rows(&train_x, &train_y, rng),
+        };
+        let (loss, train_accuracy) = nn.train_one_epoch(&epoch_x, &epoch_y, batch_size);
+        if !loss.is_finite() {
+            return Err(TrainingError::Diverged { epoch, loss });
+        }
+
+        let (_, _, val_output) = nn.forward(&val_features);
+        let metrics = EpochMetrics {
+            epoch,
+            loss,
+            train_accuracy,
+            val_loss: nn.loss().value(&val_output, &val_encoded),
+            val_accuracy: nn.evaluate(&val_features, &val_encoded),
+        };
+        history.push(metrics);
+
+        let stop = early_stopping.update(config, &metrics, &nn);
+        if !on_epoch(&metrics) || stop {
+            break;
+        }
+    }
+
+    if let Some(best) = early_stopping.best_model {
+        // Keep the full per-epoch history rather than the snapshot's
+        let (accuracies, losses) = (nn.accuracies, nn.losses);
+        nn = best;
+        nn.accuracies = accuracies;
+        nn.losses = losses;
+    }
+
+    let mut config = config.clone();
+    config.model.precision = T::precision();
+    Ok(TrainingResult {
+        final_accuracy: nn.evaluate(&val_features, &val_encoded),
+        config,
+        model: nn,
+        normalizer: normalizer.cast(),
+        class_names,
+        history,
+    })
+}
@@ -0,0 +1,351 @@
+// tree.rs
+use crate::classifier::{argmax_rows, Classifier};
+use ndarray::{Array1, Array2, ArrayView1, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TreeParams {
+    pub max_depth: usize,
+    /// Nodes with fewer samples become leaves
+    pub min_samples_split: usize,
+    /// Splits leaving fewer samples on either side are not considered
+    pub min_samples_leaf: usize,
+    /// Features drawn at random for each split, as in a random forest; all
+    /// features when absent
+    pub max_features: Option<usize>,
+}
+
+impl Default for TreeParams {
+    fn default() -> Self {
+        TreeParams {
+            max_depth: 10,
+            min_samples_split: 2,
+            min_samples_leaf: 1,
+            max_features: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Node<T> {
+    Leaf(T),
+    /// Samples with `feature <= threshold` go left
+    Split {
+        feature: usize,
+        threshold: f64,
+        left: usize,
+        right: usize,
+    },
+}
+
+// Nodes stored in a flat vector with the root first
+fn find_leaf<'a, T>(nodes: &'a [Node<T>], sample: ArrayView1<f64>) -> &'a T {
+    let mut id = 0;
+    loop {
+        match &nodes[id] {
+            Node::Leaf(value) => return value,
+            Node::Split {
+                feature,
+                threshold,
+                left,
+                right,
+            } => id = if sample[*feature] <= *threshold { *left } else { *right },
+        }
+    }
+}
+
+fn depth_from<T>(nodes: &[Node<T>], id: usize) -> usize {
+    match nodes[id] {
+        Node::Leaf(_) => 0,
+        Node::Split { left, right, .. } => 1 + depth_from(nodes, left).max(depth_from(nodes, right)),
+    }
+}
+
+/// Scales accumulated impurity decreases to sum to 1.
+pub(crate) fn normalized_importances(importances: &[f64]) -> Vec<f64> {
+    let total: f64 = importances.iter().sum();
+    if total > 0.0 {
+        importances.iter().map(|v| v / total).collect()
+    } else {
+        vec![0.0; importances.len()]
+    }
+}
+
+/// Feature indices examined at one split.
+fn candidate_features<R: Rng>(n_features: usize, max_features: Option<usize>, rng: &mut R) -> Vec<usize> {
+    match max_features {
+        Some(m) if m < n_features => rand::seq::index::sample(rng, n_features, m.max(1)).into_vec(),
+        _ => (0..n_features).collect(),
+    }
+}
+
+/// Scans the sorted values of every candidate feature and returns the
+/// feature, threshold and score of the best split. `score` is called with
+/// the number of samples moved to the left side and must be maximized;
+/// `push` moves one sample from the right side to the left.
+fn best_split<S>(
+    features: &Array2<f64>,
+    rows: &[usize],
+    candidates: &[usize],
+    min_samples_leaf: usize,
+    mut state: impl FnMut() -> S,
+    mut push: impl FnMut(&mut S, usize),
+    mut score: impl FnMut(&S, usize) -> f64,
+) -> Option<(usize, f64, f64)> {
+    let n = rows.len();
+    let mut best: Option<(usize, f64, f64)> = None;
+    let mut sorted = rows.to_vec();
+
+    for &feature in candidates {
+        sorted.sort_by(|&a, &b| features[[a, feature]].total_cmp(&features[[b, feature]]));
+        let mut split_state = state();
+
+        for pos in 0..n - 1 {
+            push(&mut split_state, sorted[pos]);
+            let (value, next) = (features[[sorted[pos], feature]], features[[sorted[pos + 1], feature]]);
+            let n_left = pos + 1;
+            if value == next || n_left < min_samples_leaf || n - n_left < min_samples_leaf {
+                continue;
+            }
+
+            let gain = score(&split_state, n_left);
+            if best.is_none_or(|(_, _, best_gain)| gain > best_gain) {
+                best = Some((feature, (value + next) / 2.0, gain));
+            }
+        }
+    }
+    best
+}
+
+/// CART classification tree grown greedily on Gini impurity.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionTree {
+    /// Leaves hold the class distribution of the training samples that
+    /// reached them
+    nodes: Vec<Node<Vec<f64>>>,
+    n_classes: usize,
+    /// Total weighted Gini decrease of the splits on each feature
+    impurity_decrease: Vec<f64>,
+}
+
+impl DecisionTree {
+    pub fn fit(features: &Array2<f64>, labels: &Array2<f64>, params: &TreeParams) -> Self {
+        // Only used when `max_features` limits the candidates
+        Self::fit_with_rng(features, labels, params, &mut StdRng::seed_from_u64(0))
+    }
+
+    pub fn fit_with_rng<R: Rng>(features: &Array2<f64>, labels: &Array2<f64>, params: &TreeParams, rng: &mut R) -> Self {
+        let classes = argmax_rows(labels);
+        let mut tree = DecisionTree {
+            nodes: Vec::new(),
+            n_classes: labels.ncols(),
+            impurity_decrease: vec![0.0; features.ncols()],
+        };
+        tree.grow(features, &classes, (0..features.nrows()).collect(), 0, params, rng);
+        tree
+    }
+
+    /// Number of splits on the longest path from the root to a leaf.
+    pub fn depth(&self) -> usize {
+        if self.nodes.is_empty() {
+            0
+        } else {
+            depth_from(&self.nodes, 0)
+        }
+    }
+
+    pub fn n_leaves(&self) -> usize {
+        self.nodes.iter().filter(|n| matches!(n, Node::Leaf(_))).count()
+    }
+
+    /// Share of the total impurity decrease contributed by each feature.
+    pub fn feature_importances(&self) -> Vec<f64> {
+        normalized_importances(&self.impurity_decrease)
+    }
+
+    fn grow<R: Rng>(
+        &mut self,
+        features: &Array2<f64>,
+        classes: &[usize],
+        rows: Vec<usize>,
+        depth: usize,
+        params: &TreeParams,
+        rng: &mut R,
+    ) -> usize {
+        let mut counts = vec![0usize; self.n_classes];
+        for &i in &rows {
+            counts[classes[i]] += 1;
+        }
+        let n = rows.len();
+
+        let id = self.nodes.len();
+        self.nodes
+            .push(Node::Leaf(counts.iter().map(|&c| c as f64 / n.max(1) as f64).collect()));
+
+        let pure = counts.iter().filter(|&&c| c > 0).count() <= 1;
+        if pure || depth >= params.max_depth || n < params.min_samples_split {
+            return id;
+        }
+
+        // Maximizing the negated weighted child impurity
+        let candidates = candidate_features(features.ncols(), params.max_features, rng);
+        let split = best_split(
+            features,
+            &rows,
+            &candidates,
+            params.min_samples_leaf,
+            || vec![0usize; counts.len()],
+            |left, i| left[classes[i]] += 1,
+            |left, n_left| -children_gini(&counts, left, n_left),
+        );
+        let parent = n as f64 * gini(&counts, n);
+        let Some((feature, threshold, gain)) = split.filter(|&(_, _, gain)| parent + gain > 1e-12) else {
+            return id;
+        };
+        self.impurity_decrease[feature] += parent + gain;
+
+        let (left_rows, right_rows): (Vec<usize>, Vec<usize>) =
+            rows.into_iter().partition(|&i| features[[i, feature]] <= threshold);
+        let left = self.grow(features, classes, left_rows, depth + 1, params, rng);
+        let right = self.grow(features, classes, right_rows, depth + 1, params, rng);
+        self.nodes[id] = Node::Split {
+            feature,
+            threshold,
+            left,
+            right,
+        };
+        id
+    }
+}
+
+fn gini(counts: &[usize], total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    let total = total as f64;
+    1.0 - counts.iter().map(|&c| (c as f64 / total).powi(2)).sum::<f64>()
+}
+
+/// Sample-weighted Gini impurity of the two sides of a split, where the
+/// right side holds whatever of `counts` is not in `left`.
+fn children_gini(counts: &[usize], left: &[usize], n_left: usize) -> f64 {
+    let n_right = counts.iter().sum::<usize>() - n_left;
+    let (mut left_sum, mut right_sum) = (0.0, 0.0);
+    for (&c, &l) in counts.iter().zip(left) {
+        left_sum += (l * l) as f64;
+        right_sum += ((c - l) * (c - l)) as f64;
+    }
+    let side = |sum: f64, n: usize| if n == 0 { 0.0 } else { n as f64 - sum / n as f64 };
+    side(left_sum, n_left) + side(right_sum, n_right)
+}
+
+impl Classifier for DecisionTree {
+    fn name(&self) -> &str {
+        "decision_tree"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        let mut probabilities = Array2::zeros((features.nrows(), self.n_classes));
+        for (sample, mut out) in features.axis_iter(Axis(0)).zip(probabilities.axis_iter_mut(Axis(0))) {
+            for (o, &p) in out.iter_mut().zip(find_leaf(&self.nodes, sample)) {
+                *o = p;
+            }
+        }
+        probabilities
+    }
+}
+
+/// Regression tree fitted to gradients, as used by gradient boosting.
+/// Splits minimize the squared error of the gradients and each leaf holds
+/// the Newton step `sum(gradient) / sum(hessian)` of its samples.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegressionTree {
+    nodes: Vec<Node<f64>>,
+    /// Total squared-error decrease of the splits on each feature
+    error_decrease: Vec<f64>,
+}
+
+impl RegressionTree {
+    /// Fits the tree on `rows` of `features`; `gradients` and `hessians`
+    /// are indexed like the rows of `features`.
+    pub fn fit(features: &Array2<f64>, rows: &[usize], gradients: &[f64], hessians: &[f64], params: &TreeParams) -> Self {
+        let mut tree = RegressionTree {
+            nodes: Vec::new(),
+            error_decrease: vec![0.0; features.ncols()],
+        };
+        tree.grow(features, rows.to_vec(), gradients, hessians, 0, params);
+        tree
+    }
+
+    pub fn predict(&self, features: &Array2<f64>) -> Array1<f64> {
+        features
+            .axis_iter(Axis(0))
+            .map(|sample| *find_leaf(&self.nodes, sample))
+            .collect()
+    }
+
+    pub fn depth(&self) -> usize {
+        if self.nodes.is_empty() {
+            0
+        } else {
+            depth_from(&self.nodes, 0)
+        }
+    }
+
+    /// Unnormalized squared-error decrease per feature.
+    pub(crate) fn error_decrease(&self) -> &[f64] {
+        &self.error_decrease
+    }
+
+    fn grow(
+        &mut self,
+        features: &Array2<f64>,
+        rows: Vec<usize>,
+        gradients: &[f64],
+        hessians: &[f64],
+        depth: usize,
+        params: &TreeParams,
+    ) -> usize {
+        let n = rows.len();
+        let sum: f64 = rows.iter().map(|&i| gradients[i]).sum();
+        let hessian: f64 = rows.iter().map(|&i| hessians[i]).sum();
+
+        let id = self.nodes.len();
+        self.nodes.push(Node::Leaf(sum / hessian.max(1e-12)));
+        if depth >= params.max_depth || n < params.min_samples_split {
+            return id;
+        }
+
+        // Squared error is sum(g^2) - S^2/n on each side, so minimizing it
+        // maximizes S_left^2/n_left + S_right^2/n_right
+        let candidates: Vec<usize> = (0..features.ncols()).collect();
+        let split = best_split(
+            features,
+            &rows,
+            &candidates,
+            params.min_samples_leaf,
+            || 0.0,
+            |left, i| *left += gradients[i],
+            |&left, n_left| left * left / n_left as f64 + (sum - left).powi(2) / (n - n_left) as f64,
+        );
+        let parent = sum * sum / n.max(1) as f64;
+        let Some((feature, threshold, gain)) = split.filter(|&(_, _, gain)| gain - parent > 1e-12) else {
+            return id;
+        };
+        self.error_decrease[feature] += gain - parent;
+
+        let (left_rows, right_rows): (Vec<usize>, Vec<usize>) =
+            rows.into_iter().partition(|&i| features[[i, feature]] <= threshold);
+        let left = self.grow(features, left_rows, gradients, hessians, depth + 1, params);
+        let right = self.grow(features, right_rows, gradients, hessians, depth + 1, params);
+        self.nodes[id] = Node::Split {
+            feature,
+            threshold,
+            left,
+            right,
+        };
+        id
+    }
+}
@@ -0,0 +1,567 @@
+// tuning.rs
+use crate::config::{self, invalid, ConfigError, TrainingConfig};
+use crate::data::FruitSample;
+use crate::training::{self, TrainingError};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::path::Path;
+use std::time::Instant;
+
+/// Values a hyperparameter may take during the search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ParamSpace {
+    /// Explicit candidates; the only form grid search accepts
+    Values(Vec<f64>),
+    /// Continuous range for random search, sampled uniformly or, with
+    /// `log`, uniformly in log space
+    Range {
+        min: f64,
+        max: f64,
+        #[serde(default)]
+        log: bool,
+    },
+}
+
+impl ParamSpace {
+    fn sample<R: Rng>(&self, rng: &mut R) -> f64 {
+        match *self {
+            ParamSpace::Values(ref values) => *values.choose(rng).expect("validated to be non-empty"),
+            ParamSpace::Range { min, max, log: false } => rng.gen_range(min..=max),
+            ParamSpace::Range { min, max, log: true } => rng.gen_range(min.ln()..=max.ln()).exp(),
+        }
+    }
+}
+
+/// Hyperparameters that can be searched. Anything left out keeps the value
+/// from the base config.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchSpace {
+    pub hidden_size: Option<ParamSpace>,
+    pub learning_rate: Option<ParamSpace>,
+    pub batch_size: Option<ParamSpace>,
+    pub momentum: Option<ParamSpace>,
+    pub l2: Option<ParamSpace>,
+}
+
+impl SearchSpace {
+    fn params(&self) -> Vec<(Param, &ParamSpace)> {
+        [
+            (Param::HiddenSize, &self.hidden_size),
+            (Param::LearningRate, &self.learning_rate),
+            (Param::BatchSize, &self.batch_size),
+            (Param::Momentum, &self.momentum),
+            (Param::L2, &self.l2),
+        ]
+        .into_iter()
+        .filter_map(|(param, space)| space.as_ref().map(|s| (param, s)))
+        .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Param {
+    HiddenSize,
+    LearningRate,
+    BatchSize,
+    Momentum,
+    L2,
+}
+
+impl Param {
+    fn field(self) -> &'static str {
+        match self {
+            Param::HiddenSize => "space.hidden_size",
+            Param::LearningRate => "space.learning_rate",
+            Param::BatchSize => "space.batch_size",
+            Param::Momentum => "space.momentum",
+            Param::L2 => "space.l2",
+        }
+    }
+
+    fn set(self, params: &mut Hyperparameters, value: f64) {
+        match self {
+            Param::HiddenSize => params.hidden_size = value.round().max(1.0) as usize,
+            Param::LearningRate => params.learning_rate = value,
+            Param::BatchSize => params.batch_size = value.round().max(1.0) as usize,
+            Param::Momentum => params.momentum = value,
+            Param::L2 => params.l2 = value,
+        }
+    }
+}
+
+/// One point in the search space.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Hyperparameters {
+    pub hidden_size: usize,
+    pub learning_rate: f64,
+    pub batch_size: usize,
+    pub momentum: f64,
+    pub l2: f64,
+}
+
+impl Hyperparameters {
+    pub fn from_config(config: &TrainingConfig) -> Self {
+        Hyperparameters {
+            hidden_size: config.model.hidden_size,
+            learning_rate: config.optimizer.learning_rate,
+            batch_size: config.optimizer.batch_size,
+            momentum: config.optimizer.momentum,
+            l2: config.regularization.l2,
+        }
+    }
+
+    pub fn apply(&self, config: &mut TrainingConfig) {
+        config.model.hidden_size = self.hidden_size;
+        config.optimizer.learning_rate = self.learning_rate;
+        config.optimizer.batch_size = self.batch_size;
+        config.optimizer.momentum = self.momentum;
+        config.regularization.l2 = self.l2;
+    }
+}
+
+/// How candidates are chosen. Epoch budgets are capped by
+/// `base.schedule.epochs`.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum SearchStrategy {
+    /// Every combination of the listed values
+    #[default]
+    Grid,
+    /// `trials` candidates sampled from the space
+    Random { trials: usize },
+    /// Starts `trials` random candidates at `min_epochs` and keeps the best
+    /// `1/eta` of them, with `eta` times the epochs, until one is left
+    SuccessiveHalving {
+        trials: usize,
+        min_epochs: usize,
+        eta: usize,
+    },
+    /// Successive halving repeated over every trade-off between the number
+    /// of candidates and their starting budget
+    Hyperband { min_epochs: usize, eta: usize },
+}
+
+/// How a candidate is scored.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Evaluation {
+    /// Accuracy on the validation set described by `base.split`
+    #[default]
+    Validation,
+    /// Mean validation accuracy over `folds` folds
+    CrossValidation { folds: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TuningConfig {
+    /// Seeds the sampling of random candidates; random when absent
+    pub seed: Option<u64>,
+    /// Trials run in parallel on this many threads, 0 uses every core
+    pub threads: usize,
+    pub strategy: SearchStrategy,
+    pub evaluation: Evaluation,
+    pub space: SearchSpace,
+    /// Every trial starts from this config; searched values override it
+    pub base: TrainingConfig,
+}
+
+impl TuningConfig {
+    /// Loads and validates a `.toml` or `.json` tuning file.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, ConfigError> {
+        let tuning: TuningConfig = config::parse_file(path.as_ref())?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    pub fn from_toml_str(text: &str) -> Result<Self, ConfigError> {
+        let tuning: TuningConfig = toml::from_str(text).map_err(|e| ConfigError::Parse(e.to_string()))?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.base.validate()?;
+
+        for (param, space) in self.space.params() {
+            match space {
+                ParamSpace::Values(values) => {
+                    if values.is_empty() {
+                        return Err(invalid(param.field(), "must list at least one value"));
+                    }
+                    if let Some(v) = values.iter().find(|v| !v.is_finite()) {
+                        return Err(invalid(param.field(), format!("values must be finite, got {}", v)));
+                    }
+                }
+                ParamSpace::Range { min, max, log } => {
+                    if !(min.is_finite() && max.is_finite() && min <= max) {
+                        return Err(invalid(param.field(), format!("invalid range {}..{}", min, max)));
+                    }
+                    if *log && *min <= 0.0 {
+                        return Err(invalid(param.field(), "log ranges must be positive"));
+                    }
+                    if self.strategy == SearchStrategy::Grid {
+                        return Err(invalid(param.field(), "grid search needs explicit values"));
+                    }
+                }
+            }
+        }
+
+        let max_epochs = self.base.schedule.epochs;
+        match self.strategy {
+            SearchStrategy::Grid => {}
+            SearchStrategy::Random { trials } => {
+                if trials == 0 {
+                    return Err(invalid("strategy.trials", "must be at least 1"));
+                }
+            }
+            SearchStrategy::SuccessiveHalving { trials, min_epochs, eta } => {
+                if trials == 0 {
+                    return Err(invalid("strategy.trials", "must be at least 1"));
+                }
+                validate_budget(min_epochs, eta, max_epochs)?;
+            }
+            SearchStrategy::Hyperband { min_epochs, eta } => validate_budget(min_epochs, eta, max_epochs)?,
+        }
+
+        if let Evaluation::CrossValidation { folds } = self.evaluation
+            && folds < 2
+        {
+            return Err(invalid("evaluation.folds", "must be at least 2"));
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_budget(min_epochs: usize, eta: usize, max_epochs: usize) -> Result<(), ConfigError> {
+    if min_epochs == 0 || min_epochs > max_epochs {
+        return Err(invalid(
+            "strategy.min_epochs",
+            format!("must be between 1 and base.schedule.epochs ({})", max_epochs),
+        ));
+    }
+    if eta < 2 {
+        return Err(invalid("strategy.eta", "must be at least 2"));
+    }
+    Ok(())
+}
+
+/// One evaluated candidate. Successive halving and Hyperband evaluate the
+/// same candidate at several budgets, each of which is a separate trial.
+#[derive(Debug, Clone, Serialize)]
+pub struct Trial {
+    /// Identifies the candidate across budgets
+    pub candidate: usize,
+    pub params: Hyperparameters,
+    pub epochs: usize,
+    /// Validation accuracy, averaged over folds when cross-validating
+    pub score: f64,
+    /// Standard deviation of the accuracy across folds, 0 without folds
+    pub score_std: f64,
+    pub seconds: f64,
+    /// Why training failed, e.g. divergence; failed trials rank last
+    pub error: Option<String>,
+}
+
+impl Trial {
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+#[derive(Serialize)]
+struct LeaderboardRow<'a> {
+    rank: usize,
+    candidate: usize,
+    epochs: usize,
+    hidden_size: usize,
+    learning_rate: f64,
+    batch_size: usize,
+    momentum: f64,
+    l2: f64,
+    score: f64,
+    score_std: f64,
+    seconds: f64,
+    error: &'a str,
+}
+
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    /// Every trial, best first: successful trials before failed ones, then
+    /// larger budgets before smaller, then higher scores
+    pub trials: Vec<Trial>,
+    base: TrainingConfig,
+}
+
+impl SearchResult {
+    pub fn best(&self) -> Option<&Trial> {
+        self.trials.first().filter(|t| t.succeeded())
+    }
+
+    /// The base config with the best trial's hyperparameters, trained for
+    /// the full `schedule.epochs`.
+    pub fn best_config(&self) -> Option<TrainingConfig> {
+        self.best().map(|trial| {
+            let mut config = self.base.clone();
+            trial.params.apply(&mut config);
+            config
+        })
+    }
+
+    pub fn write_leaderboard(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::Writer::from_path(path)?;
+        for (i, trial) in self.trials.iter().enumerate() {
+            writer.serialize(LeaderboardRow {
+                rank: i + 1,
+                candidate: trial.candidate,
+                epochs: trial.epochs,
+                hidden_size: trial.params.hidden_size,
+                learning_rate: trial.params.learning_rate,
+                batch_size: trial.params.batch_size,
+                momentum: trial.params.momentum,
+                l2: trial.params.l2,
+                score: trial.score,
+                score_std: trial.score_std,
+                seconds: trial.seconds,
+                error: trial.error.as_deref().unwrap_or(""),
+            })?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Runs the search on the dataset named in `tuning.base.data`.
+pub fn run_search(tuning: &TuningConfig) -> Result<SearchResult, TrainingError> {
+    run_search_with_callback(tuning, |_| {})
+}
+
+/// Like `run_search`, calling `on_trial` as each trial finishes. Trials run
+/// in parallel, so the calls come from worker threads in no fixed order.
+pub fn run_search_with_callback<F>(tuning: &TuningConfig, on_trial: F) -> Result<SearchResult, TrainingError>
+where
+    F: Fn(&Trial) + Sync,
+{
+    tuning.validate()?;
+    let samples = training::load_samples(&tuning.base.data)?;
+    search_samples(&samples, tuning, on_trial)
+}
+
+pub fn search_samples<F>(samples: &[FruitSample], tuning: &TuningConfig, on_trial: F) -> Result<SearchResult, TrainingError>
+where
+    F: Fn(&Trial) + Sync,
+{
+    tuning.validate()?;
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(tuning.threads)
+        .build()
+        .map_err(|e| invalid("threads", format!("failed to start worker threads: {}", e)))?;
+
+    let search = Search {
+        samples,
+        tuning,
+        on_trial: &on_trial,
+    };
+    let mut trials = pool.install(|| search.run());
+
+    trials.sort_by(|a, b| {
+        b.succeeded()
+            .cmp(&a.succeeded())
+            .then(b.epochs.cmp(&a.epochs))
+            .then(b.score.total_cmp(&a.score))
+    });
+
+    if let Some(trial) = trials.first().filter(|t| !t.succeeded()) {
+        return Err(TrainingError::TrialsFailed {
+            trials: trials.len(),
+            error: trial.error.clone().unwrap_or_default(),
+        });
+    }
+
+    Ok(SearchResult {
+        trials,
+        base: tuning.base.clone(),
+    })
+}
+
+struct Search<'a> {
+    samples: &'a [FruitSample],
+    tuning: &'a TuningConfig,
+    on_trial: &'a (dyn Fn(&Trial) + Sync),
+}
+
+impl Search<'_> {
+    fn run(&self) -> Vec<Trial> {
+        let mut rng = training::seeded_rng(self.tuning.seed);
+        let max_epochs = self.tuning.base.schedule.epochs;
+
+        match self.tuning.strategy {
+            SearchStrategy::Grid => self.evaluate_all(&self.grid(), max_epochs),
+            SearchStrategy::Random { trials } => self.evaluate_all(&self.sample(&mut rng, 0, trials), max_epochs),
+            SearchStrategy::SuccessiveHalving { trials, min_epochs, eta } => {
+                self.successive_halving(self.sample(&mut rng, 0, trials), min_epochs, eta)
+            }
+            SearchStrategy::Hyperband { min_epochs, eta } => {
+                // Number of halvings that fit between the smallest and the full
+                // budget; a budget that overflows certainly does not fit
+                let mut s_max = 0;
+                while eta
+                    .checked_pow(s_max + 1)
+                    .and_then(|factor| factor.checked_mul(min_epochs))
+                    .is_some_and(|budget| budget <= max_epochs)
+                {
+                    s_max += 1;
+                }
+
+                let mut trials = Vec::new();
+                let mut next_candidate = 0;
+                for s in (0..=s_max).rev() {
+                    let count = ((s_max + 1) as usize * eta.pow(s)).div_ceil(s as usize + 1);
+                    let candidates = self.sample(&mut rng, next_candidate, count);
+                    next_candidate += count;
+                    trials.extend(self.successive_halving(candidates, max_epochs / eta.pow(s), eta));
+                }
+                trials
+            }
+        }
+    }
+
+    fn grid(&self) -> Vec<(usize, Hyperparameters)> {
+        let mut grid = vec![Hyperparameters::from_config(&self.tuning.base)];
+        for (param, space) in self.tuning.space.params() {
+            let ParamSpace::Values(values) = space else {
+                unreachable!("grid search rejects ranges during validation");
+            };
+            grid = grid
+                .iter()
+                .flat_map(|point| {
+                    values.iter().map(move |&value| {
+                        let mut point = *point;
+                        param.set(&mut point, value);
+                        point
+                    })
+                })
+                .collect();
+        }
+        grid.into_iter().enumerate().collect()
+    }
+
+    fn sample(&self, rng: &mut StdRng, first_candidate: usize, count: usize) -> Vec<(usize, Hyperparameters)> {
+        let params = self.tuning.space.params();
+        (first_candidate..first_candidate + count)
+            .map(|candidate| {
+                let mut point = Hyperparameters::from_config(&self.tuning.base);
+                for (param, space) in &params {
+                    param.set(&mut point, space.sample(rng));
+                }
+                (candidate, point)
+            })
+            .collect()
+    }
+
+    fn successive_halving(&self, mut candidates: Vec<(usize, Hyperparameters)>, min_epochs: usize, eta: usize) -> Vec<Trial> {
+        let max_epochs = self.tuning.base.schedule.epochs;
+        let mut epochs = min_epochs;
+        let mut trials = Vec::new();
+
+        loop {
+            let mut round = self.evaluate_all(&candidates, epochs);
+            if epochs >= max_epochs {
+                trials.append(&mut round);
+                return trials;
+            }
+
+            round.sort_by(|a, b| b.succeeded().cmp(&a.succeeded()).then(b.score.total_cmp(&a.score)));
+            let keep = (round.len() / eta).max(1);
+            candidates = round
+                .iter()
+                .take(keep)
+                .filter(|t| t.succeeded())
+                .map(|t| (t.candidate, t.params))
+                .collect();
+            trials.append(&mut round);
+            if candidates.is_empty() {
+                return trials;
+            }
+
+            // A lone survivor goes straight to the full budget
+            epochs = if candidates.len() == 1 {
+                max_epochs
+            } else {
+                (epochs * eta).min(max_epochs)
+            };
+        }
+    }
+
+    fn evaluate_all(&self, candidates: &[(usize, Hyperparameters)], epochs: usize) -> Vec<Trial> {
+        candidates
+            .par_iter()
+            .map(|&(candidate, params)| {
+                let trial = self.evaluate(candidate, params, epochs);
+                (self.on_trial)(&trial);
+                trial
+            })
+            .collect()
+    }
+
+    fn evaluate(&self, candidate: usize, params: Hyperparameters, epochs: usize) -> Trial {
+        let mut config = self.tuning.base.clone();
+        params.apply(&mut config);
+        config.schedule.epochs = epochs;
+
+        let start = Instant::now();
+        let scores = match self.tuning.evaluation {
+            Evaluation::Validation => {
+                training::run_training_from_samples(self.samples, &config).map(|r| vec![r.final_accuracy])
+            }
+            Evaluation::CrossValidation { folds } => cross_validate(self.samples, &config, folds),
+        };
+
+        let (score, score_std, error) = match scores {
+            Ok(scores) => {
+                let n = scores.len() as f64;
+                let mean = scores.iter().sum::<f64>() / n;
+                let variance = scores.iter().map(|s| (s - mean).powi(2)).sum::<f64>() / n;
+                (mean, variance.sqrt(), None)
+            }
+            Err(e) => (f64::NAN, f64::NAN, Some(e.to_string())),
+        };
+
+        Trial {
+            candidate,
+            params,
+            epochs,
+            score,
+            score_std,
+            seconds: start.elapsed().as_secs_f64(),
+            error,
+        }
+    }
+}
+
+/// Validation accuracy of `config` on each of `folds` folds.
+pub fn cross_validate(samples: &[FruitSample], config: &TrainingConfig, folds: usize) -> Result<Vec<f64>, TrainingError> {
+    let mut rng = training::seeded_rng(config.seed);
+    let mut samples = samples.to_vec();
+    if config.split.shuffle {
+        samples.shuffle(&mut rng);
+    }
+
+    (0..folds)
+        .into_par_iter()
+        .map(|fold| {
+            let start = fold * samples.len() / folds;
+            let end = (fold + 1) * samples.len() / folds;
+            let val = &samples[start..end];
+            let train: Vec<FruitSample> = samples[..start].iter().chain(&samples[end..]).cloned().collect();
+            training::run_training_on_split(&train, val, config, |_| true).map(|r| r.final_accuracy)
+        })
+        .collect()
+}
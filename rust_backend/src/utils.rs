@@ -1,13 +1,34 @@
 use ndarray::{Array2, Array1};
+use serde::{Deserialize, Serialize};
 use crate::data::FruitSample;
+use crate::precision::Real;
 
-#[derive(Debug)]
-pub struct Normalizer {
-    pub mean: Array1<f64>,
-    pub std: Array1<f64>,
+/// How features are rescaled before they reach the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ScalerKind {
+    /// Zero mean, unit variance
+    #[default]
+    Standard,
+    /// Maps the training range onto [0, 1]
+    MinMax,
+    /// Leaves the raw measurements unchanged
+    None,
 }
 
-impl Normalizer {
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Normalizer<F = f64> {
+    pub mean: Array1<F>,
+    pub std: Array1<F>,
+}
+
+impl<F: Real> Default for Normalizer<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: Real> Normalizer<F> {
     pub fn new() -> Self {
         Normalizer {
             mean: Array1::zeros(4),
@@ -15,48 +36,99 @@ impl Normalizer {
         }
     }
 
+    /// Fits the scaler; every kind is expressed as `(x - mean) / std` so that
+    /// `transform` and `normalize` work the same for all of them.
+    pub fn fit_with(&mut self, kind: ScalerKind, samples: &[FruitSample]) {
+        match kind {
+            ScalerKind::Standard => self.fit(samples),
+            ScalerKind::MinMax => {
+                let mut min = Array1::from_elem(4, f64::INFINITY);
+                let mut max = Array1::from_elem(4, f64::NEG_INFINITY);
+                for sample in samples {
+                    for (j, value) in [sample.weight, sample.size, sample.width, sample.height].into_iter().enumerate() {
+                        min[j] = f64::min(min[j], value);
+                        max[j] = f64::max(max[j], value);
+                    }
+                }
+                self.std = (&max - &min).mapv(|x: f64| F::cast_from(if x > 1e-8 { x } else { 1.0 }));
+                self.mean = min.mapv(F::cast_from);
+            }
+            ScalerKind::None => *self = Normalizer::new(),
+        }
+    }
+
+    /// Statistics are accumulated in `f64` whatever the precision of the
+    /// normalizer.
     pub fn fit(&mut self, samples: &[FruitSample]) {
         // Calculate mean
-        let mut sum = Array1::zeros(4);
+        let mut sum: Array1<f64> = Array1::zeros(4);
         for sample in samples {
             sum[0] += sample.weight;
             sum[1] += sample.size;
             sum[2] += sample.width;
             sum[3] += sample.height;
         }
-        self.mean = &sum / samples.len() as f64;
+        let mean = &sum / samples.len() as f64;
 
         // Calculate std with numerical stability
-        let mut variance = Array1::zeros(4);
+        let mut variance: Array1<f64> = Array1::zeros(4);
         for sample in samples {
-            variance[0] += (sample.weight - self.mean[0]).powi(2);
-            variance[1] += (sample.size - self.mean[1]).powi(2);
-            variance[2] += (sample.width - self.mean[2]).powi(2);
-            variance[3] += (sample.height - self.mean[3]).powi(2);
+            variance[0] += (sample.weight - mean[0]).powi(2);
+            variance[1] += (sample.size - mean[1]).powi(2);
+            variance[2] += (sample.width - mean[2]).powi(2);
+            variance[3] += (sample.height - mean[3]).powi(2);
         }
         self.std = (variance / samples.len() as f64)
             .mapv(|x: f64| x.sqrt())
-            .mapv(|x: f64| if x > 1e-8 { x } else { 1.0 });
+            .mapv(|x: f64| F::cast_from(if x > 1e-8 { x } else { 1.0 }));
+        self.mean = mean.mapv(F::cast_from);
     }
 
-    pub fn transform(&self, samples: &[FruitSample]) -> Array2<f64> {
+    pub fn transform(&self, samples: &[FruitSample]) -> Array2<F> {
         let mut features = Array2::zeros((samples.len(), 4));
         for (i, sample) in samples.iter().enumerate() {
-            features[[i, 0]] = (sample.weight - self.mean[0]) / self.std[0];
-            features[[i, 1]] = (sample.size - self.mean[1]) / self.std[1];
-            features[[i, 2]] = (sample.width - self.mean[2]) / self.std[2];
-            features[[i, 3]] = (sample.height - self.mean[3]) / self.std[3];
+            for (j, value) in [sample.weight, sample.size, sample.width, sample.height].into_iter().enumerate() {
+                features[[i, j]] = (F::cast_from(value) - self.mean[j]) / self.std[j];
+            }
         }
         features
     }
 
-    pub fn normalize(&self, data: &mut Array2<f64>) {
+    pub fn normalize(&self, data: &mut Array2<F>) {
         for mut row in data.rows_mut() {
             for j in 0..4 {
                 row[j] = (row[j] - self.mean[j]) / self.std[j];
             }
         }
     }
+
+    /// The same normalizer in another precision.
+    pub fn cast<G: Real>(&self) -> Normalizer<G> {
+        Normalizer {
+            mean: self.mean.mapv(|v| G::cast_from(v.into_f64())),
+            std: self.std.mapv(|v| G::cast_from(v.into_f64())),
+        }
+    }
+}
+
+/// Feature columns in the order used by every feature matrix.
+pub const FEATURE_NAMES: [&str; 4] = ["weight", "size", "width", "height"];
+
+/// A single unnormalized sample as a one-row feature matrix.
+pub fn measurement_row(weight: f64, size: f64, width: f64, height: f64) -> Array2<f64> {
+    Array2::from_shape_vec((1, 4), vec![weight, size, width, height]).expect("shape matches")
+}
+
+/// Sorted, de-duplicated class names with "unknown" moved to the end.
+pub fn class_names(labels: &[String]) -> Vec<String> {
+    let mut names = labels.to_vec();
+    names.sort();
+    names.dedup();
+    if let Some(pos) = names.iter().position(|x| x == "unknown") {
+        names.remove(pos);
+        names.push("unknown".to_string());
+    }
+    names
 }
 
 pub fn encode_labels(labels: &[String], class_names: &[String]) -> Array2<f64> {
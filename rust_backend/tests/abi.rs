@@ -0,0 +1,85 @@
+// abi.rs
+//
+// Compiles tests/c/abi_test.c against the generated header and runs it
+// against the freshly built cdylib, so any drift between lib.rs, the header
+// and the signatures the Qt frontend uses fails `cargo test`. Extra flags
+// from `CFLAGS` are passed to the compiler, e.g. to run the harness under
+// AddressSanitizer:
+//
+//     CFLAGS=-fsanitize=address cargo test --test abi
+
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+mod common;
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+// Integration tests live in target/<profile>/deps next to the cdylib
+fn library_dir() -> PathBuf {
+    env::current_exe()
+        .expect("Failed to locate test executable")
+        .parent()
+        .expect("Test executable has no parent directory")
+        .to_path_buf()
+}
+
+fn compile_harness(out: &Path) {
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let lib_dir = library_dir();
+    let cflags = env::var("CFLAGS").unwrap_or_default();
+
+    let status = Command::new(&cc)
+        .arg("-std=c11")
+        .args(["-Wall", "-Wextra", "-Werror", "-pedantic"])
+        .args(cflags.split_whitespace())
+        .arg("-I")
+        .arg(manifest_dir().join("include"))
+        .arg(manifest_dir().join("tests").join("c").join("abi_test.c"))
+        .arg("-o")
+        .arg(out)
+        .arg("-L")
+        .arg(&lib_dir)
+        .arg(format!("-Wl,-rpath,{}", lib_dir.display()))
+        .arg("-lfruit_classifier")
+        .arg("-lm")
+        .status()
+        .unwrap_or_else(|e| panic!("Failed to run C compiler `{}`: {}", cc, e));
+
+    assert!(status.success(), "C ABI harness failed to compile");
+}
+
+// CARGO_TARGET_TMPDIR is shared by every profile, so the harness is named
+// after the one it links against
+fn harness_path() -> PathBuf {
+    let lib_dir = library_dir();
+    let profile = lib_dir
+        .parent()
+        .and_then(Path::file_name)
+        .expect("Test executable is not inside a profile directory");
+    PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("abi_test_{}", profile.to_string_lossy()))
+}
+
+#[test]
+fn c_harness_matches_exported_abi() {
+    let exe = harness_path();
+    compile_harness(&exe);
+
+    // The rpath may be emitted as RUNPATH, which the environment overrides,
+    // so point the loader at the fresh library explicitly as well
+    let output = Command::new(&exe)
+        .env("LD_LIBRARY_PATH", library_dir())
+        .arg(common::DATASET)
+        .output()
+        .expect("Failed to run C ABI harness");
+
+    assert!(
+        output.status.success(),
+        "C ABI harness failed:\n{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
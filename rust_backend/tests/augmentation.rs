@@ -0,0 +1,120 @@
+// augmentation.rs
+//
+// Measurement noise, unit rounding and mixup on normalized features, and a
+// training run with every perturbation enabled.
+
+use fruit_classifier::augmentation::Augmenter;
+use fruit_classifier::config::{AugmentationConfig, Noise, TrainingConfig};
+use fruit_classifier::training;
+use fruit_classifier::utils::{Normalizer, ScalerKind};
+use ndarray::{Array2, Axis};
+
+mod common;
+
+fn prepared() -> (Normalizer, Array2<f64>, Array2<f64>) {
+    let samples = common::samples();
+    let mut normalizer = Normalizer::new();
+    normalizer.fit_with(ScalerKind::Standard, &samples);
+    let features = normalizer.transform(&samples);
+    let mut labels = Array2::zeros((samples.len(), 2));
+    for (i, sample) in samples.iter().enumerate() {
+        labels[[i, (sample.label == "apple") as usize]] = 1.0;
+    }
+    (normalizer, features, labels)
+}
+
+fn raw(features: &Array2<f64>, normalizer: &Normalizer) -> Array2<f64> {
+    features * &normalizer.std + &normalizer.mean
+}
+
+#[test]
+fn defaults_are_disabled() {
+    assert!(!AugmentationConfig::default().is_enabled());
+}
+
+#[test]
+fn gaussian_noise_has_the_configured_spread_in_raw_units() {
+    let (normalizer, features, labels) = prepared();
+    let config = AugmentationConfig {
+        noise: Noise::Gaussian { std: [5.0, 0.2, 0.0, 1.0] },
+        ..AugmentationConfig::default()
+    };
+    let mut augmenter = Augmenter::new(&config, &normalizer, 1);
+    let (augmented, augmented_labels) = augmenter.augment(&features, &labels);
+    assert_eq!(augmented_labels, labels);
+
+    let difference = raw(&augmented, &normalizer) - raw(&features, &normalizer);
+    for (j, expected) in [5.0f64, 0.2, 0.0, 1.0].into_iter().enumerate() {
+        let column = difference.index_axis(Axis(1), j);
+        assert!(column.mean().unwrap().abs() < 0.1 * expected.max(0.01));
+        let std = column.std(0.0);
+        assert!((std - expected).abs() <= 0.1 * expected + 1e-9, "feature {}: {}", j, std);
+    }
+}
+
+#[test]
+fn rounding_snaps_measurements_to_their_units() {
+    let (normalizer, features, labels) = prepared();
+    let config = AugmentationConfig {
+        noise: Noise::Multiplicative { relative: 0.05 },
+        rounding: 1.0,
+        rounding_units: [10.0, 0.5, 0.5, 0.5],
+        ..AugmentationConfig::default()
+    };
+    let (augmented, _) = Augmenter::new(&config, &normalizer, 2).augment(&features, &labels);
+    for row in raw(&augmented, &normalizer).rows() {
+        for (value, unit) in row.iter().zip(config.rounding_units) {
+            let steps = value / unit;
+            assert!((steps - steps.round()).abs() < 1e-6, "{} is not a multiple of {}", value, unit);
+        }
+    }
+}
+
+#[test]
+fn mixup_blends_samples_and_labels() {
+    let (normalizer, features, labels) = prepared();
+    let config = AugmentationConfig {
+        mixup_alpha: 0.4,
+        ..AugmentationConfig::default()
+    };
+    let mut augmenter = Augmenter::new(&config, &normalizer, 3);
+    let (augmented, mixed) = augmenter.augment(&features, &labels);
+
+    assert_ne!(mixed, labels);
+    for (row, original) in mixed.rows().into_iter().zip(labels.rows()) {
+        assert!((row.sum() - 1.0).abs() < 1e-9);
+        // The original sample keeps at least half of the weight
+        let class = original.iter().position(|&v| v == 1.0).unwrap();
+        assert!(row[class] >= 0.5 - 1e-9);
+    }
+    assert_ne!(augmented, features);
+
+    let (again, _) = Augmenter::new(&config, &normalizer, 3).augment(&features, &labels);
+    assert_eq!(again, augmented);
+    let (next_epoch, _) = augmenter.augment(&features, &labels);
+    assert_ne!(next_epoch, augmented);
+}
+
+#[test]
+fn network_trains_on_augmented_epochs() {
+    let config = TrainingConfig::from_toml_str(
+        r#"
+        seed = 23
+        [optimizer]
+        learning_rate = 0.05
+        momentum = 0.9
+        [schedule]
+        epochs = 40
+        [augmentation]
+        rounding = 0.3
+        mixup_alpha = 0.2
+        noise = { kind = "multiplicative", relative = 0.03 }
+        "#,
+    )
+    .unwrap();
+    let result = training::run_training_from_samples(&common::samples(), &config).unwrap();
+    assert!(result.final_accuracy > 0.9, "accuracy {}", result.final_accuracy);
+
+    let again = training::run_training_from_samples(&common::samples(), &config).unwrap();
+    assert_eq!(result.losses(), again.losses());
+}
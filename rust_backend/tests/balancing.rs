@@ -0,0 +1,143 @@
+// balancing.rs
+//
+// Oversampling, undersampling and SMOTE on a small imbalanced problem, and
+// training on an imbalanced subset of the dataset.
+
+use fruit_classifier::balancing::{class_counts, rebalance};
+use fruit_classifier::config::{Balancing, ClassWeights};
+use fruit_classifier::data::FruitSample;
+use fruit_classifier::training;
+use ndarray::{Array2, Axis};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+mod common;
+
+// 12 samples of class 0, 4 of class 1 and 2 of class 2
+fn imbalanced() -> (Array2<f64>, Array2<f64>) {
+    let sizes = [12, 4, 2];
+    let n: usize = sizes.iter().sum();
+    let mut features = Array2::zeros((n, 4));
+    let mut labels = Array2::zeros((n, 3));
+    let mut row = 0;
+    for (class, &size) in sizes.iter().enumerate() {
+        for i in 0..size {
+            for j in 0..4 {
+                features[[row, j]] = class as f64 * 10.0 + i as f64 * 0.5 + j as f64;
+            }
+            labels[[row, class]] = 1.0;
+            row += 1;
+        }
+    }
+    (features, labels)
+}
+
+fn rows_of_class(features: &Array2<f64>, labels: &Array2<f64>, class: usize) -> Vec<Vec<f64>> {
+    features
+        .axis_iter(Axis(0))
+        .zip(labels.axis_iter(Axis(0)))
+        .filter(|(_, label)| label[class] == 1.0)
+        .map(|(row, _)| row.to_vec())
+        .collect()
+}
+
+#[test]
+fn none_leaves_the_data_unchanged() {
+    let (x, y) = imbalanced();
+    let (bx, by) = rebalance(&x, &y, Balancing::None, &mut StdRng::seed_from_u64(0));
+    assert_eq!((bx, by), (x, y));
+}
+
+#[test]
+fn oversampling_duplicates_minority_samples() {
+    let (x, y) = imbalanced();
+    let (bx, by) = rebalance(&x, &y, Balancing::Oversample, &mut StdRng::seed_from_u64(1));
+    assert_eq!(class_counts(&by), [12, 12, 12]);
+
+    for class in 0..3 {
+        let originals = rows_of_class(&x, &y, class);
+        assert!(rows_of_class(&bx, &by, class).iter().all(|row| originals.contains(row)));
+    }
+}
+
+#[test]
+fn undersampling_keeps_a_subset_of_each_class() {
+    let (x, y) = imbalanced();
+    let (bx, by) = rebalance(&x, &y, Balancing::Undersample, &mut StdRng::seed_from_u64(2));
+    assert_eq!(class_counts(&by), [2, 2, 2]);
+
+    let mut kept = rows_of_class(&bx, &by, 0);
+    kept.dedup();
+    assert_eq!(kept.len(), 2);
+    assert!(kept.iter().all(|row| rows_of_class(&x, &y, 0).contains(row)));
+}
+
+#[test]
+fn smote_interpolates_within_each_class() {
+    let (x, y) = imbalanced();
+    let method = Balancing::Smote { k_neighbors: 3 };
+    let (bx, by) = rebalance(&x, &y, method, &mut StdRng::seed_from_u64(3));
+    assert_eq!(class_counts(&by), [12, 12, 12]);
+
+    for class in 1..3 {
+        let originals = rows_of_class(&x, &y, class);
+        let synthetic: Vec<_> = rows_of_class(&bx, &by, class)
+            .into_iter()
+            .filter(|row| !originals.contains(row))
+            .collect();
+        assert!(!synthetic.is_empty());
+        for row in synthetic {
+            // Every point lies on a segment between two samples of its class,
+            // so within the class's range on each feature
+            for j in 0..4 {
+                let min = originals.iter().map(|r| r[j]).fold(f64::INFINITY, f64::min);
+                let max = originals.iter().map(|r| r[j]).fold(f64::NEG_INFINITY, f64::max);
+                assert!(row[j] >= min && row[j] <= max, "class {}: {:?}", class, row);
+            }
+        }
+    }
+
+    let (again, _) = rebalance(&x, &y, method, &mut StdRng::seed_from_u64(3));
+    assert_eq!(bx, again);
+}
+
+// The dataset with only a tenth of the grapes
+fn few_grapes() -> Vec<FruitSample> {
+    let mut grapes = 0;
+    common::samples()
+        .into_iter()
+        .filter(|s| {
+            grapes += (s.label == "grape") as usize;
+            s.label != "grape" || grapes % 10 == 0
+        })
+        .collect()
+}
+
+#[test]
+fn training_rebalances_an_imbalanced_split() {
+    let samples = few_grapes();
+    for balancing in [Balancing::Oversample, Balancing::Undersample, Balancing::Smote { k_neighbors: 5 }] {
+        let mut config = common::config(17);
+        config.schedule.epochs = 80;
+        config.balancing = balancing;
+        let result = training::run_training_from_samples(&samples, &config).unwrap();
+        assert!(result.final_accuracy > 0.9, "{:?}: accuracy {}", balancing, result.final_accuracy);
+    }
+}
+
+#[test]
+fn balanced_weights_see_the_split_before_resampling() {
+    let mut config = common::config(17);
+    config.schedule.epochs = 1;
+    config.balancing = Balancing::Oversample;
+    config.loss.class_weights = ClassWeights::Balanced;
+    let result = training::run_training_from_samples(&few_grapes(), &config).unwrap();
+
+    let weights = result.model.loss().class_weights().unwrap();
+    let grape = result.class_names.iter().position(|name| name == "grape").unwrap();
+    for (class, weight) in weights.iter().enumerate() {
+        if class != grape {
+            assert!(weights[grape] > 5.0 * weight, "{:?}", weights);
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// baselines.rs
+//
+// Every model behind the `Classifier` trait on small hand-made problems, and
+// the comparison across them on the real dataset.
+
+use fruit_classifier::baselines::{GaussianNaiveBayes, KNearestNeighbors, LogisticRegression, LogisticRegressionParams};
+use fruit_classifier::classifier::{Classifier, Metrics};
+use fruit_classifier::compare;
+use fruit_classifier::model::NeuralNet;
+use fruit_classifier::tree::{DecisionTree, TreeParams};
+use ndarray::{array, Array2};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+mod common;
+
+// Three well separated clusters along the first two features
+fn clusters() -> (Array2<f64>, Array2<f64>) {
+    let centers = [(-2.0, -2.0), (0.0, 2.0), (2.0, -2.0)];
+    let offsets = [(-0.3, 0.1), (0.2, -0.2), (0.1, 0.3), (-0.1, -0.1)];
+
+    let mut features = Array2::zeros((centers.len() * offsets.len(), 4));
+    let mut labels = Array2::zeros((centers.len() * offsets.len(), centers.len()));
+    for (c, (cx, cy)) in centers.iter().enumerate() {
+        for (o, (dx, dy)) in offsets.iter().enumerate() {
+            let row = c * offsets.len() + o;
+            features[[row, 0]] = cx + dx;
+            features[[row, 1]] = cy + dy;
+            labels[[row, c]] = 1.0;
+        }
+    }
+    (features, labels)
+}
+
+fn assert_separates<C: Classifier>(model: &C) {
+    let (features, labels) = clusters();
+    let metrics = Metrics::evaluate(model, &features, &labels);
+    assert_eq!(metrics.accuracy, 1.0, "{} misclassifies the training clusters", model.name());
+
+    let probabilities = model.predict_proba(&array![[-2.0, -2.0, 0.0, 0.0], [2.0, -2.0, 0.0, 0.0]]);
+    for row in probabilities.rows() {
+        assert!((row.sum() - 1.0).abs() < 1e-9, "{} probabilities don't sum to 1", model.name());
+    }
+    assert_eq!(model.predict(&array![[0.1, 1.8, 0.0, 0.0]]), vec![1], "{}", model.name());
+}
+
+#[test]
+fn every_baseline_separates_clusters() {
+    let (x, y) = clusters();
+    assert_separates(&KNearestNeighbors::fit(&x, &y, 3));
+    assert_separates(&LogisticRegression::fit(&x, &y, &LogisticRegressionParams::default()));
+    assert_separates(&GaussianNaiveBayes::fit(&x, &y));
+    assert_separates(&DecisionTree::fit(&x, &y, &TreeParams::default()));
+}
+
+#[test]
+fn tree_respects_its_limits() {
+    let (x, y) = clusters();
+    let stump = DecisionTree::fit(
+        &x,
+        &y,
+        &TreeParams {
+            max_depth: 1,
+            ..TreeParams::default()
+        },
+    );
+    assert_eq!(stump.depth(), 1);
+    assert_eq!(stump.n_leaves(), 2);
+
+    let full = DecisionTree::fit(&x, &y, &TreeParams::default());
+    assert_eq!(full.n_leaves(), 3);
+}
+
+#[test]
+fn neural_net_is_a_classifier() {
+    let (x, y) = clusters();
+    let mut nn = NeuralNet::with_rng(4, 8, 3, 0.1, &mut StdRng::seed_from_u64(11));
+    for _ in 0..300 {
+        nn.train_one_epoch(&x, &y, 4);
+    }
+    assert_separates(&nn);
+}
+
+#[test]
+fn neural_net_trains_on_batches_that_do_not_divide_the_data() {
+    let (x, y) = clusters();
+    // One partial batch per epoch, then a full one followed by a single row
+    for batch_size in [x.nrows() + 1, x.nrows() - 1] {
+        let mut nn = NeuralNet::with_rng(4, 8, 3, 0.1, &mut StdRng::seed_from_u64(11));
+        for _ in 0..300 {
+            nn.train_one_epoch(&x, &y, batch_size);
+        }
+        assert_separates(&nn);
+    }
+}
+
+#[test]
+fn metrics_match_hand_computed_values() {
+    let labels = array![[1.0, 0.0], [1.0, 0.0], [0.0, 1.0], [0.0, 1.0]];
+    let probabilities = array![[0.9, 0.1], [0.4, 0.6], [0.2, 0.8], [0.4, 0.6]];
+    let metrics = Metrics::from_probabilities(&probabilities, &labels);
+
+    // Only the second sample is wrong: F1 is 2/3 for class 0 and 4/5 for class 1
+    assert_eq!(metrics.accuracy, 0.75);
+    let expected_loss = -(0.9f64.ln() + 0.4f64.ln() + 0.8f64.ln() + 0.6f64.ln()) / 4.0;
+    assert!((metrics.log_loss - expected_loss).abs() < 1e-12);
+    assert!((metrics.macro_f1 - (2.0 / 3.0 + 0.8) / 2.0).abs() < 1e-12);
+}
+
+#[test]
+fn compare_reports_every_model() {
+    let samples = common::samples();
+    let mut config = common::config(5);
+    config.schedule.epochs = 50;
+
+    let reports = compare::compare_models(&samples, &config).unwrap();
+    let names: Vec<&str> = reports.iter().map(|r| r.model.as_str()).collect();
+    let expected = [
+        "knn",
+        "logistic_regression",
+        "naive_bayes",
+        "decision_tree",
+        "random_forest",
+        "gradient_boosting",
+        "neural_net",
+    ];
+    assert_eq!(names, expected);
+    for report in &reports {
+        assert!(report.validation.accuracy > 0.8, "{} only reached {}", report.model, report.validation.accuracy);
+    }
+}
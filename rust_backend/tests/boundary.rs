@@ -0,0 +1,82 @@
+// boundary.rs
+//
+// Decision regions: the grid agrees with `predict`, held features, config
+// validation and PNG/SVG output for a trained network.
+
+use fruit_classifier::boundary::{self, DecisionGrid, GridAxis};
+use fruit_classifier::config::{ConfigError, HeldFeatures, TrainingConfig};
+use fruit_classifier::data::FruitSample;
+use std::path::PathBuf;
+
+mod common;
+
+fn sample(weight: f64, size: f64) -> FruitSample {
+    FruitSample {
+        weight,
+        size,
+        width: size * 2.0,
+        height: 1.0,
+        label: "apple".to_string(),
+    }
+}
+
+#[test]
+fn held_features_are_training_means_or_a_sample() {
+    let train = [sample(100.0, 4.0), sample(300.0, 8.0)];
+    assert_eq!(boundary::held_values(HeldFeatures::Mean, &train), [200.0, 6.0, 12.0, 1.0]);
+
+    let measurements = [150.0, 7.0, 6.0, 6.0];
+    assert_eq!(boundary::held_values(HeldFeatures::Sample { measurements }, &train), measurements);
+}
+
+#[test]
+fn boundary_settings_are_validated() {
+    for (text, field) in [
+        ("[boundary]\nx = \"colour\"", "boundary.x"),
+        ("[boundary]\nx = \"size\"\ny = \"size\"", "boundary.y"),
+        ("[boundary]\nresolution = 1", "boundary.resolution"),
+        ("[boundary.held]\nkind = \"sample\"\nmeasurements = [150, 7, 0, 6]", "boundary.held.measurements"),
+    ] {
+        match TrainingConfig::from_toml_str(text) {
+            Err(ConfigError::Invalid { field: got, .. }) => assert_eq!(got, field, "for {:?}", text),
+            other => panic!("Expected an invalid `{}` for {:?}, got {:?}", field, text, other),
+        }
+    }
+}
+
+#[test]
+fn trained_network_regions_match_predict_and_are_plotted() {
+    let samples = common::samples();
+    let mut config = common::config(3);
+    config.schedule.epochs = 40;
+    let (result, train) = boundary::network_boundary(&samples, &config).unwrap();
+    assert_eq!(train.len(), samples.len() * 4 / 5);
+
+    let held = [150.0, 7.0, 6.0, 6.0];
+    let (x, y) = (
+        GridAxis { feature: 0, low: 0.0, high: 400.0 },
+        GridAxis { feature: 3, low: 2.0, high: 10.0 },
+    );
+    let grid = DecisionGrid::new(&result.model, &result.normalizer, x, y, held, 8);
+    assert_eq!((grid.xs.len(), grid.ys.len(), grid.classes.len()), (8, 8, 64));
+    assert_eq!((grid.xs[0], grid.ys[7]), (25.0, 9.5));
+    for cell in [0, 13, 63] {
+        let (weight, height) = (grid.xs[cell % 8], grid.ys[cell / 8]);
+        let (label, confidence) = result.predict(weight, held[1], held[2], height);
+        assert_eq!(grid.confidences[cell], confidence);
+        if label != "unknown" {
+            assert_eq!(result.class_names[grid.classes[cell]], label);
+        }
+    }
+
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    config.boundary.resolution = 20;
+    for name in ["decision_boundary.png", "decision_boundary.svg"] {
+        let path = dir.join(name);
+        config.boundary.output = path.to_str().unwrap().to_string();
+        boundary::plot_decision_boundary(&result, &train, &config.boundary).unwrap();
+        assert!(path.metadata().unwrap().len() > 0);
+    }
+    let svg = std::fs::read_to_string(dir.join("decision_boundary.svg")).unwrap();
+    assert!(svg.starts_with("<svg") && svg.contains("watermelon"));
+}
@@ -0,0 +1,290 @@
+/* ABI conformance test for the fruit classifier C API.
+ *
+ * The function pointer declarations below spell out the signatures the Qt
+ * frontend relies on. They are checked against the generated header at
+ * compile time (-Werror turns any mismatch into a build failure), and the
+ * exported functions are then exercised against the real library.
+ */
+#include "fruit_classifier.h"
+
+#include <math.h>
+#include <stdint.h>
+#include <stdio.h>
+#include <string.h>
+
+static bool (*const train_network_sig)(const char *, size_t, TrainingResult *) = train_network;
+static bool (*const train_network_with_callback_sig)(const char *, size_t, EpochCallback, void *,
+                                                     TrainingResult *) = train_network_with_callback;
+static FcTrainJob *(*const fc_train_start_sig)(const char *, size_t) = fc_train_start;
+static FcTrainJob *(*const fc_train_start_with_config_sig)(const char *) = fc_train_start_with_config;
+static FcTrainStatus (*const fc_train_poll_sig)(const FcTrainJob *, FcTrainProgress *) = fc_train_poll;
+static bool (*const fc_train_history_sig)(const FcTrainJob *, FcF64Array *, FcF64Array *) = fc_train_history;
+static void (*const fc_train_cancel_sig)(const FcTrainJob *) = fc_train_cancel;
+static FcModel *(*const fc_train_join_sig)(FcTrainJob *) = fc_train_join;
+static double (*const fc_model_final_accuracy_sig)(const FcModel *) = fc_model_final_accuracy;
+static char *(*const fc_model_predict_sig)(const FcModel *, double, double, double, double) = fc_model_predict;
+static char *(*const fc_model_predict_f32_sig)(const FcModel *, float, float, float, float) = fc_model_predict_f32;
+static int32_t (*const fc_model_precision_sig)(const FcModel *) = fc_model_precision;
+static FcModel *(*const fc_model_convert_sig)(const FcModel *, uint32_t) = fc_model_convert;
+static bool (*const fc_model_explain_sig)(const FcModel *, double, double, double, double, uint32_t,
+                                          FcExplanation *) = fc_model_explain;
+static void (*const fc_explanation_free_sig)(FcExplanation *) = fc_explanation_free;
+static bool (*const fc_model_save_sig)(const FcModel *, const char *) = fc_model_save;
+static FcModel *(*const fc_model_load_sig)(const char *) = fc_model_load;
+static void (*const fc_model_free_sig)(FcModel *) = fc_model_free;
+static char *(*const predict_sig)(double, double, double, double) = predict;
+static void (*const fc_f64_array_free_sig)(FcF64Array *) = fc_f64_array_free;
+static void (*const free_training_result_sig)(TrainingResult *) = free_training_result;
+static void (*const free_string_sig)(char *) = free_string;
+static FcErrorCode (*const fc_last_error_code_sig)(void) = fc_last_error_code;
+static const char *(*const fc_last_error_message_sig)(void) = fc_last_error_message;
+
+_Static_assert(offsetof(FcTrainProgress, epoch) == 0, "FcTrainProgress.epoch moved");
+_Static_assert(offsetof(FcTrainProgress, total_epochs) == sizeof(size_t), "FcTrainProgress.total_epochs moved");
+_Static_assert(offsetof(FcTrainProgress, loss) == 2 * sizeof(size_t), "FcTrainProgress.loss moved");
+_Static_assert(offsetof(FcTrainProgress, finished) == 2 * sizeof(size_t) + 3 * sizeof(double),
+               "FcTrainProgress.finished moved");
+_Static_assert(offsetof(FcF64Array, len) == sizeof(double *), "FcF64Array layout changed");
+_Static_assert(offsetof(TrainingResult, losses) == sizeof(FcF64Array), "TrainingResult layout changed");
+_Static_assert(offsetof(TrainingResult, final_accuracy) == 2 * sizeof(FcF64Array), "TrainingResult layout changed");
+_Static_assert(offsetof(FcExplanation, confidence) == sizeof(char *), "FcExplanation layout changed");
+_Static_assert(offsetof(FcExplanation, attributions) == sizeof(char *) + 2 * sizeof(double),
+               "FcExplanation layout changed");
+_Static_assert(FC_ERROR_CODE_NONE == 0 && FC_ERROR_CODE_PANIC == 5 && FC_ERROR_CODE_CONFIG == 7 &&
+                   FC_ERROR_CODE_INVALID_ARGUMENT == 8,
+               "FcErrorCode values changed");
+_Static_assert(FC_EXPLAIN_METHOD_GRADIENT_TIMES_INPUT == 0 && FC_EXPLAIN_METHOD_KERNEL_SHAP == 2,
+               "FcExplainMethod values changed");
+_Static_assert(FC_PRECISION_F64 == 0 && FC_PRECISION_F32 == 1, "FcPrecision values changed");
+
+static int failures = 0;
+
+#define CHECK(cond)                                                      \
+    do {                                                                 \
+        if (!(cond)) {                                                   \
+            fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__,      \
+                    __LINE__, #cond);                                    \
+            failures++;                                                  \
+        }                                                                \
+    } while (0)
+
+static bool stop_after_two(void *user_data, size_t epoch, double loss, double train_accuracy,
+                           double val_accuracy)
+{
+    size_t *calls = user_data;
+    (*calls)++;
+    CHECK(epoch == *calls);
+    CHECK(isfinite(loss));
+    CHECK(train_accuracy >= 0.0 && train_accuracy <= 1.0);
+    CHECK(val_accuracy >= 0.0 && val_accuracy <= 1.0);
+    return epoch < 2;
+}
+
+static void test_errors(void)
+{
+    TrainingResult result;
+
+    CHECK(!train_network_sig(NULL, 1, &result));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+    CHECK(fc_last_error_message_sig() != NULL);
+
+    CHECK(!train_network_sig("\xff\xfe", 1, &result));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_INVALID_UTF8);
+
+    CHECK(!train_network_sig("does/not/exist.csv", 1, &result));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_DATASET);
+
+    CHECK(!train_network_sig("does/not/exist.csv", 1, NULL));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+
+    /* Out-of-range epoch counts are rejected before anything is allocated */
+    CHECK(!train_network_sig("does/not/exist.csv", SIZE_MAX, &result));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(!train_network_sig("does/not/exist.csv", 0, &result));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(fc_train_start_sig("does/not/exist.csv", MAX_EPOCHS + 1) == NULL);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_INVALID_ARGUMENT);
+
+    CHECK(fc_train_poll_sig(NULL, NULL) == FC_TRAIN_STATUS_ERROR);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+    FcTrainProgress untouched;
+    memset(&untouched, 0, sizeof(untouched));
+    untouched.epoch = 7;
+    CHECK(fc_train_poll_sig(NULL, &untouched) == FC_TRAIN_STATUS_ERROR);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+    CHECK(untouched.epoch == 7 && !untouched.finished);
+    FcF64Array history = {NULL, 0};
+    CHECK(!fc_train_history_sig(NULL, &history, &history));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+    CHECK(isnan(fc_model_final_accuracy_sig(NULL)));
+    CHECK(fc_model_predict_sig(NULL, 150.0, 7.0, 6.0, 6.0) == NULL);
+    CHECK(fc_model_precision_sig(NULL) == -1);
+    CHECK(fc_model_convert_sig(NULL, FC_PRECISION_F32) == NULL);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+
+    /* Freeing null is always allowed */
+    fc_f64_array_free_sig(NULL);
+    free_training_result_sig(NULL);
+    free_string_sig(NULL);
+    fc_model_free_sig(NULL);
+    fc_explanation_free_sig(NULL);
+}
+
+static void test_predict(void)
+{
+    char *label = predict_sig(150.0, 7.0, 6.0, 6.0);
+    CHECK(label != NULL && strcmp(label, "apple") == 0);
+    free_string_sig(label);
+}
+
+static void test_train_network(const char *dataset)
+{
+    TrainingResult result;
+    memset(&result, 0, sizeof(result));
+    result.final_accuracy = -1.0;
+
+    CHECK(train_network_sig(dataset, 3, &result));
+    CHECK(result.accuracies.len == 3 && result.losses.len == 3);
+    CHECK(result.accuracies.ptr != NULL && result.losses.ptr != NULL);
+    CHECK(result.final_accuracy >= 0.0 && result.final_accuracy <= 1.0);
+    for (size_t i = 0; i < result.accuracies.len; i++) {
+        CHECK(result.accuracies.ptr[i] >= 0.0 && result.accuracies.ptr[i] <= 1.0);
+        CHECK(isfinite(result.losses.ptr[i]));
+    }
+
+    free_training_result_sig(&result);
+    CHECK(result.accuracies.ptr == NULL && result.accuracies.len == 0);
+    CHECK(result.losses.ptr == NULL && result.losses.len == 0);
+
+    /* Freeing again is a no-op */
+    free_training_result_sig(&result);
+}
+
+static void test_callback_stops(const char *dataset)
+{
+    TrainingResult result;
+    size_t calls = 0;
+
+    CHECK(train_network_with_callback_sig(dataset, 100, stop_after_two, &calls, &result));
+    CHECK(calls == 2);
+    CHECK(result.accuracies.len == 2 && result.losses.len == 2);
+    free_training_result_sig(&result);
+}
+
+static void test_background_job(const char *dataset)
+{
+    FcTrainJob *job = fc_train_start_sig(dataset, 1000000);
+    CHECK(job != NULL);
+    if (!job) {
+        return;
+    }
+
+    FcTrainProgress progress;
+    memset(&progress, 0, sizeof(progress));
+    FcTrainStatus status = fc_train_poll_sig(job, &progress);
+    CHECK(status == FC_TRAIN_STATUS_RUNNING || status == FC_TRAIN_STATUS_FINISHED);
+    CHECK(progress.total_epochs == 1000000);
+
+    /* The history holds every finished epoch, at least as many as polled */
+    FcF64Array accuracies = {NULL, 0};
+    FcF64Array losses = {NULL, 0};
+    CHECK(fc_train_history_sig(job, &accuracies, &losses));
+    CHECK(accuracies.len == losses.len && accuracies.len >= progress.epoch);
+    for (size_t i = 0; i < accuracies.len; i++) {
+        CHECK(accuracies.ptr[i] >= 0.0 && accuracies.ptr[i] <= 1.0);
+        CHECK(isfinite(losses.ptr[i]));
+    }
+    fc_f64_array_free_sig(&accuracies);
+    fc_f64_array_free_sig(&losses);
+    CHECK(!fc_train_history_sig(job, &accuracies, NULL));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+
+    fc_train_cancel_sig(job);
+    FcModel *model = fc_train_join_sig(job);
+    CHECK(model != NULL);
+    if (!model) {
+        return;
+    }
+
+    double accuracy = fc_model_final_accuracy_sig(model);
+    CHECK(accuracy >= 0.0 && accuracy <= 1.0);
+
+    char *label = fc_model_predict_sig(model, 150.0, 7.0, 6.0, 6.0);
+    CHECK(label != NULL && strlen(label) > 0);
+    free_string_sig(label);
+
+    FcExplanation explanation;
+    memset(&explanation, 0, sizeof(explanation));
+    CHECK(fc_model_explain_sig(model, 150.0, 7.0, 6.0, 6.0, FC_EXPLAIN_METHOD_INTEGRATED_GRADIENTS, &explanation));
+    CHECK(explanation.label != NULL && strlen(explanation.label) > 0);
+    CHECK(explanation.confidence >= 0.0 && explanation.confidence <= 1.0);
+    for (size_t i = 0; i < 4; i++) {
+        CHECK(isfinite(explanation.attributions[i]));
+    }
+    fc_explanation_free_sig(&explanation);
+    CHECK(explanation.label == NULL);
+    fc_explanation_free_sig(&explanation);
+
+    CHECK(!fc_model_explain_sig(model, 150.0, 7.0, 6.0, 6.0, 3, &explanation));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_INVALID_ARGUMENT);
+    CHECK(!fc_model_explain_sig(model, 150.0, 7.0, 6.0, 6.0, FC_EXPLAIN_METHOD_KERNEL_SHAP, NULL));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+
+    /* An f32 copy predicts from floats like the original */
+    CHECK(fc_model_precision_sig(model) == FC_PRECISION_F64);
+    FcModel *single = fc_model_convert_sig(model, FC_PRECISION_F32);
+    CHECK(single != NULL);
+    if (single) {
+        CHECK(fc_model_precision_sig(single) == FC_PRECISION_F32);
+        CHECK(fc_model_final_accuracy_sig(single) == accuracy);
+        char *single_label = fc_model_predict_f32_sig(single, 150.0f, 7.0f, 6.0f, 6.0f);
+        CHECK(single_label != NULL && strlen(single_label) > 0);
+        free_string_sig(single_label);
+        fc_model_free_sig(single);
+    }
+    CHECK(fc_model_convert_sig(model, 2) == NULL);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_INVALID_ARGUMENT);
+    fc_model_free_sig(model);
+
+    /* Dataset errors surface when joining */
+    job = fc_train_start_sig("does/not/exist.csv", 10);
+    CHECK(job != NULL);
+    CHECK(fc_train_join_sig(job) == NULL);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_DATASET);
+}
+
+static void test_config_and_model_files(void)
+{
+    /* Config errors are reported before any thread is started */
+    CHECK(fc_train_start_with_config_sig(NULL) == NULL);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+    CHECK(fc_train_start_with_config_sig("does/not/exist.toml") == NULL);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_CONFIG);
+
+    CHECK(!fc_model_save_sig(NULL, "model.json"));
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_NULL_POINTER);
+    CHECK(fc_model_load_sig("does/not/exist.json") == NULL);
+    CHECK(fc_last_error_code_sig() == FC_ERROR_CODE_IO);
+}
+
+int main(int argc, char **argv)
+{
+    if (argc != 2) {
+        fprintf(stderr, "usage: %s <dataset.csv>\n", argv[0]);
+        return 2;
+    }
+
+    test_errors();
+    test_predict();
+    test_train_network(argv[1]);
+    test_callback_stops(argv[1]);
+    test_background_job(argv[1]);
+    test_config_and_model_files();
+
+    if (failures > 0) {
+        fprintf(stderr, "%d check(s) failed\n", failures);
+        return 1;
+    }
+    printf("all ABI checks passed\n");
+    return 0;
+}
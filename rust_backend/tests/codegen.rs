@@ -0,0 +1,222 @@
+// codegen.rs
+//
+// Compiles the generated C (with the system C compiler) and the generated
+// Rust module (inside a `no_std` library, with rustc) into small drivers and
+// checks that both reproduce `NeuralNet::forward` and `predict`. `CC` and
+// `RUSTC` select the compilers, as for tests/abi.rs.
+
+use fruit_classifier::codegen;
+use fruit_classifier::data::FruitSample;
+use fruit_classifier::training::{self, TrainingResult};
+use fruit_classifier::utils::measurement_row;
+use std::env;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+mod common;
+
+const C_DRIVER: &str = r#"#include <stdio.h>
+#include "fruit_model.h"
+
+int main(void)
+{
+    float w, s, wi, h, p[FRUIT_CLASSES];
+    int j;
+
+    while (scanf("%f %f %f %f", &w, &s, &wi, &h) == 4) {
+        fruit_probabilities(w, s, wi, h, p);
+        for (j = 0; j < FRUIT_CLASSES; j++)
+            printf("%.9g ", p[j]);
+        printf("%s\n", fruit_classify(w, s, wi, h));
+    }
+    return 0;
+}
+"#;
+
+// Every label followed by a NUL, so any byte may appear in a label
+const C_LABELS_DRIVER: &str = r#"#include <stdio.h>
+#include "fruit_model.h"
+
+int main(void)
+{
+    int j;
+
+    for (j = 0; j < FRUIT_CLASSES; j++) {
+        fputs(FRUIT_CLASS_LABELS[j], stdout);
+        putchar('\0');
+    }
+    return 0;
+}
+"#;
+
+const RUST_LIBRARY: &str = "#![no_std]\n#[path = \"fruit_model.rs\"]\npub mod fruit_model;\n";
+
+const RUST_DRIVER: &str = r#"use fruit_model_lib::fruit_model;
+use std::io::BufRead;
+
+fn main() {
+    for line in std::io::stdin().lock().lines() {
+        let m: Vec<f32> = line.unwrap().split_whitespace().map(|v| v.parse().unwrap()).collect();
+        for p in fruit_model::probabilities(m[0], m[1], m[2], m[3]) {
+            print!("{:?} ", p);
+        }
+        println!("{}", fruit_model::classify(m[0], m[1], m[2], m[3]));
+    }
+}
+"#;
+
+fn trained(batch_norm: bool) -> (TrainingResult, Vec<FruitSample>) {
+    let samples = common::samples();
+    let mut config = common::config(4);
+    config.model.batch_norm = batch_norm;
+    let result = training::run_training_from_samples(&samples, &config).unwrap();
+    (result, samples.into_iter().step_by(5).collect())
+}
+
+fn run(command: &mut Command, what: &str) {
+    let output = command.output().unwrap_or_else(|e| panic!("Failed to run {}: {}", what, e));
+    assert!(output.status.success(), "{} failed:\n{}", what, String::from_utf8_lossy(&output.stderr));
+}
+
+fn compile_c(dir: &Path, result: &TrainingResult, driver: &str) -> PathBuf {
+    codegen::write_c(result, dir.join("fruit_model.c")).unwrap();
+    std::fs::write(dir.join("driver.c"), driver).unwrap();
+    let cc = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let exe = dir.join("c_driver");
+    run(
+        Command::new(&cc)
+            .args(["-std=c11", "-Wall", "-Wextra", "-Werror", "-pedantic"])
+            .arg(dir.join("fruit_model.c"))
+            .arg(dir.join("driver.c"))
+            .arg("-o")
+            .arg(&exe),
+        "C compiler",
+    );
+    exe
+}
+
+fn compile_rust(dir: &Path, result: &TrainingResult) -> PathBuf {
+    codegen::write_rust(result, dir.join("fruit_model.rs")).unwrap();
+    std::fs::write(dir.join("lib.rs"), RUST_LIBRARY).unwrap();
+    std::fs::write(dir.join("driver.rs"), RUST_DRIVER).unwrap();
+    let rustc = env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rlib = dir.join("libfruit_model_lib.rlib");
+    let exe = dir.join("rust_driver");
+    run(
+        Command::new(&rustc)
+            .args(["--edition", "2021", "--crate-type", "rlib", "--crate-name", "fruit_model_lib", "-D", "warnings"])
+            .arg(dir.join("lib.rs"))
+            .arg("-o")
+            .arg(&rlib),
+        "rustc (no_std library)",
+    );
+    run(
+        Command::new(&rustc)
+            .args(["--edition", "2021", "--extern"])
+            .arg(format!("fruit_model_lib={}", rlib.display()))
+            .arg(dir.join("driver.rs"))
+            .arg("-o")
+            .arg(&exe),
+        "rustc (driver)",
+    );
+    exe
+}
+
+// Probabilities and label per line of the driver's output
+fn evaluate(exe: &Path, samples: &[FruitSample]) -> Vec<(Vec<f64>, String)> {
+    let mut child = Command::new(exe).stdin(Stdio::piped()).stdout(Stdio::piped()).spawn().unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    for s in samples {
+        writeln!(stdin, "{} {} {} {}", s.weight, s.size, s.width, s.height).unwrap();
+    }
+    drop(stdin);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let mut fields: Vec<&str> = line.split_whitespace().collect();
+            let label = fields.pop().unwrap().to_string();
+            (fields.iter().map(|p| p.parse().unwrap()).collect(), label)
+        })
+        .collect()
+}
+
+fn assert_matches_forward(result: &TrainingResult, samples: &[FruitSample], outputs: &[(Vec<f64>, String)]) {
+    assert_eq!(outputs.len(), samples.len());
+    for (s, (probabilities, label)) in samples.iter().zip(outputs) {
+        let mut input = measurement_row(s.weight, s.size, s.width, s.height);
+        result.normalizer.normalize(&mut input);
+        let (_, _, expected) = result.model.forward(&input);
+        assert_eq!(probabilities.len(), expected.len());
+        for (p, e) in probabilities.iter().zip(&expected) {
+            assert!((p - e).abs() < 1e-4, "{} vs {} for {:?}", p, e, s);
+        }
+
+        // Labels only need to agree away from ties and the unknown threshold
+        let (expected_label, confidence) = result.predict(s.weight, s.size, s.width, s.height);
+        let mut sorted = expected.iter().copied().collect::<Vec<_>>();
+        sorted.sort_by(|a, b| b.total_cmp(a));
+        if sorted[0] - sorted[1] > 1e-4 && (confidence - 0.5).abs() > 1e-4 {
+            assert_eq!(label, expected_label, "for {:?}", s);
+        }
+    }
+}
+
+#[test]
+fn generated_c_matches_forward() {
+    for batch_norm in [false, true] {
+        let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("codegen_c_{}", batch_norm));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (result, samples) = trained(batch_norm);
+        let outputs = evaluate(&compile_c(&dir, &result, C_DRIVER), &samples);
+        assert_matches_forward(&result, &samples, &outputs);
+    }
+}
+
+#[test]
+fn generated_c_spells_any_label() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("codegen_c_labels");
+    std::fs::create_dir_all(&dir).unwrap();
+    let (mut result, _) = trained(false);
+    result.class_names = ["pomme \"verte\"", "ananá", "??=grape\\", "bánh\tmì 7"]
+        .map(String::from)
+        .to_vec();
+
+    let output = Command::new(compile_c(&dir, &result, C_LABELS_DRIVER)).output().unwrap();
+    assert!(output.status.success());
+    let labels: Vec<&str> = std::str::from_utf8(&output.stdout).unwrap().split_terminator('\0').collect();
+    assert_eq!(labels, result.class_names);
+}
+
+#[test]
+fn generated_no_std_rust_matches_forward() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("codegen_rust");
+    std::fs::create_dir_all(&dir).unwrap();
+    let (result, samples) = trained(true);
+    let outputs = evaluate(&compile_rust(&dir, &result), &samples);
+    assert_matches_forward(&result, &samples, &outputs);
+}
+
+#[test]
+fn generated_sources_embed_the_model() {
+    let (result, _) = trained(false);
+    let header = codegen::c_header(&result, "weighing_model.h");
+    assert!(header.contains("#ifndef WEIGHING_MODEL_H") && header.contains("#define FRUIT_CLASSES 4"));
+    let source = codegen::c_source(&result, "weighing_model.h").unwrap();
+    assert!(source.contains("#include \"weighing_model.h\""));
+    let rust = codegen::rust_source(&result).unwrap();
+    for source in [&source, &rust] {
+        assert!(!source.contains("#include <math.h>") && !source.contains("std::"));
+        for name in &result.class_names {
+            assert!(source.contains(&format!("{:?}", name)));
+        }
+    }
+
+    let mut broken = result.clone();
+    broken.normalizer.std[2] = f64::NAN;
+    assert!(codegen::c_source(&broken, "weighing_model.h").is_err());
+    assert!(codegen::rust_source(&broken).is_err());
+}
@@ -0,0 +1,27 @@
+// common/mod.rs
+//
+// Fixtures shared by the integration tests. Each test binary uses only
+// some of these.
+#![allow(dead_code)]
+
+use fruit_classifier::config::TrainingConfig;
+use fruit_classifier::data::{load_dataset, FruitSample};
+
+pub const DATASET: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/dataset/fruits_dataset.csv");
+
+pub fn samples() -> Vec<FruitSample> {
+    load_dataset(DATASET).unwrap()
+}
+
+/// A short, seeded run that still separates the classes: 30 epochs of SGD
+/// with momentum. Tests override only the fields they exercise.
+pub fn config(seed: u64) -> TrainingConfig {
+    let mut config = TrainingConfig {
+        seed: Some(seed),
+        ..TrainingConfig::default()
+    };
+    config.schedule.epochs = 30;
+    config.optimizer.learning_rate = 0.05;
+    config.optimizer.momentum = 0.9;
+    config
+}
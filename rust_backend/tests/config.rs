@@ -0,0 +1,105 @@
+// config.rs
+//
+// Parsing and validation of training configs, and the round trip of the
+// resolved config through a saved model.
+
+use fruit_classifier::config::{ConfigError, LrSchedule, TrainingConfig};
+use fruit_classifier::training::{self, TrainingResult};
+use std::path::PathBuf;
+
+mod common;
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+#[test]
+fn example_config_matches_defaults() {
+    let config = TrainingConfig::from_file(manifest_dir().join("config").join("training.toml"))
+        .expect("Example config should be valid");
+
+    let expected = TrainingConfig {
+        seed: Some(42),
+        ..TrainingConfig::default()
+    };
+    assert_eq!(config, expected);
+}
+
+#[test]
+fn partial_config_falls_back_to_defaults() {
+    let config = TrainingConfig::from_toml_str(
+        r#"
+        [optimizer]
+        learning_rate = 0.05
+
+        [schedule]
+        epochs = 10
+        learning_rate = { kind = "step", step_size = 5, gamma = 0.5 }
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.optimizer.learning_rate, 0.05);
+    assert_eq!(config.optimizer.batch_size, 32);
+    assert_eq!(config.schedule.epochs, 10);
+    assert_eq!(config.schedule.learning_rate, LrSchedule::Step { step_size: 5, gamma: 0.5 });
+    assert_eq!(config.schedule.learning_rate.learning_rate(0.05, 5), 0.025);
+}
+
+#[test]
+fn toml_and_json_agree() {
+    let config = TrainingConfig {
+        seed: Some(7),
+        ..TrainingConfig::default()
+    };
+    let json = serde_json::to_string(&config).unwrap();
+
+    assert_eq!(TrainingConfig::from_toml_str(&config.to_toml_string()).unwrap(), config);
+    assert_eq!(TrainingConfig::from_json_str(&json).unwrap(), config);
+}
+
+#[test]
+fn invalid_values_name_the_field() {
+    for (text, field) in [
+        ("[split]\ntrain = 1.5", "split.train"),
+        ("[optimizer]\nlearning_rate = -1.0", "optimizer.learning_rate"),
+        ("[optimizer]\nbatch_size = 0", "optimizer.batch_size"),
+        ("[schedule]\nepochs = 0", "schedule.epochs"),
+        ("[schedule]\nepochs = 1000001", "schedule.epochs"),
+        ("[model]\nhidden_size = 0", "model.hidden_size"),
+        ("[early_stopping]\nenabled = true\npatience = 0", "early_stopping.patience"),
+        ("[importance]\noutput = \"\"", "importance.output"),
+        ("[boundary]\noutput = \" \"", "boundary.output"),
+        ("[embedding]\noutput = \"\"", "embedding.output"),
+    ] {
+        match TrainingConfig::from_toml_str(text) {
+            Err(ConfigError::Invalid { field: got, .. }) => assert_eq!(got, field, "for {:?}", text),
+            other => panic!("Expected an invalid `{}` for {:?}, got {:?}", field, text, other),
+        }
+    }
+}
+
+#[test]
+fn unknown_keys_are_rejected() {
+    let err = TrainingConfig::from_toml_str("[optimizer]\nlearnig_rate = 0.1").unwrap_err();
+    assert!(matches!(err, ConfigError::Parse(_)), "{:?}", err);
+}
+
+#[test]
+fn saved_model_embeds_its_config() {
+    let mut config = TrainingConfig {
+        seed: Some(1),
+        ..TrainingConfig::default()
+    };
+    config.schedule.epochs = 5;
+    config.data.path = common::DATASET.to_owned();
+
+    let result = training::run_training(&config).unwrap();
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("config_round_trip.json");
+    result.save(&path).unwrap();
+    let loaded = TrainingResult::load(&path).unwrap();
+
+    assert_eq!(loaded.config, config);
+    assert_eq!(loaded.class_names, result.class_names);
+    assert_eq!(loaded.predict(150.0, 7.0, 6.0, 6.0), result.predict(150.0, 7.0, 6.0, 6.0));
+}
@@ -0,0 +1,101 @@
+// embedding.rs
+//
+// Hidden-layer projections: PCA against a known covariance, t-SNE keeping
+// separated clusters apart, the confusion counts and a trained network.
+
+use fruit_classifier::config::{EmbeddedSamples, EmbeddingConfig};
+use fruit_classifier::embedding::{self, Embedding};
+use ndarray::{array, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::PathBuf;
+
+mod common;
+
+#[test]
+fn pca_orders_axes_by_variance_and_preserves_distances() {
+    let mut rng = StdRng::seed_from_u64(5);
+    // Spread 5 along (1, 1, 0) / sqrt(2), 1 along z and 0.1 along (1, -1, 0) / sqrt(2)
+    let data = Array2::from_shape_fn((200, 3), |_| rng.gen_range(-1.0..1.0));
+    let data = data.dot(&array![
+        [5.0 / 2f64.sqrt(), 5.0 / 2f64.sqrt(), 0.0],
+        [0.0, 0.0, 1.0],
+        [0.1 / 2f64.sqrt(), -0.1 / 2f64.sqrt(), 0.0]
+    ]);
+
+    let projected = embedding::pca(&data, 3);
+    let variances = projected.var_axis(Axis(0), 1.0);
+    assert!(variances[0] > variances[1] && variances[1] > variances[2], "{}", variances);
+    assert!(projected.mean_axis(Axis(0)).unwrap().iter().all(|m| m.abs() < 1e-9));
+
+    let distance = |a: &Array2<f64>, i: usize, j: usize| (&a.row(i) - &a.row(j)).mapv(|v| v * v).sum().sqrt();
+    for (i, j) in [(0, 1), (5, 150), (42, 199)] {
+        assert!((distance(&data, i, j) - distance(&projected, i, j)).abs() < 1e-9);
+    }
+
+    let first = embedding::pca(&data, 1);
+    assert_eq!(first.ncols(), 1);
+    assert!((&first.column(0) - &projected.column(0)).iter().all(|d| d.abs() < 1e-12));
+}
+
+#[test]
+fn tsne_keeps_separated_clusters_apart() {
+    let mut rng = StdRng::seed_from_u64(8);
+    let centres = [[0.0; 5], [10.0, 0.0, 0.0, 0.0, 0.0], [0.0, 0.0, 10.0, 10.0, 0.0]];
+    let data = Array2::from_shape_fn((60, 5), |(i, j)| centres[i / 20][j] + rng.gen_range(-1.0..1.0));
+    let settings = EmbeddingConfig {
+        perplexity: 10.0,
+        iterations: 400,
+        ..EmbeddingConfig::default()
+    };
+
+    let y = embedding::tsne(&data, &settings);
+    assert_eq!(y.dim(), (60, 2));
+    assert!(y.iter().all(|v| v.is_finite()));
+    for i in 0..60 {
+        let nearest = (0..60)
+            .filter(|&j| j != i)
+            .min_by(|&a, &b| {
+                let d = |j: usize| (&y.row(i) - &y.row(j)).mapv(|v| v * v).sum();
+                d(a).total_cmp(&d(b))
+            })
+            .unwrap();
+        assert_eq!(nearest / 20, i / 20, "point {} is next to {}", i, nearest);
+    }
+    assert_eq!(embedding::tsne(&data, &settings), y);
+}
+
+#[test]
+fn confusions_count_mistakes_per_pair() {
+    let embedding = Embedding {
+        class_names: vec!["apple".into(), "grape".into(), "unknown".into()],
+        labels: vec![0, 0, 1, 2, 2, 2],
+        predictions: vec![0, 2, 1, 0, 0, 2],
+        pca: Array2::zeros((6, 2)),
+        tsne: Array2::zeros((6, 2)),
+    };
+    assert_eq!(embedding.confusions(), vec![("unknown", "apple", 2), ("apple", "unknown", 1)]);
+}
+
+#[test]
+fn trained_network_embedding_is_plotted() {
+    let samples = common::samples();
+    let mut config = common::config(6);
+    config.schedule.epochs = 20;
+    config.embedding.iterations = 250;
+
+    let (result, embedding) = embedding::network_embedding(&samples, &config).unwrap();
+    let n = samples.len() - samples.len() * 4 / 5;
+    assert_eq!((embedding.pca.dim(), embedding.tsne.dim()), ((n, 2), (n, 2)));
+    let correct = embedding.labels.iter().zip(&embedding.predictions).filter(|(l, p)| l == p).count();
+    assert_eq!(correct as f64 / n as f64, result.final_accuracy);
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("embedding.png");
+    embedding::plot_embedding(&embedding, &path).unwrap();
+    assert!(path.metadata().unwrap().len() > 0);
+
+    config.embedding.samples = EmbeddedSamples::All;
+    config.embedding.iterations = 1;
+    let (_, all) = embedding::network_embedding(&samples[..200], &config).unwrap();
+    assert_eq!(all.labels.len(), 200);
+}
@@ -0,0 +1,90 @@
+// ensemble.rs
+//
+// Soft voting, stacking and the disagreement signal on short training runs.
+
+use fruit_classifier::classifier::Classifier;
+use fruit_classifier::config::{Combination, Resampling, TrainingConfig};
+use fruit_classifier::ensemble::{self, Ensemble};
+use fruit_classifier::model::NeuralNet;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::path::PathBuf;
+
+mod common;
+
+fn config(resampling: Resampling, combination: Combination) -> TrainingConfig {
+    let mut config = common::config(9);
+    config.ensemble.members = 3;
+    config.ensemble.resampling = resampling;
+    config.ensemble.combination = combination;
+    config
+}
+
+#[test]
+fn soft_voting_averages_members() {
+    let ensemble = ensemble::train_ensemble(&common::samples(), &config(Resampling::Bootstrap, Combination::SoftVoting)).unwrap();
+    assert_eq!(ensemble.members.len(), 3);
+    assert_eq!(ensemble.member_accuracies.len(), 3);
+    assert!(ensemble.final_accuracy > 0.9, "accuracy {}", ensemble.final_accuracy);
+
+    let mut input = fruit_classifier::utils::measurement_row(150.0, 7.0, 6.0, 6.0);
+    ensemble.normalizer.normalize(&mut input);
+    let members = ensemble.member_probabilities(&input);
+    let expected = members.iter().fold(members[0].clone() * 0.0, |acc, p| acc + p) / members.len() as f64;
+    let combined = ensemble.predict_proba(&input);
+    assert!((&combined - &expected).iter().all(|d| d.abs() < 1e-12));
+}
+
+#[test]
+fn training_is_reproducible_with_a_seed() {
+    let config = config(Resampling::Seeds, Combination::SoftVoting);
+    let a = ensemble::train_ensemble(&common::samples(), &config).unwrap();
+    let b = ensemble::train_ensemble(&common::samples(), &config).unwrap();
+    assert_eq!(a.member_accuracies, b.member_accuracies);
+    assert_eq!(a.predict(150.0, 7.0, 6.0, 6.0), b.predict(150.0, 7.0, 6.0, 6.0));
+}
+
+#[test]
+fn stacking_produces_probabilities() {
+    let ensemble = ensemble::train_ensemble(&common::samples(), &config(Resampling::Seeds, Combination::Stacking)).unwrap();
+    let mut input = fruit_classifier::utils::measurement_row(5.0, 1.5, 1.5, 1.5);
+    ensemble.normalizer.normalize(&mut input);
+
+    let probabilities = ensemble.predict_proba(&input);
+    assert_eq!(probabilities.ncols(), ensemble.class_names.len());
+    assert!((probabilities.sum() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn disagreement_turns_predictions_unknown() {
+    let mut ensemble = ensemble::train_ensemble(&common::samples(), &config(Resampling::Bootstrap, Combination::SoftVoting)).unwrap();
+    let mut input = fruit_classifier::utils::measurement_row(150.0, 7.0, 6.0, 6.0);
+    ensemble.normalizer.normalize(&mut input);
+    let trained_class = ensemble.members[0].predict(&input)[0];
+
+    // Swap in an untrained network that votes for another class
+    let n_classes = ensemble.class_names.len();
+    let dissenter = (0..100)
+        .map(|seed| NeuralNet::with_rng(4, 16, n_classes, 0.01, &mut StdRng::seed_from_u64(seed)))
+        .find(|net| net.predict(&input)[0] != trained_class)
+        .expect("some initialization predicts another class");
+    ensemble.members[2] = dissenter;
+
+    let prediction = ensemble.predict(150.0, 7.0, 6.0, 6.0);
+    assert!(prediction.disagreement >= 1.0 / 3.0 - 1e-12, "{:?}", prediction);
+    assert_eq!(prediction.label, ensemble.class_names[trained_class]);
+
+    ensemble.config.ensemble.max_disagreement = 0.0;
+    assert_eq!(ensemble.predict(150.0, 7.0, 6.0, 6.0).label, "unknown");
+}
+
+#[test]
+fn ensemble_round_trips_through_json() {
+    let ensemble = ensemble::train_ensemble(&common::samples(), &config(Resampling::Bootstrap, Combination::Stacking)).unwrap();
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("ensemble.json");
+    ensemble.save(&path).unwrap();
+    let loaded = Ensemble::load(&path).unwrap();
+
+    assert_eq!(loaded.config, ensemble.config);
+    assert_eq!(loaded.predict(500.0, 10.0, 10.0, 10.0), ensemble.predict(500.0, 10.0, 10.0, 10.0));
+}
@@ -0,0 +1,135 @@
+// explain.rs
+//
+// Local explanations: input gradients against finite differences, the
+// completeness of integrated gradients, exact Shapley values for an
+// additive model and explanations of a trained network.
+
+use fruit_classifier::classifier::Classifier;
+use fruit_classifier::data::{self, DataSchema};
+use fruit_classifier::explain::{self, ExplainMethod};
+use fruit_classifier::model::NeuralNet;
+use fruit_classifier::training;
+use ndarray::{array, Array2, Axis};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::fs;
+use std::path::PathBuf;
+
+mod common;
+
+fn network(batch_norm: bool) -> NeuralNet {
+    let mut nn = NeuralNet::with_rng(4, 8, 3, 0.1, &mut StdRng::seed_from_u64(3));
+    nn.set_batch_norm(batch_norm);
+    nn
+}
+
+// Class 1 probability is a sum of one term per feature
+struct Additive;
+
+impl Classifier for Additive {
+    fn name(&self) -> &str {
+        "additive"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        let mut probabilities = Array2::zeros((features.nrows(), 2));
+        for (i, row) in features.axis_iter(Axis(0)).enumerate() {
+            let p = 0.5 + 0.1 * row[0] - 0.05 * row[1] * row[1] + 0.02 * row[3];
+            probabilities[[i, 0]] = 1.0 - p;
+            probabilities[[i, 1]] = p;
+        }
+        probabilities
+    }
+}
+
+#[test]
+fn input_gradient_matches_finite_differences() {
+    for batch_norm in [false, true] {
+        let nn = network(batch_norm);
+        let x = array![[0.3, -1.2, 0.7, 0.1], [-0.5, 0.4, 1.5, -0.9]];
+        let gradient = nn.input_gradient(&x, 2);
+
+        let h = 1e-6;
+        for i in 0..x.nrows() {
+            for j in 0..x.ncols() {
+                let (mut up, mut down) = (x.clone(), x.clone());
+                up[[i, j]] += h;
+                down[[i, j]] -= h;
+                let numeric = (nn.predict_proba(&up)[[i, 2]] - nn.predict_proba(&down)[[i, 2]]) / (2.0 * h);
+                assert!((gradient[[i, j]] - numeric).abs() < 1e-6, "{} vs {}", gradient[[i, j]], numeric);
+            }
+        }
+    }
+}
+
+#[test]
+fn integrated_gradients_add_up_to_the_change_in_probability() {
+    let nn = network(false);
+    let x = array![1.2, -0.4, 0.9, 2.0];
+    let baseline = array![0.0, 0.0, 0.0, 0.0];
+
+    let explanation = explain::explain(&nn, x.view(), baseline.view(), ExplainMethod::IntegratedGradients);
+    let total: f64 = explanation.attributions.iter().sum();
+    let change = explanation.probability - explanation.base_value;
+    assert!((total - change).abs() < 1e-3 * change.abs().max(1.0), "{} vs {}", total, change);
+
+    let fine = explain::integrated_gradients(
+        &nn,
+        &x.clone().insert_axis(Axis(0)),
+        &baseline.clone().insert_axis(Axis(0)),
+        explanation.class,
+        2000,
+    );
+    assert!((fine.sum() - change).abs() < 1e-6);
+}
+
+#[test]
+fn kernel_shap_recovers_additive_contributions() {
+    let background = Array2::from_shape_fn((20, 4), |(i, j)| ((i * (j + 2)) % 5) as f64 - 2.0);
+    let x = array![1.5, 2.0, -3.0, 0.5];
+    let phi = explain::kernel_shap(&Additive, x.view(), &background, 1);
+
+    let mean = background.mean_axis(Axis(0)).unwrap();
+    let mean_square = background.column(1).mapv(|v| v * v).mean().unwrap();
+    let expected = [
+        0.1 * (x[0] - mean[0]),
+        -0.05 * (x[1] * x[1] - mean_square),
+        0.0,
+        0.02 * (x[3] - mean[3]),
+    ];
+    for (value, expected) in phi.iter().zip(expected) {
+        assert!((value - expected).abs() < 1e-10, "{} vs {}", phi, expected);
+    }
+}
+
+#[test]
+fn trained_network_explains_its_predictions() {
+    let samples = common::samples();
+    let mut config = common::config(2);
+    config.schedule.epochs = 40;
+    let result = training::run_training_from_samples(&samples, &config).unwrap();
+
+    for method in [ExplainMethod::GradientTimesInput, ExplainMethod::IntegratedGradients, ExplainMethod::KernelShap] {
+        assert_eq!(method.to_string().parse::<ExplainMethod>(), Ok(method));
+        let (label, explanation) = result.explain(150.0, 7.0, 6.0, 6.0, method);
+        let (expected, confidence) = result.predict(150.0, 7.0, 6.0, 6.0);
+        assert_eq!(label, expected);
+        assert_eq!(explanation.probability, confidence);
+        assert_eq!(explanation.attributions.len(), 4);
+        assert!(explanation.attributions.iter().all(|a| a.is_finite()));
+        if method == ExplainMethod::KernelShap {
+            let total: f64 = explanation.attributions.iter().sum();
+            assert!((total - (explanation.probability - explanation.base_value)).abs() < 1e-10);
+        }
+    }
+    assert!("shap".parse::<ExplainMethod>().is_err());
+}
+
+#[test]
+fn measurements_load_without_a_label_column() {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("measurements.csv");
+    fs::write(&path, "height,width,size,weight\n6,6,7,150\n1.5,1.5,1.5,5\n").unwrap();
+
+    let rows = data::load_measurements_with_schema(path.to_str().unwrap(), &DataSchema::default()).unwrap();
+    assert_eq!(rows, vec![[150.0, 7.0, 6.0, 6.0], [5.0, 1.5, 1.5, 1.5]]);
+}
@@ -0,0 +1,68 @@
+// ffi_memory.rs
+//
+// Checks that every array handed out over the C ABI is released with the
+// exact layout it was allocated with. Run under Miri to have mismatched
+// allocation/deallocation pairs reported as undefined behaviour:
+//
+//     cargo +nightly miri test --test ffi_memory
+
+use fruit_classifier::{FcF64Array, TrainingResult, fc_f64_array_free, free_training_result};
+
+#[test]
+fn array_round_trips_through_free() {
+    for len in [0, 1, 2, 7, 64] {
+        let values: Vec<f64> = (0..len).map(|i| i as f64 * 0.5).collect();
+        let mut array = FcF64Array::from(values.clone());
+        assert_eq!(array.len, len);
+
+        let view = unsafe { std::slice::from_raw_parts(array.ptr, array.len) };
+        assert_eq!(view, values.as_slice());
+
+        unsafe { fc_f64_array_free(&mut array) };
+        assert!(array.ptr.is_null());
+        assert_eq!(array.len, 0);
+    }
+}
+
+#[test]
+fn array_drops_spare_capacity() {
+    // The free function only knows `len`, so the allocation must not keep
+    // any extra capacity from the source vector
+    let mut values = Vec::with_capacity(32);
+    values.extend_from_slice(&[1.0, 2.0, 3.0]);
+
+    let mut array = FcF64Array::from(values);
+    assert_eq!(array.len, 3);
+    unsafe { fc_f64_array_free(&mut array) };
+}
+
+#[test]
+fn double_free_and_null_are_harmless() {
+    let mut array = FcF64Array::from(vec![1.0, 2.0]);
+    unsafe {
+        fc_f64_array_free(&mut array);
+        fc_f64_array_free(&mut array);
+        fc_f64_array_free(std::ptr::null_mut());
+    }
+
+    let mut empty = FcF64Array::EMPTY;
+    unsafe { fc_f64_array_free(&mut empty) };
+}
+
+#[test]
+fn training_result_frees_both_arrays() {
+    let mut result = TrainingResult {
+        accuracies: vec![0.5, 0.75, 0.9].into(),
+        losses: vec![1.2, 0.8].into(),
+        final_accuracy: 0.9,
+    };
+
+    unsafe { free_training_result(&mut result) };
+    assert!(result.accuracies.ptr.is_null());
+    assert!(result.losses.ptr.is_null());
+
+    unsafe {
+        free_training_result(&mut result);
+        free_training_result(std::ptr::null_mut());
+    }
+}
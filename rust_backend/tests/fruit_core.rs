@@ -0,0 +1,51 @@
+// fruit_core.rs
+//
+// The no_std, allocation-free forward pass agrees with the ndarray one for
+// trained networks with and without batch normalization.
+
+use fruit_classifier::training::{self, UNKNOWN_CONFIDENCE_THRESHOLD};
+use fruit_classifier::utils::measurement_row;
+
+mod common;
+
+#[test]
+fn core_forward_matches_ndarray_forward() {
+    let samples = common::samples();
+    for batch_norm in [false, true] {
+        let mut config = common::config(12);
+        config.schedule.epochs = 20;
+        config.model.batch_norm = batch_norm;
+        let result = training::run_training_from_samples(&samples, &config).unwrap();
+        let network = result.to_core::<16, 4>().unwrap();
+
+        for s in &samples {
+            let mut input = measurement_row(s.weight, s.size, s.width, s.height);
+            result.normalizer.normalize(&mut input);
+            let (_, _, expected) = result.model.forward(&input);
+            let probabilities = network.forward(&[s.weight, s.size, s.width, s.height]);
+            for (p, e) in probabilities.iter().zip(&expected) {
+                assert!((p - e).abs() < 1e-12, "{} vs {} for {:?}", p, e, s);
+            }
+
+            let (class, confidence) = network.classify(&[s.weight, s.size, s.width, s.height]);
+            let (label, expected_confidence) = result.predict(s.weight, s.size, s.width, s.height);
+            assert!((confidence - expected_confidence).abs() < 1e-12);
+            if confidence >= UNKNOWN_CONFIDENCE_THRESHOLD {
+                assert_eq!(result.class_names[class], label);
+            }
+        }
+    }
+}
+
+#[test]
+fn core_network_sizes_must_match() {
+    let samples = common::samples();
+    let mut config = common::config(12);
+    config.schedule.epochs = 1;
+    config.model.hidden_size = 8;
+    let result = training::run_training_from_samples(&samples[..200], &config).unwrap();
+
+    assert!(result.to_core::<8, 4>().is_ok());
+    let err = result.to_core::<16, 4>().unwrap_err();
+    assert_eq!(err.to_string(), "Expected 16 hidden units and 4 classes, the network has 8 and 4");
+}
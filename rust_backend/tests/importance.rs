@@ -0,0 +1,92 @@
+// importance.rs
+//
+// Permutation importance: confidence intervals, a model that only looks at
+// one feature, the trained network and the bar chart.
+
+use fruit_classifier::classifier::Classifier;
+use fruit_classifier::config::ImportanceConfig;
+use fruit_classifier::importance::{self, Estimate};
+use ndarray::{Array2, Axis};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use std::path::PathBuf;
+
+mod common;
+
+// Predicts class 1 exactly when the first feature is positive
+struct FirstFeature;
+
+impl Classifier for FirstFeature {
+    fn name(&self) -> &str {
+        "first_feature"
+    }
+
+    fn predict_proba(&self, features: &Array2<f64>) -> Array2<f64> {
+        let mut probabilities = Array2::zeros((features.nrows(), 2));
+        for (i, row) in features.axis_iter(Axis(0)).enumerate() {
+            let p = if row[0] > 0.0 { 0.9 } else { 0.1 };
+            probabilities[[i, 0]] = 1.0 - p;
+            probabilities[[i, 1]] = p;
+        }
+        probabilities
+    }
+}
+
+#[test]
+fn estimate_uses_a_student_t_interval() {
+    let estimate = Estimate::from_repeats(&[1.0, 2.0, 3.0, 4.0, 5.0], 0.95);
+    assert_eq!(estimate.mean, 3.0);
+    assert!((estimate.std - 2.5f64.sqrt()).abs() < 1e-12);
+    // t(0.975, 4) = 2.776
+    let half_width = 2.776 * 2.5f64.sqrt() / 5f64.sqrt();
+    assert!((estimate.upper - 3.0 - half_width).abs() < 0.01, "{:?}", estimate);
+    assert!((3.0 - estimate.lower - half_width).abs() < 0.01, "{:?}", estimate);
+
+    let constant = Estimate::from_repeats(&[0.5; 4], 0.9);
+    assert_eq!((constant.lower, constant.upper), (0.5, 0.5));
+}
+
+#[test]
+fn unused_features_have_no_importance() {
+    let features = Array2::from_shape_fn((40, 4), |(i, j)| ((i * (j + 3)) % 7) as f64 - 3.0 + if j == 0 { 0.5 } else { 0.0 });
+    let mut labels = Array2::zeros((40, 2));
+    for i in 0..40 {
+        labels[[i, (features[[i, 0]] > 0.0) as usize]] = 1.0;
+    }
+
+    let settings = ImportanceConfig::default();
+    let importances = importance::permutation_importance(&FirstFeature, &features, &labels, &settings, &mut StdRng::seed_from_u64(1));
+    let names: Vec<&str> = importances.iter().map(|f| f.feature.as_str()).collect();
+    assert_eq!(names, ["weight", "size", "width", "height"]);
+
+    assert!(importances[0].accuracy_drop.mean > 0.2, "{:?}", importances[0]);
+    assert!(importances[0].log_loss_increase.lower > 0.0);
+    for unused in &importances[1..] {
+        assert_eq!(unused.accuracy_drop.mean, 0.0);
+        assert_eq!(unused.log_loss_increase.upper, 0.0);
+    }
+}
+
+#[test]
+fn network_importance_is_reproducible_and_plottable() {
+    let samples = common::samples();
+    let mut config = common::config(4);
+    config.schedule.epochs = 40;
+    config.importance.repeats = 5;
+
+    let (result, importances) = importance::network_importance(&samples, &config).unwrap();
+    assert!(result.final_accuracy > 0.9);
+    assert_eq!(importances.len(), 4);
+    for f in &importances {
+        let e = f.accuracy_drop;
+        assert!(e.lower <= e.mean && e.mean <= e.upper, "{:?}", f);
+    }
+    assert!(importances.iter().any(|f| f.accuracy_drop.mean > 0.05));
+
+    let (_, again) = importance::network_importance(&samples, &config).unwrap();
+    assert_eq!(importances, again);
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("feature_importance.png");
+    importance::plot_importances(&importances, &path).unwrap();
+    assert!(path.metadata().unwrap().len() > 0);
+}
@@ -0,0 +1,110 @@
+// layers.rs
+//
+// Dropout and batch normalization on their own and inside a trained
+// network: mode handling, seeded masks, gradients and persisted statistics.
+
+use fruit_classifier::config::TrainingConfig;
+use fruit_classifier::layers::{BatchNorm, Dropout, Mode};
+use fruit_classifier::training::{self, TrainingResult};
+use ndarray::{array, Array2, Axis};
+use std::path::PathBuf;
+
+mod common;
+
+fn activations() -> Array2<f64> {
+    Array2::from_shape_fn((64, 8), |(i, j)| ((i * 7 + j * 3) % 11) as f64 - 4.0 + j as f64)
+}
+
+#[test]
+fn dropout_is_identity_at_inference_and_seeded_in_training() {
+    let x = activations();
+    let mut dropout = Dropout::new(0.5, 7);
+    let (output, mask) = dropout.forward(&x, Mode::Inference);
+    assert_eq!(output, x);
+    assert!(mask.is_none());
+
+    let (a, mask) = dropout.forward(&x, Mode::Training);
+    let mask = mask.unwrap();
+    assert!(mask.iter().all(|&m| m == 0.0 || m == 2.0));
+    assert_eq!(a, &x * &mask);
+    let kept = mask.iter().filter(|&&m| m > 0.0).count() as f64 / mask.len() as f64;
+    assert!((kept - 0.5).abs() < 0.1, "kept {}", kept);
+
+    let (b, _) = Dropout::new(0.5, 7).forward(&x, Mode::Training);
+    assert_eq!(a, b);
+    let (c, _) = dropout.forward(&x, Mode::Training);
+    assert_ne!(a, c);
+}
+
+#[test]
+fn batch_norm_normalizes_batches_and_tracks_running_statistics() {
+    let x = activations();
+    let mut batch_norm = BatchNorm::new(8);
+    let (output, cache) = batch_norm.forward(&x, Mode::Training);
+    assert!(cache.is_some());
+    for column in output.axis_iter(Axis(1)) {
+        assert!(column.mean().unwrap().abs() < 1e-9);
+        assert!((column.var(0.0) - 1.0).abs() < 1e-3);
+    }
+
+    let mean = x.mean_axis(Axis(0)).unwrap();
+    let expected = &mean * 0.1;
+    assert!((batch_norm.running_mean() - &expected).iter().all(|d| d.abs() < 1e-12));
+
+    // Inference uses the running statistics and leaves them alone
+    let before = batch_norm.running_var().clone();
+    let (inference, cache) = batch_norm.forward(&x, Mode::Inference);
+    assert!(cache.is_none());
+    assert_eq!(inference, batch_norm.apply(&x));
+    assert_eq!(batch_norm.running_var(), &before);
+}
+
+#[test]
+fn batch_norm_gradient_matches_finite_differences() {
+    let x = array![[0.5, -1.0], [2.0, 0.3], [-0.7, 1.1], [1.2, -0.4]];
+    // Loss is sum(output * weights) so that d(loss)/d(output) = weights
+    let weights = array![[0.3, -0.2], [0.1, 0.5], [-0.4, 0.2], [0.7, -0.6]];
+    let loss = |x: &Array2<f64>| (BatchNorm::new(2).forward(x, Mode::Training).0 * &weights).sum();
+
+    let mut batch_norm = BatchNorm::new(2);
+    let (_, cache) = batch_norm.forward(&x, Mode::Training);
+    let (grad, _) = batch_norm.backward(&weights, &cache.unwrap());
+
+    let h = 1e-6;
+    for ((i, j), &analytic) in grad.indexed_iter() {
+        let (mut plus, mut minus) = (x.clone(), x.clone());
+        plus[[i, j]] += h;
+        minus[[i, j]] -= h;
+        let numeric = (loss(&plus) - loss(&minus)) / (2.0 * h);
+        assert!((analytic - numeric).abs() < 1e-5, "({}, {}): {} vs {}", i, j, analytic, numeric);
+    }
+}
+
+fn config() -> TrainingConfig {
+    let mut config = common::config(13);
+    config.schedule.epochs = 60;
+    config.model.dropout = 0.2;
+    config.model.batch_norm = true;
+    config
+}
+
+#[test]
+fn network_trains_with_dropout_and_batch_norm() {
+    let samples = common::samples();
+    let result = training::run_training_from_samples(&samples, &config()).unwrap();
+    assert!(result.final_accuracy > 0.9, "accuracy {}", result.final_accuracy);
+    assert_eq!(result.val_losses().len(), 60);
+    assert_eq!(result.model.dropout_rate(), 0.2);
+
+    let again = training::run_training_from_samples(&samples, &config()).unwrap();
+    assert_eq!(result.val_losses(), again.val_losses());
+
+    // Running statistics are part of the saved model
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("batch_norm_model.json");
+    result.save(&path).unwrap();
+    let loaded = TrainingResult::load(&path).unwrap();
+    let (saved, restored) = (result.model.batch_norm().unwrap(), loaded.model.batch_norm().unwrap());
+    assert_eq!(saved.running_mean(), restored.running_mean());
+    assert_eq!(saved.running_var(), restored.running_var());
+    assert_eq!(loaded.predict(150.0, 7.0, 6.0, 6.0), result.predict(150.0, 7.0, 6.0, 6.0));
+}
@@ -0,0 +1,145 @@
+// loss.rs
+//
+// Class weights, focal loss and label smoothing: values, gradients against
+// finite differences, resolution from the config, and a training run.
+
+use fruit_classifier::config::{ClassWeights, ConfigError, LossConfig, LossFunction, TrainingConfig};
+use fruit_classifier::loss::Loss;
+use fruit_classifier::training::{self, TrainingError};
+use ndarray::{array, Array2, Axis};
+use std::collections::BTreeMap;
+
+mod common;
+
+fn softmax(logits: &Array2<f64>) -> Array2<f64> {
+    let exp = logits.mapv(f64::exp);
+    let sum = exp.sum_axis(Axis(1)).insert_axis(Axis(1));
+    exp / sum
+}
+
+fn logits() -> Array2<f64> {
+    array![[2.0, -1.0, 0.5], [0.1, 0.2, -0.3], [-1.5, 0.4, 3.0], [0.0, 1.0, 0.0]]
+}
+
+fn targets() -> Array2<f64> {
+    array![[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]]
+}
+
+#[test]
+fn default_loss_is_plain_cross_entropy() {
+    let (p, y) = (softmax(&logits()), targets());
+    let loss = Loss::default();
+    let expected = -(&y * &p.mapv(f64::ln)).sum() / 4.0;
+    assert!((loss.value(&p, &y) - expected).abs() < 1e-12);
+    assert_eq!(loss.gradient(&p, &y), &p - &y);
+}
+
+#[test]
+fn gradients_match_finite_differences() {
+    let losses = [
+        Loss::new(LossFunction::CrossEntropy, Some(array![1.0, 3.0, 0.5]), 0.1),
+        Loss::new(LossFunction::Focal { gamma: 2.0 }, None, 0.0),
+        Loss::new(LossFunction::Focal { gamma: 0.5 }, Some(array![2.0, 1.0, 1.0]), 0.2),
+    ];
+    let (z, y) = (logits(), targets());
+    let h = 1e-6;
+
+    for loss in &losses {
+        // `value` is a mean, the gradient is per sample
+        let analytic = loss.gradient(&softmax(&z), &y) / z.nrows() as f64;
+        for ((i, j), &a) in analytic.indexed_iter() {
+            let (mut plus, mut minus) = (z.clone(), z.clone());
+            plus[[i, j]] += h;
+            minus[[i, j]] -= h;
+            let numeric = (loss.value(&softmax(&plus), &y) - loss.value(&softmax(&minus), &y)) / (2.0 * h);
+            assert!((a - numeric).abs() < 1e-6, "{:?} at ({}, {}): {} vs {}", loss, i, j, a, numeric);
+        }
+    }
+}
+
+#[test]
+fn focal_loss_down_weights_confident_samples() {
+    let y = array![[1.0, 0.0]];
+    let confident = array![[0.95, 0.05]];
+    let unsure = array![[0.4, 0.6]];
+    let focal = Loss::new(LossFunction::Focal { gamma: 2.0 }, None, 0.0);
+    let plain = Loss::default();
+
+    let ratio = |p: &Array2<f64>| focal.value(p, &y) / plain.value(p, &y);
+    assert!(ratio(&confident) < 0.01);
+    assert!(ratio(&unsure) > 0.3);
+}
+
+#[test]
+fn class_weights_resolve_against_training_classes() {
+    let names: Vec<String> = ["apple", "grape", "unknown"].iter().map(|s| s.to_string()).collect();
+    let labels = array![[1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.0, 0.0, 1.0]];
+
+    let config = LossConfig {
+        class_weights: ClassWeights::Balanced,
+        ..LossConfig::default()
+    };
+    let balanced = Loss::from_config(&config, &names, &labels).unwrap();
+    let weights = balanced.class_weights().unwrap();
+    assert!((weights - &array![6.0 / 9.0, 2.0, 1.0]).iter().all(|d| d.abs() < 1e-12));
+
+    let config = LossConfig {
+        class_weights: ClassWeights::Manual {
+            weights: BTreeMap::from([("grape".to_string(), 4.0)]),
+        },
+        ..LossConfig::default()
+    };
+    let manual = Loss::from_config(&config, &names, &labels).unwrap();
+    assert_eq!(manual.class_weights().unwrap(), &array![1.0, 4.0, 1.0]);
+
+    let config = LossConfig {
+        class_weights: ClassWeights::Manual {
+            weights: BTreeMap::from([("banana".to_string(), 2.0)]),
+        },
+        ..LossConfig::default()
+    };
+    let err = Loss::from_config(&config, &names, &labels).unwrap_err();
+    assert!(err.to_string().contains("banana"), "{}", err);
+}
+
+#[test]
+fn config_rejects_invalid_loss_settings() {
+    assert!(matches!(
+        TrainingConfig::from_toml_str("[loss]\nlabel_smoothing = 1.0"),
+        Err(ConfigError::Invalid { field: "loss.label_smoothing", .. })
+    ));
+    assert!(matches!(
+        TrainingConfig::from_toml_str("[loss.function]\nkind = \"focal\"\ngamma = -1.0"),
+        Err(ConfigError::Invalid { field: "loss.function.gamma", .. })
+    ));
+}
+
+#[test]
+fn network_trains_with_weighted_focal_loss() {
+    let samples = common::samples();
+    let mut config = TrainingConfig::from_toml_str(
+        r#"
+        seed = 21
+        [optimizer]
+        learning_rate = 0.05
+        [schedule]
+        epochs = 60
+        [loss]
+        label_smoothing = 0.1
+        function = { kind = "focal", gamma = 2.0 }
+        class_weights = { kind = "balanced" }
+        "#,
+    )
+    .unwrap();
+    let result = training::run_training_from_samples(&samples, &config).unwrap();
+    assert!(result.final_accuracy > 0.9, "accuracy {}", result.final_accuracy);
+    assert!(result.model.loss().class_weights().is_some());
+
+    config.loss.class_weights = ClassWeights::Manual {
+        weights: BTreeMap::from([("kiwi".to_string(), 2.0)]),
+    };
+    assert!(matches!(
+        training::run_training_from_samples(&samples, &config),
+        Err(TrainingError::Config(_))
+    ));
+}
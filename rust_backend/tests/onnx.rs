@@ -0,0 +1,251 @@
+// onnx.rs
+//
+// Round trip of the ONNX export through a small protobuf decoder and a
+// reference evaluator for the operators the exporter emits, compared with
+// `NeuralNet::forward`.
+
+use fruit_classifier::onnx;
+use fruit_classifier::training::{self, TrainingResult};
+use fruit_classifier::utils::measurement_row;
+use ndarray::{Array2, ArrayD, Axis, Ix2, IxDyn};
+use std::collections::HashMap;
+
+mod common;
+
+// Fields of one protobuf message: number and either a varint or bytes
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+}
+
+fn read_varint(bytes: &[u8], pos: &mut usize) -> u64 {
+    let mut value = 0;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[*pos];
+        *pos += 1;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte < 0x80 {
+            return value;
+        }
+        shift += 7;
+    }
+}
+
+fn fields(bytes: &[u8]) -> Vec<(u64, Value<'_>)> {
+    let mut pos = 0;
+    let mut fields = Vec::new();
+    while pos < bytes.len() {
+        let key = read_varint(bytes, &mut pos);
+        let value = match key & 7 {
+            0 => Value::Varint(read_varint(bytes, &mut pos)),
+            2 => {
+                let len = read_varint(bytes, &mut pos) as usize;
+                pos += len;
+                Value::Bytes(&bytes[pos - len..pos])
+            }
+            other => panic!("Unexpected wire type {}", other),
+        };
+        fields.push((key >> 3, value));
+    }
+    fields
+}
+
+fn varints(bytes: &[u8], field: u64) -> Vec<i64> {
+    fields(bytes)
+        .into_iter()
+        .filter_map(|(f, v)| match v {
+            Value::Varint(x) if f == field => Some(x as i64),
+            _ => None,
+        })
+        .collect()
+}
+
+fn messages(bytes: &[u8], field: u64) -> Vec<&[u8]> {
+    fields(bytes)
+        .into_iter()
+        .filter_map(|(f, v)| match v {
+            Value::Bytes(b) if f == field => Some(b),
+            _ => None,
+        })
+        .collect()
+}
+
+fn strings(bytes: &[u8], field: u64) -> Vec<String> {
+    messages(bytes, field).into_iter().map(|b| String::from_utf8(b.to_vec()).unwrap()).collect()
+}
+
+fn string(bytes: &[u8], field: u64) -> String {
+    strings(bytes, field).pop().unwrap_or_default()
+}
+
+#[derive(Debug, Clone)]
+enum Tensor {
+    Float(ArrayD<f32>),
+    Int64(ArrayD<i64>),
+}
+
+impl Tensor {
+    fn float(&self) -> &ArrayD<f32> {
+        match self {
+            Tensor::Float(t) => t,
+            Tensor::Int64(_) => panic!("Expected a float tensor"),
+        }
+    }
+}
+
+struct Node {
+    op_type: String,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+    attributes: HashMap<String, i64>,
+}
+
+struct Model {
+    ir_version: i64,
+    opset: i64,
+    metadata: HashMap<String, String>,
+    nodes: Vec<Node>,
+    initializers: HashMap<String, Tensor>,
+    inputs: Vec<String>,
+    outputs: Vec<String>,
+}
+
+impl Model {
+    fn decode(bytes: &[u8]) -> Self {
+        let graph = messages(bytes, 7)[0];
+        let opset = messages(bytes, 8)[0];
+        assert_eq!(string(opset, 1), "");
+
+        let nodes = messages(graph, 1)
+            .into_iter()
+            .map(|node| Node {
+                op_type: string(node, 4),
+                inputs: strings(node, 1),
+                outputs: strings(node, 2),
+                attributes: messages(node, 5)
+                    .into_iter()
+                    .map(|attr| {
+                        assert_eq!(varints(attr, 20), [2], "only INT attributes are expected");
+                        (string(attr, 1), varints(attr, 3)[0])
+                    })
+                    .collect(),
+            })
+            .collect();
+        let initializers = messages(graph, 5)
+            .into_iter()
+            .map(|tensor| {
+                assert_eq!(varints(tensor, 2), [1], "initializers are float32");
+                let dims: Vec<usize> = varints(tensor, 1).into_iter().map(|d| d as usize).collect();
+                let raw = messages(tensor, 9)[0];
+                let values = raw.chunks_exact(4).map(|c| f32::from_le_bytes(c.try_into().unwrap())).collect();
+                (string(tensor, 8), Tensor::Float(ArrayD::from_shape_vec(IxDyn(&dims), values).unwrap()))
+            })
+            .collect();
+
+        Model {
+            ir_version: varints(bytes, 1)[0],
+            opset: varints(opset, 2)[0],
+            metadata: messages(bytes, 14).into_iter().map(|e| (string(e, 1), string(e, 2))).collect(),
+            nodes,
+            initializers,
+            inputs: messages(graph, 11).into_iter().map(|v| string(v, 1)).collect(),
+            outputs: messages(graph, 12).into_iter().map(|v| string(v, 1)).collect(),
+        }
+    }
+
+    // Runs the nodes in order, which ONNX requires to be topological
+    fn run(&self, input: Array2<f32>) -> HashMap<String, Tensor> {
+        let mut values = self.initializers.clone();
+        values.insert(self.inputs[0].clone(), Tensor::Float(input.into_dyn()));
+        for node in &self.nodes {
+            let arg = |i: usize| values[&node.inputs[i]].float().clone();
+            let output = match node.op_type.as_str() {
+                "Sub" => Tensor::Float(&arg(0) - &arg(1)),
+                "Div" => Tensor::Float(&arg(0) / &arg(1)),
+                "Add" => Tensor::Float(&arg(0) + &arg(1)),
+                "MatMul" => {
+                    let (a, b) = (arg(0).into_dimensionality::<Ix2>().unwrap(), arg(1).into_dimensionality::<Ix2>().unwrap());
+                    Tensor::Float(a.dot(&b).into_dyn())
+                }
+                "Relu" => Tensor::Float(arg(0).mapv(|v| v.max(0.0))),
+                "Softmax" => {
+                    let axis = Axis(node.attributes["axis"] as usize);
+                    let x = arg(0);
+                    let max = x.map_axis(axis, |row| row.fold(f32::NEG_INFINITY, |m, &v| m.max(v)));
+                    let exp = (&x - &max.insert_axis(axis)).mapv(f32::exp);
+                    let sum = exp.sum_axis(axis).insert_axis(axis);
+                    Tensor::Float(exp / sum)
+                }
+                "ArgMax" => {
+                    assert_eq!(node.attributes["keepdims"], 0);
+                    let axis = Axis(node.attributes["axis"] as usize);
+                    Tensor::Int64(arg(0).map_axis(axis, |row| {
+                        // First maximum wins, as with select_last_index = 0
+                        row.iter().enumerate().fold((0, f32::NEG_INFINITY), |best, (i, &v)| if v > best.1 { (i, v) } else { best }).0 as i64
+                    }))
+                }
+                other => panic!("Unsupported operator {}", other),
+            };
+            values.insert(node.outputs[0].clone(), output);
+        }
+        values
+    }
+}
+
+fn trained(batch_norm: bool) -> (TrainingResult, Array2<f64>) {
+    let samples = common::samples();
+    let mut config = common::config(9);
+    config.model.batch_norm = batch_norm;
+    let result = training::run_training_from_samples(&samples, &config).unwrap();
+
+    let mut raw = Array2::zeros((0, 4));
+    for sample in samples.iter().step_by(7) {
+        raw.push_row(measurement_row(sample.weight, sample.size, sample.width, sample.height).row(0)).unwrap();
+    }
+    (result, raw)
+}
+
+#[test]
+fn export_describes_the_graph_and_classes() {
+    let (result, _) = trained(false);
+    let model = Model::decode(&onnx::encode_model(&result).unwrap());
+
+    assert_eq!((model.ir_version, model.opset), (7, 13));
+    assert_eq!(model.inputs, ["measurements"]);
+    assert_eq!(model.outputs, ["probabilities", "class"]);
+    let ops: Vec<&str> = model.nodes.iter().map(|n| n.op_type.as_str()).collect();
+    assert_eq!(ops, ["Sub", "Div", "MatMul", "Add", "Relu", "MatMul", "Add", "Softmax", "ArgMax"]);
+
+    let labels: Vec<String> = serde_json::from_str(&model.metadata["class_labels"]).unwrap();
+    assert_eq!(labels, result.class_names);
+    assert_eq!(model.initializers["mean"].float().len(), 4);
+}
+
+#[test]
+fn evaluated_export_reproduces_forward() {
+    for batch_norm in [false, true] {
+        let (result, raw) = trained(batch_norm);
+        let path = std::path::PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("model.onnx");
+        onnx::export_onnx(&result, &path).unwrap();
+        let model = Model::decode(&std::fs::read(&path).unwrap());
+
+        let outputs = model.run(raw.mapv(|v| v as f32));
+        let probabilities = outputs["probabilities"].float();
+        let mut normalized = raw.clone();
+        result.normalizer.normalize(&mut normalized);
+        let (_, _, expected) = result.model.forward(&normalized);
+        assert_eq!(probabilities.shape(), expected.shape());
+        for (p, e) in probabilities.iter().zip(expected.iter()) {
+            assert!((*p as f64 - e).abs() < 1e-4, "{} vs {} (batch norm: {})", p, e, batch_norm);
+        }
+
+        let Tensor::Int64(classes) = &outputs["class"] else {
+            panic!("class output is not int64");
+        };
+        for (row, &class) in expected.rows().into_iter().zip(classes.iter()) {
+            let best = row.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            assert!(best - row[class as usize] < 1e-4);
+        }
+    }
+}
@@ -0,0 +1,99 @@
+// precision.rs
+//
+// Training and inference in f32 against f64 on the fruit dataset, and
+// conversions between the two precisions.
+
+use fruit_classifier::classifier::{argmax_rows, Classifier};
+use fruit_classifier::config::TrainingConfig;
+use fruit_classifier::data::FruitSample;
+use fruit_classifier::precision::Precision;
+use fruit_classifier::training::{self, TrainingResult};
+use ndarray::Array2;
+use std::path::PathBuf;
+
+mod common;
+
+// Normalized features of every sample, as the f64 model sees them
+fn features(result: &TrainingResult, samples: &[FruitSample]) -> Array2<f64> {
+    result.normalizer.transform(samples)
+}
+
+#[test]
+fn f32_training_stays_close_to_f64() {
+    let samples = common::samples();
+    for batch_norm in [false, true] {
+        let mut config = common::config(17);
+        config.model.batch_norm = batch_norm;
+        let double = training::run_training_as::<f64, _>(&samples, &config, |_| true).unwrap();
+        let single = training::run_training_as::<f32, _>(&samples, &config, |_| true).unwrap();
+        assert_eq!(single.history.len(), double.history.len());
+
+        assert!(double.final_accuracy > 0.9, "{}", double.final_accuracy);
+        let difference = (single.final_accuracy - double.final_accuracy).abs();
+        assert!(difference <= 0.02, "batch_norm {}: f32 {} vs f64 {}", batch_norm, single.final_accuracy, double.final_accuracy);
+
+        let x = features(&double, &samples);
+        let (a, b) = (argmax_rows(&double.model.predict_proba(&x)), argmax_rows(&single.model.predict_proba(&x)));
+        let agreement = a.iter().zip(&b).filter(|(a, b)| a == b).count() as f64 / samples.len() as f64;
+        assert!(agreement > 0.97, "batch_norm {}: agreement {}", batch_norm, agreement);
+    }
+}
+
+#[test]
+fn config_precision_selects_the_training_path() {
+    let samples = common::samples();
+    let mut config = common::config(17);
+    let default = training::run_training_from_samples(&samples, &config).unwrap();
+    let double = training::run_training_as::<f64, _>(&samples, &config, |_| true).unwrap();
+    assert_eq!(default.final_accuracy, double.final_accuracy);
+    assert_eq!(default.model.losses, double.model.losses);
+
+    config.model.precision = Precision::F32;
+    let configured = training::run_training_from_samples(&samples, &config).unwrap();
+    let single = training::run_training_as::<f32, _>(&samples, &config, |_| true).unwrap();
+    assert_eq!(configured.config.model.precision, Precision::F32);
+    assert_eq!(configured.final_accuracy, single.final_accuracy);
+    assert_eq!(configured.model.losses, single.model.losses);
+
+    let config = TrainingConfig::from_toml_str("[model]\nprecision = \"f32\"").unwrap();
+    assert_eq!(config.model.precision, Precision::F32);
+    assert!(TrainingConfig::from_toml_str("[model]\nprecision = \"f16\"").is_err());
+}
+
+#[test]
+fn casts_preserve_predictions() {
+    let samples = common::samples();
+    let mut config = common::config(17);
+    config.model.batch_norm = true;
+    let double = training::run_training_from_samples(&samples, &config).unwrap();
+    let single = double.cast::<f32>();
+
+    // Rounding to f32 barely moves the probabilities
+    let x = features(&double, &samples);
+    let (p64, p32) = (double.model.predict_proba(&x), single.model.predict_proba(&x));
+    let error = (&p64 - &p32).fold(0.0f64, |m, d| m.max(d.abs()));
+    assert!(error < 1e-5, "{}", error);
+
+    // f32 -> f64 -> f32 is exact
+    let back = single.cast::<f64>().cast::<f32>();
+    assert_eq!(back.normalizer.mean, single.normalizer.mean);
+    assert_eq!(back.model.predict_proba(&x), p32);
+    for sample in &samples {
+        let m = [sample.weight, sample.size, sample.width, sample.height];
+        assert_eq!(back.predict(m[0], m[1], m[2], m[3]), single.predict(m[0], m[1], m[2], m[3]));
+    }
+}
+
+#[test]
+fn f32_models_round_trip_through_json() {
+    let samples = common::samples();
+    let single = training::run_training_as::<f32, _>(&samples, &common::config(17), |_| true).unwrap();
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("f32_model.json");
+    single.save(&path).unwrap();
+
+    let loaded = TrainingResult::load(&path).unwrap().cast::<f32>();
+    assert_eq!(loaded.config.model.precision, Precision::F32);
+    assert_eq!(loaded.normalizer.std, single.normalizer.std);
+    let x = features(&loaded.cast(), &samples);
+    assert_eq!(loaded.model.predict_proba(&x), single.model.predict_proba(&x));
+}
@@ -0,0 +1,118 @@
+// quantization.rs
+//
+// Int8 post-training quantization: the affine mapping, every granularity
+// and scheme on a trained network, and saving next to the float model.
+
+use fruit_classifier::config::{ConfigError, Granularity, QuantScheme, QuantizationConfig, TrainingConfig};
+use fruit_classifier::model::DenseLayer;
+use fruit_classifier::quantization::{self, QuantParams, QuantizedLayer, QuantizedModel};
+use fruit_classifier::training::TrainingResult;
+use ndarray::{array, Array1};
+use std::path::PathBuf;
+
+mod common;
+
+#[test]
+fn params_cover_the_range_and_keep_zero_exact() {
+    let symmetric = QuantParams::from_range(-0.5, 2.54, QuantScheme::Symmetric);
+    assert_eq!((symmetric.scale, symmetric.zero_point), (0.02, 0));
+    assert_eq!((symmetric.quantize(2.54), symmetric.quantize(-2.54), symmetric.quantize(100.0)), (127, -127, 127));
+    assert!((symmetric.dequantize(symmetric.quantize(1.234)) - 1.234).abs() <= symmetric.scale / 2.0);
+
+    // ReLU outputs use all 256 values
+    let asymmetric = QuantParams::from_range(0.0, 5.1, QuantScheme::Asymmetric);
+    assert_eq!(asymmetric.zero_point, -128);
+    assert_eq!((asymmetric.quantize(0.0), asymmetric.quantize(5.1)), (-128, 127));
+    assert_eq!(asymmetric.dequantize(-128), 0.0);
+
+    let shifted = QuantParams::from_range(-1.0, 3.0, QuantScheme::Asymmetric);
+    assert_eq!(shifted.dequantize(shifted.quantize(0.0)), 0.0);
+    assert!((shifted.dequantize(shifted.quantize(-1.0)) + 1.0).abs() <= shifted.scale / 2.0);
+
+    assert_eq!(QuantParams::from_range(0.0, 0.0, QuantScheme::Symmetric).scale, 1.0);
+}
+
+#[test]
+fn dead_units_keep_their_bias_without_overflowing() {
+    // A live unit, a dead one and one with vanishing weights, whose
+    // per-channel scale is so small that its bias has to saturate
+    let layer = DenseLayer {
+        weights: array![[0.5, 0.0, 1e-20], [-0.25, 0.0, -1e-20], [1.0, 0.0, 1e-20]],
+        bias: array![0.1, 2.0, 1.0],
+    };
+    let input = QuantParams::from_range(-3.0, 3.0, QuantScheme::Symmetric);
+    for scheme in [QuantScheme::Symmetric, QuantScheme::Asymmetric] {
+        let settings = QuantizationConfig {
+            granularity: Granularity::PerChannel,
+            scheme,
+            ..QuantizationConfig::default()
+        };
+        let quantized = QuantizedLayer::new(&layer, input, &settings);
+        assert!(quantized.bias[2] < i32::MAX && quantized.bias[2] > 0, "{:?}", quantized.bias);
+
+        let x = array![3.0, -3.0, 3.0];
+        let q: Array1<i8> = x.mapv(|v| input.quantize(v));
+        let output = quantized.forward(q.view(), input);
+        assert!(output.iter().all(|v| v.is_finite()), "{:?}", output);
+        assert!((output[0] - (x.dot(&layer.weights.column(0)) + 0.1)).abs() < 0.05, "{:?}", output);
+        assert!((output[1] - 2.0).abs() <= input.scale, "{:?}", output);
+    }
+}
+
+#[test]
+fn every_scheme_stays_close_to_the_float_network() {
+    let samples = common::samples();
+    let mut config = common::config(21);
+    config.model.batch_norm = true;
+    // The whole training split, so no validation input saturates far
+    // outside the calibrated ranges
+    config.quantization.calibration_samples = samples.len();
+    for granularity in [Granularity::PerTensor, Granularity::PerChannel] {
+        for scheme in [QuantScheme::Symmetric, QuantScheme::Asymmetric] {
+            config.quantization.granularity = granularity;
+            config.quantization.scheme = scheme;
+            let model = quantization::network_quantization(&samples, &config).unwrap();
+            let report = model.report;
+            let (hidden, output) = (&model.quantized.hidden, &model.quantized.output);
+            let channels = if granularity == Granularity::PerChannel { 16 } else { 1 };
+            assert_eq!(hidden.weight_params.len(), channels);
+            if scheme == QuantScheme::Symmetric {
+                assert!(hidden.weight_params.iter().chain(&output.weight_params).all(|p| p.zero_point == 0));
+            }
+
+            assert!(report.float_accuracy > 0.9, "{:?}", report);
+            assert!(report.accuracy_drop < 0.02, "{:?} {:?}: {:?}", granularity, scheme, report);
+            assert!(report.agreement > 0.97, "{:?} {:?}: {:?}", granularity, scheme, report);
+            assert!(report.max_probability_error < 0.1, "{:?} {:?}: {:?}", granularity, scheme, report);
+        }
+    }
+}
+
+#[test]
+fn quantized_model_is_saved_alongside_the_float_one() {
+    let samples = common::samples();
+    let model = quantization::network_quantization(&samples, &common::config(21)).unwrap();
+    assert!(model.report.accuracy_drop < 0.02 && model.report.agreement > 0.97, "{:?}", model.report);
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("quantized_model.json");
+    model.save(&path).unwrap();
+
+    let loaded = QuantizedModel::load(&path).unwrap();
+    assert_eq!(loaded.quantized, model.quantized);
+    assert_eq!(loaded.report, model.report);
+    let float = TrainingResult::load(&path).unwrap();
+    for m in [[150.0, 7.0, 6.0, 6.0], [5.0, 2.0, 1.5, 1.5], [5000.0, 30.0, 25.0, 25.0]] {
+        assert_eq!(loaded.predict(m[0], m[1], m[2], m[3]), model.predict(m[0], m[1], m[2], m[3]));
+        assert_eq!(float.predict(m[0], m[1], m[2], m[3]), model.float.predict(m[0], m[1], m[2], m[3]));
+    }
+}
+
+#[test]
+fn calibration_needs_samples() {
+    match TrainingConfig::from_toml_str("[quantization]\ncalibration_samples = 0") {
+        Err(ConfigError::Invalid { field, .. }) => assert_eq!(field, "quantization.calibration_samples"),
+        other => panic!("Expected an invalid calibration size, got {:?}", other),
+    }
+    let config = TrainingConfig::from_toml_str("[quantization]\ngranularity = \"per_tensor\"\nscheme = \"asymmetric\"").unwrap();
+    assert_eq!(config.quantization.granularity, Granularity::PerTensor);
+    assert_eq!(config.quantization.scheme, QuantScheme::Asymmetric);
+}
@@ -0,0 +1,74 @@
+// trees.rs
+//
+// Random forest and gradient-boosted trees on the real dataset: accuracy,
+// feature importances, reproducibility and persistence.
+
+use fruit_classifier::boosting::{self, GradientBoosting};
+use fruit_classifier::classifier::{Classifier, TrainedModel};
+use fruit_classifier::config::TrainingConfig;
+use fruit_classifier::forest::{self, RandomForest};
+use std::path::PathBuf;
+
+mod common;
+
+fn config() -> TrainingConfig {
+    let mut config = common::config(5);
+    config.forest.n_trees = 20;
+    config.boosting.n_rounds = 30;
+    config
+}
+
+fn assert_importances(importances: &[f64]) {
+    assert_eq!(importances.len(), 4);
+    assert!(importances.iter().all(|&v| v >= 0.0), "{:?}", importances);
+    assert!((importances.iter().sum::<f64>() - 1.0).abs() < 1e-9, "{:?}", importances);
+}
+
+#[test]
+fn random_forest_learns_the_dataset() {
+    let trained = forest::train_forest(&common::samples(), &config()).unwrap();
+    assert_eq!(trained.model.trees().len(), 20);
+    assert!(trained.final_accuracy > 0.9, "accuracy {}", trained.final_accuracy);
+    assert_importances(&trained.model.feature_importances());
+}
+
+#[test]
+fn gradient_boosting_learns_the_dataset() {
+    let trained = boosting::train_boosting(&common::samples(), &config()).unwrap();
+    assert_eq!(trained.model.n_rounds(), 30);
+    assert!(trained.final_accuracy > 0.9, "accuracy {}", trained.final_accuracy);
+    assert_importances(&trained.model.feature_importances());
+
+    let mut input = fruit_classifier::utils::measurement_row(150.0, 7.0, 6.0, 6.0);
+    trained.normalizer.normalize(&mut input);
+    assert!((trained.model.predict_proba(&input).sum() - 1.0).abs() < 1e-9);
+}
+
+#[test]
+fn training_is_reproducible_with_a_seed() {
+    let mut config = config();
+    config.boosting.subsample = 0.5;
+    let (a, b) = (forest::train_forest(&common::samples(), &config).unwrap(), forest::train_forest(&common::samples(), &config).unwrap());
+    assert_eq!(a.model.feature_importances(), b.model.feature_importances());
+
+    let (a, b) = (boosting::train_boosting(&common::samples(), &config).unwrap(), boosting::train_boosting(&common::samples(), &config).unwrap());
+    assert_eq!(a.model.feature_importances(), b.model.feature_importances());
+    assert_eq!(a.predict(150.0, 7.0, 6.0, 6.0), b.predict(150.0, 7.0, 6.0, 6.0));
+}
+
+#[test]
+fn models_round_trip_through_json() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let samples = common::samples();
+
+    let trained = forest::train_forest(&samples, &config()).unwrap();
+    trained.save(dir.join("forest.json")).unwrap();
+    let loaded = TrainedModel::<RandomForest>::load(dir.join("forest.json")).unwrap();
+    assert_eq!(loaded.config, trained.config);
+    assert_eq!(loaded.predict(500.0, 10.0, 10.0, 10.0), trained.predict(500.0, 10.0, 10.0, 10.0));
+
+    let trained = boosting::train_boosting(&samples, &config()).unwrap();
+    trained.save(dir.join("boosting.json")).unwrap();
+    let loaded = TrainedModel::<GradientBoosting>::load(dir.join("boosting.json")).unwrap();
+    assert_eq!(loaded.predict(5.0, 1.5, 1.5, 1.5), trained.predict(5.0, 1.5, 1.5, 1.5));
+}
@@ -0,0 +1,139 @@
+// tuning.rs
+//
+// Search strategies on a small, fixed budget, and the leaderboard they
+// produce.
+
+use fruit_classifier::config::ConfigError;
+use fruit_classifier::training::TrainingError;
+use fruit_classifier::tuning::{self, Evaluation, ParamSpace, SearchStrategy, TuningConfig};
+use std::path::PathBuf;
+
+mod common;
+
+fn manifest_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn small_search(strategy: SearchStrategy) -> TuningConfig {
+    let mut tuning = TuningConfig {
+        seed: Some(3),
+        strategy,
+        ..TuningConfig::default()
+    };
+    tuning.base.seed = Some(3);
+    tuning.base.schedule.epochs = 9;
+    tuning.space.hidden_size = Some(ParamSpace::Values(vec![4.0, 8.0]));
+    tuning.space.learning_rate = Some(ParamSpace::Values(vec![0.01, 0.05]));
+    tuning
+}
+
+#[test]
+fn example_tuning_config_is_valid() {
+    let tuning = TuningConfig::from_file(manifest_dir().join("config").join("tuning.toml")).unwrap();
+    assert_eq!(tuning.evaluation, Evaluation::CrossValidation { folds: 5 });
+}
+
+#[test]
+fn grid_search_tries_every_combination() {
+    let samples = common::samples();
+    let search = tuning::search_samples(&samples, &small_search(SearchStrategy::Grid), |_| {}).unwrap();
+
+    assert_eq!(search.trials.len(), 4);
+    let mut seen: Vec<_> = search
+        .trials
+        .iter()
+        .map(|t| (t.params.hidden_size, t.params.learning_rate))
+        .collect();
+    seen.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(seen, vec![(4, 0.01), (4, 0.05), (8, 0.01), (8, 0.05)]);
+
+    // Best first
+    assert!(search.trials.windows(2).all(|w| w[0].score >= w[1].score));
+    let best = search.best_config().unwrap();
+    assert_eq!(best.model.hidden_size, search.trials[0].params.hidden_size);
+    assert_eq!(best.schedule.epochs, 9);
+}
+
+#[test]
+fn successive_halving_promotes_survivors() {
+    let samples = common::samples();
+    let mut tuning = small_search(SearchStrategy::SuccessiveHalving {
+        trials: 9,
+        min_epochs: 1,
+        eta: 3,
+    });
+    tuning.evaluation = Evaluation::CrossValidation { folds: 3 };
+    let search = tuning::search_samples(&samples, &tuning, |_| {}).unwrap();
+
+    let at = |epochs| search.trials.iter().filter(|t| t.epochs == epochs).count();
+    assert_eq!((at(1), at(3), at(9)), (9, 3, 1));
+    assert_eq!(search.best().unwrap().epochs, 9);
+    assert!(search.trials.iter().all(|t| t.score_std >= 0.0));
+}
+
+#[test]
+fn hyperband_runs_every_bracket() {
+    let samples = common::samples();
+    let search = tuning::search_samples(
+        &samples,
+        &small_search(SearchStrategy::Hyperband { min_epochs: 3, eta: 3 }),
+        |_| {},
+    )
+    .unwrap();
+
+    // A bracket of 3 candidates from 3 epochs, whose survivor gets 9, and a
+    // bracket of 2 candidates at 9 epochs
+    let mut candidates: Vec<_> = search.trials.iter().map(|t| t.candidate).collect();
+    candidates.sort();
+    candidates.dedup();
+    assert_eq!(candidates.len(), 5);
+    assert_eq!(search.trials.len(), 3 + 1 + 2);
+}
+
+#[test]
+fn huge_eta_leaves_a_single_full_budget_bracket() {
+    let samples = common::samples();
+    let search = tuning::search_samples(
+        &samples,
+        &small_search(SearchStrategy::Hyperband { min_epochs: 3, eta: usize::MAX / 2 }),
+        |_| {},
+    )
+    .unwrap();
+    assert_eq!(search.trials.len(), 1);
+    assert_eq!(search.trials[0].epochs, 9);
+}
+
+#[test]
+fn leaderboard_has_a_row_per_trial() {
+    let samples = common::samples();
+    let search = tuning::search_samples(
+        &samples,
+        &small_search(SearchStrategy::Random { trials: 3 }),
+        |_| {},
+    )
+    .unwrap();
+
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("leaderboard.csv");
+    search.write_leaderboard(&path).unwrap();
+    let text = std::fs::read_to_string(&path).unwrap();
+    let mut lines = text.lines();
+    assert_eq!(
+        lines.next().unwrap(),
+        "rank,candidate,epochs,hidden_size,learning_rate,batch_size,momentum,l2,score,score_std,seconds,error"
+    );
+    assert_eq!(lines.count(), 3);
+}
+
+#[test]
+fn grid_rejects_ranges() {
+    let err = TuningConfig::from_toml_str("[space]\nlearning_rate = { min = 0.001, max = 0.1 }").unwrap_err();
+    assert!(matches!(err, ConfigError::Invalid { field: "space.learning_rate", .. }), "{:?}", err);
+}
+
+#[test]
+fn a_search_where_every_trial_fails_is_a_training_error() {
+    let samples = common::samples();
+    // Too few samples for a validation split
+    let err = tuning::search_samples(&samples[..1], &small_search(SearchStrategy::Grid), |_| {}).unwrap_err();
+    assert!(matches!(err, TrainingError::TrialsFailed { trials: 4, .. }), "{:?}", err);
+}